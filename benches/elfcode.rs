@@ -0,0 +1,121 @@
+// Compares four ways to run the day 19 program:
+//
+//  - lookup: how days 19 and 21 used to do it, rebuilding a map from
+//    mnemonics to functions and copying the registers on every step,
+//  - machine: `Machine::step`, which decodes the operands on every step,
+//  - compiled: the pre-decoded `Compiled` program,
+//  - transpiled: the program turned into Rust by `elfcode::transpiler`,
+//    only with the `transpiled` feature.
+//
+//     cargo bench --bench elfcode --features transpiled
+extern crate y2018;
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use y2018::bench::Stats;
+#[cfg(feature = "transpiled")]
+use y2018::day19;
+use y2018::elfcode::{self, Compiled, Machine, Program};
+
+const RUNS: usize = 5;
+// The lookup interpreter is too slow for the whole program.
+const LOOKUP_CYCLES: u64 = 1_000_000;
+
+type Registers = [i128; 6];
+type Operation = fn(&Registers, i128, i128) -> i128;
+
+fn op_map() -> BTreeMap<&'static str, Operation> {
+  let mut map: BTreeMap<&'static str, Operation> = BTreeMap::new();
+  map.insert("addr", |r, a, b| r[a as usize] + r[b as usize]);
+  map.insert("addi", |r, a, b| r[a as usize] + b);
+  map.insert("mulr", |r, a, b| r[a as usize] * r[b as usize]);
+  map.insert("muli", |r, a, b| r[a as usize] * b);
+  map.insert("banr", |r, a, b| r[a as usize] & r[b as usize]);
+  map.insert("bani", |r, a, b| r[a as usize] & b);
+  map.insert("borr", |r, a, b| r[a as usize] | r[b as usize]);
+  map.insert("bori", |r, a, b| r[a as usize] | b);
+  map.insert("setr", |r, a, _| r[a as usize]);
+  map.insert("seti", |_, a, _| a);
+  map.insert("gtir", |r, a, b| (a > r[b as usize]) as i128);
+  map.insert("gtri", |r, a, b| (r[a as usize] > b) as i128);
+  map.insert("gtrr", |r, a, b| (r[a as usize] > r[b as usize]) as i128);
+  map.insert("eqir", |r, a, b| (a == r[b as usize]) as i128);
+  map.insert("eqri", |r, a, b| (r[a as usize] == b) as i128);
+  map.insert("eqrr", |r, a, b| (r[a as usize] == r[b as usize]) as i128);
+  map
+}
+
+fn lookup(program: &[(String, [i128; 3])], ip_register: usize, max_cycles: u64) -> u64 {
+  let mut registers: Registers = [0; 6];
+  let mut cycles = 0;
+
+  while cycles < max_cycles {
+    let ip = registers[ip_register];
+    let (name, [a, b, c]) = match program.get(ip as usize) {
+      Some(v) if ip >= 0 => v,
+      _ => break,
+    };
+
+    let map = op_map();
+    let before = registers;
+    registers[*c as usize] = map[name.as_str()](&before, *a, *b);
+    registers[ip_register] += 1;
+    cycles += 1;
+  }
+
+  cycles
+}
+
+fn time<F: FnMut() -> u64>(mut f: F) -> (Duration, u64) {
+  let mut cycles = 0;
+  let samples = (0..RUNS)
+    .map(|_| {
+      let start = Instant::now();
+      cycles = f();
+      start.elapsed()
+    })
+    .collect::<Vec<Duration>>();
+
+  (Stats::from_samples(&samples).unwrap().median, cycles)
+}
+
+fn report(name: &str, (median, cycles): (Duration, u64), baseline: Option<f64>) -> f64 {
+  let per_cycle = median.as_secs_f64() * 1e9 / cycles as f64;
+  let speedup = baseline.map_or(String::new(), |b| format!("{:>8.1}x", b / per_cycle));
+  println!("{:<10} {:>10} cycles  {:>10.3} ms  {:>8.2} ns/cycle  {}",
+    name, cycles, median.as_secs_f64() * 1000.0, per_cycle, speedup);
+
+  per_cycle
+}
+
+fn main() {
+  let input = include_str!("../src/day19/data/input.txt");
+  let program: Program<i128> = elfcode::parse(input, 6).unwrap();
+  let compiled = Compiled::new(&program, 6).unwrap();
+  let ip_register = program.ip_register.unwrap();
+  let named = program.instructions
+    .iter()
+    .map(|i| (i.opcode.to_string(), [i.a, i.b, i.c]))
+    .collect::<Vec<(String, [i128; 3])>>();
+
+  let baseline = report("lookup", time(|| lookup(&named, ip_register, LOOKUP_CYCLES)), None);
+
+  report("machine", time(|| {
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    machine.run(&program).unwrap();
+    machine.cycles
+  }), Some(baseline));
+
+  report("compiled", time(|| {
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    compiled.run(&mut machine).unwrap();
+    machine.cycles
+  }), Some(baseline));
+
+  #[cfg(feature = "transpiled")]
+  report("transpiled", time(|| {
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    day19::transpiled::run(&mut machine, None).unwrap();
+    machine.cycles
+  }), Some(baseline));
+}
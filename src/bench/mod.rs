@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use super::utils::{describe, parse_number, preprocess_input, Error};
+use super::solution::Puzzle;
+
+// Medians that are more than this many percent slower than the baseline
+// are reported as regressions.
+const REGRESSION_THRESHOLD: f64 = 10.0;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Phase {
+  Parse,
+  Part(u8),
+}
+
+impl fmt::Display for Phase {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Phase::Parse => write!(f, "parse"),
+      Phase::Part(n) => write!(f, "part{}", n),
+    }
+  }
+}
+
+impl FromStr for Phase {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Phase, Error> {
+    match s {
+      "parse" => Ok(Phase::Parse),
+      "part1" => Ok(Phase::Part(1)),
+      "part2" => Ok(Phase::Part(2)),
+      _ => Err(Error::new(&format!("Unknown phase: {}", s))),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Stats {
+  pub min: Duration,
+  pub median: Duration,
+  pub max: Duration,
+}
+
+impl Stats {
+  pub fn from_samples(samples: &[Duration]) -> Option<Stats> {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+
+    let min = *sorted.first()?;
+    let max = *sorted.last()?;
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) {
+      (sorted[mid - 1] + sorted[mid]) / 2
+    } else {
+      sorted[mid]
+    };
+
+    Some(Stats { min, median, max })
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct Timing {
+  pub day: u8,
+  pub phase: Phase,
+  pub stats: Stats,
+}
+
+fn timed<T, F>(f: F) -> (T, Duration)
+where F: FnOnce() -> T {
+  let start = Instant::now();
+  let result = f();
+  (result, start.elapsed())
+}
+
+pub fn measure(puzzle: &dyn Puzzle, raw: &str, parts: &[u8], runs: usize) -> Result<Vec<Timing>, Error> {
+  let day = puzzle.day();
+  let mut samples: BTreeMap<Phase, Vec<Duration>> = BTreeMap::new();
+
+  for _ in 0..runs {
+    let (input, elapsed) = timed(|| puzzle.parse(raw));
+    let input = match input {
+      Ok(v) => v,
+      Err(err) => Err(Error::new(&format!("Unable to parse input: {}", describe(&err))))?
+    };
+    samples.entry(Phase::Parse).or_default().push(elapsed);
+
+    for &part in parts {
+      let (result, elapsed) = timed(|| puzzle.solve(input.as_ref(), part));
+      if let Err(err) = result {
+        Err(Error::new(&format!("Part {} failed: {}", part, describe(&err))))?
+      }
+      samples.entry(Phase::Part(part)).or_default().push(elapsed);
+    }
+  }
+
+  let timings = samples
+    .into_iter()
+    .filter_map(|(phase, v)| Stats::from_samples(&v).map(|stats| Timing { day, phase, stats }))
+    .collect();
+
+  Ok(timings)
+}
+
+// Baselines are stored as one line per day and phase with the
+// min, median and max times in nanoseconds:
+//
+//   <day> <phase> <min> <median> <max>
+pub type Baseline = BTreeMap<(u8, Phase), Stats>;
+
+fn parse_baseline_line(line: &str) -> Result<((u8, Phase), Stats), Error> {
+  let fields: Vec<&str> = line.split_whitespace().collect();
+  if fields.len() != 5 {
+    Err(Error::new(&format!("Invalid baseline entry: {}", line)))?
+  }
+
+  let day = parse_number::<u8>(fields[0])?;
+  let phase = fields[1].parse::<Phase>()?;
+  let nanos = fields[2..]
+    .iter()
+    .map(|v| parse_number::<u64>(v).map(Duration::from_nanos))
+    .collect::<Result<Vec<Duration>, _>>()?;
+
+  Ok(((day, phase), Stats { min: nanos[0], median: nanos[1], max: nanos[2] }))
+}
+
+pub fn parse_baseline(input: &str) -> Result<Baseline, Error> {
+  preprocess_input(input)
+    .into_iter()
+    .filter(|line| !line.starts_with('#'))
+    .map(parse_baseline_line)
+    .collect()
+}
+
+pub fn update_baseline(baseline: &mut Baseline, timings: &[Timing]) {
+  for timing in timings {
+    baseline.insert((timing.day, timing.phase), timing.stats);
+  }
+}
+
+pub fn format_baseline(baseline: &Baseline) -> String {
+  let mut out = String::from("# day phase min median max (nanoseconds)\n");
+  for ((day, phase), stats) in baseline {
+    out.push_str(&format!("{} {} {} {} {}\n", day, phase,
+      stats.min.as_nanos(), stats.median.as_nanos(), stats.max.as_nanos()));
+  }
+
+  out
+}
+
+fn milliseconds(d: Duration) -> String {
+  format!("{:.3} ms", d.as_secs_f64() * 1000.0)
+}
+
+// Relative change of the median compared to the baseline in percent.
+pub fn change(stats: &Stats, baseline: &Stats) -> Option<f64> {
+  let base = baseline.median.as_secs_f64();
+  if base == 0.0 {
+    return None;
+  }
+
+  Some((stats.median.as_secs_f64() - base) / base * 100.0)
+}
+
+pub fn header() -> String {
+  format!("{:>3}  {:<6}  {:>12}  {:>12}  {:>12}  {:>12}  Change", "Day", "Phase", "Min", "Median", "Max", "Baseline")
+}
+
+pub fn format_timing(timing: &Timing, baseline: Option<&Stats>) -> String {
+  let stats = &timing.stats;
+  let row = format!("{:>3}  {:<6}  {:>12}  {:>12}  {:>12}", timing.day, timing.phase.to_string(),
+    milliseconds(stats.min), milliseconds(stats.median), milliseconds(stats.max));
+
+  let base = match baseline {
+    Some(v) => v,
+    None => return row,
+  };
+
+  let change = match change(stats, base) {
+    Some(c) if c > REGRESSION_THRESHOLD => format!("{:+.1}% (regression)", c),
+    Some(c) => format!("{:+.1}%", c),
+    None => "-".to_string(),
+  };
+
+  format!("{}  {:>12}  {}", row, milliseconds(base.median), change)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::solution;
+
+  fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+  }
+
+  #[test]
+  fn stats_of_odd_and_even_number_of_samples() {
+    assert_eq!(Stats::from_samples(&[ms(5), ms(1), ms(3)]).unwrap(), Stats { min: ms(1), median: ms(3), max: ms(5) });
+    assert_eq!(Stats::from_samples(&[ms(4), ms(1), ms(2), ms(8)]).unwrap(), Stats { min: ms(1), median: ms(3), max: ms(8) });
+    assert_eq!(Stats::from_samples(&[]), None);
+  }
+
+  #[test]
+  fn baseline_roundtrip() {
+    let mut baseline = Baseline::new();
+    baseline.insert((3, Phase::Parse), Stats { min: ms(1), median: ms(2), max: ms(3) });
+    baseline.insert((3, Phase::Part(2)), Stats { min: ms(4), median: ms(5), max: ms(6) });
+
+    assert_eq!(parse_baseline(&format_baseline(&baseline)).unwrap(), baseline);
+  }
+
+  #[test]
+  fn baseline_rejects_invalid_lines() {
+    assert!(parse_baseline("3 parse 1 2\n").is_err());
+    assert!(parse_baseline("3 part3 1 2 3\n").is_err());
+  }
+
+  #[test]
+  fn change_is_relative_to_baseline_median() {
+    let base = Stats { min: ms(1), median: ms(100), max: ms(200) };
+    let now = Stats { min: ms(1), median: ms(120), max: ms(200) };
+    assert!((change(&now, &base).unwrap() - 20.0).abs() < 1e-9);
+    assert!(format_timing(&Timing { day: 1, phase: Phase::Part(1), stats: now }, Some(&base)).ends_with("(regression)"));
+  }
+
+  #[test]
+  fn measure_reports_every_phase() {
+    let timings = measure(solution::get(1).unwrap(), "+1\n-2\n", &[1, 2], 3).unwrap();
+    let phases: Vec<Phase> = timings.iter().map(|t| t.phase).collect();
+    assert_eq!(phases, vec![Phase::Parse, Phase::Part(1), Phase::Part(2)]);
+  }
+}
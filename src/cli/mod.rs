@@ -0,0 +1,555 @@
+use std::path::PathBuf;
+use super::utils::Error;
+use super::input::Source;
+use super::report::Format;
+use super::elfcode::trace::TraceLimits;
+use super::elfcode::Arithmetic;
+use super::solution::{self, Puzzle};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Selection {
+  All,
+  Day(u8, Option<u8>),
+}
+
+impl Selection {
+  // The puzzles and parts to run.
+  pub fn resolve(&self) -> Result<(Vec<&'static dyn Puzzle>, Vec<u8>), Error> {
+    match *self {
+      Selection::All => Ok((solution::all().to_vec(), vec![1, 2])),
+      Selection::Day(day, part) => {
+        let puzzle = match solution::get(day) {
+          Some(v) => v,
+          None => Err(Error::new(&format!("There is no solution for day {}", day)))?
+        };
+        Ok((vec![puzzle], part.map_or(vec![1, 2], |p| vec![p])))
+      }
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunOptions {
+  pub selection: Selection,
+  pub source: Source,
+  pub format: Format,
+}
+
+impl RunOptions {
+  pub fn new(selection: Selection) -> RunOptions {
+    RunOptions { selection, source: Source::default(), format: Format::Table }
+  }
+}
+
+pub const DEFAULT_BENCH_RUNS: usize = 5;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BenchOptions {
+  pub selection: Selection,
+  pub source: Source,
+  pub runs: usize,
+  // Compare the timings against this baseline file.
+  pub baseline: Option<PathBuf>,
+  // Write the timings to this file so it can be used as a baseline later.
+  pub save: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerifyOptions {
+  pub selection: Selection,
+  pub source: Source,
+  // Defaults to the answers file in the input directory.
+  pub answers: Option<PathBuf>,
+  pub format: Format,
+}
+
+// The days whose input is an ElfCode program with a bound instruction pointer.
+pub const PROGRAM_DAYS: [u8; 2] = [19, 21];
+
+// Where to find the ElfCode program for the program tools: the input of a
+// day or, without a day, the given file or stdin.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProgramOptions {
+  pub day: Option<u8>,
+  pub source: Source,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DebugOptions {
+  pub program: ProgramOptions,
+  // Run the debugger commands in this file instead of reading them from stdin.
+  pub script: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ProfileOptions {
+  pub program: ProgramOptions,
+  // Stop the program after this many cycles.
+  pub max_cycles: Option<u64>,
+  pub arithmetic: Arithmetic,
+  // Write an execution trace to this file.
+  pub trace: Option<PathBuf>,
+  pub limits: TraceLimits,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+  Run(RunOptions),
+  Bench(BenchOptions),
+  Verify(VerifyOptions),
+  Decompile(ProgramOptions),
+  Debug(DebugOptions),
+  Profile(ProfileOptions),
+  Transpile(ProgramOptions),
+  Cfg(ProgramOptions),
+  // Checks the samples of the day 16 manual read from the source.
+  Samples(Source),
+  List,
+  Help,
+}
+
+pub const USAGE: &str = "Usage:
+  y2018 run --day <N> [--part <1|2>] [--input <FILE|->] [--format <table|json>]
+  y2018 run --all [--input-dir <DIR>] [--format <table|json>]
+  y2018 bench (--day <N> [--part <1|2>] | --all) [--runs <N>] [--baseline <FILE>] [--save <FILE>]
+  y2018 verify (--day <N> [--part <1|2>] | --all) [--answers <FILE>] [--format <table|json>]
+  y2018 decompile (--day <19|21> | --input <FILE|->)
+  y2018 debug (--day <19|21> | --input <FILE>) [--script <FILE>]
+  y2018 profile (--day <19|21> | --input <FILE|->) [--max-cycles <N>]
+                [--arithmetic <wrapping|checked|saturating|BITS>]
+                [--trace <FILE> [--trace-cycles <N>] [--trace-bytes <N>]]
+  y2018 transpile (--day <19|21> | --input <FILE|->)
+  y2018 cfg (--day <19|21> | --input <FILE|->)
+  y2018 samples [--input <FILE|-> | --input-dir <DIR>]
+  y2018 list
+
+Inputs are read from <DIR>/day<N>.txt (default: inputs/), from FILE or
+from stdin if FILE is '-'. The input options work with every command.
+Expected answers are read from <DIR>/answers.txt unless --answers is given.";
+
+fn parse_number(flag: &str, value: Option<String>, max: u8) -> Result<u8, Error> {
+  let value = match value {
+    Some(v) => v,
+    None => Err(Error::new(&format!("Missing value for {}", flag)))?
+  };
+
+  match value.parse::<u8>() {
+    Ok(n) if (1..=max).contains(&n) => Ok(n),
+    _ => Err(Error::new(&format!("Invalid value for {}: {} (expected 1 to {})", flag, value, max)))
+  }
+}
+
+fn parse_format(value: Option<String>) -> Result<Format, Error> {
+  match value {
+    Some(v) => v.parse::<Format>(),
+    None => Err(Error::new("Missing value for --format")),
+  }
+}
+
+fn parse_limit(flag: &str, value: Option<String>) -> Result<u64, Error> {
+  match value.map(|v| v.parse::<u64>()) {
+    Some(Ok(n)) if n > 0 => Ok(n),
+    _ => Err(Error::new(&format!("{} expects a positive number", flag))),
+  }
+}
+
+fn parse_path(flag: &str, value: Option<String>) -> Result<PathBuf, Error> {
+  match value {
+    Some(v) => Ok(PathBuf::from(v)),
+    None => Err(Error::new(&format!("Missing value for {}", flag))),
+  }
+}
+
+// Arguments shared by all commands that work on a selection of days.
+#[derive(Default)]
+struct SelectionArgs {
+  all: bool,
+  day: Option<u8>,
+  part: Option<u8>,
+  source: Option<Source>,
+}
+
+impl SelectionArgs {
+  // Returns false if the argument is not a selection argument.
+  fn accept<I>(&mut self, arg: &str, args: &mut I) -> Result<bool, Error>
+  where I: Iterator<Item = String> {
+    match arg {
+      "--all" | "-a" => self.all = true,
+      "--day" | "-d" => self.day = Some(parse_number("--day", args.next(), 25)?),
+      "--part" | "-p" => self.part = Some(parse_number("--part", args.next(), 2)?),
+      "--input" | "-i" | "--input-dir" if self.source.is_some() =>
+        Err(Error::new("Only one of --input or --input-dir can be given"))?,
+      "--input" | "-i" => self.source = Some(match parse_path("--input", args.next())? {
+        ref p if p.as_os_str() == "-" => Source::Stdin,
+        p => Source::File(p),
+      }),
+      "--input-dir" => self.source = Some(Source::Directory(parse_path("--input-dir", args.next())?)),
+      _ => return Ok(false),
+    }
+
+    Ok(true)
+  }
+
+  fn finish(self) -> Result<(Selection, Source), Error> {
+    let selection = match (self.all, self.day, self.part) {
+      (true, None, None) => Selection::All,
+      (true, _, _) => Err(Error::new("--all cannot be combined with --day or --part"))?,
+      (false, Some(d), p) => Selection::Day(d, p),
+      (false, None, _) => Err(Error::new("Either --day or --all is required"))?,
+    };
+
+    match (&selection, self.source) {
+      (Selection::All, Some(Source::File(_))) | (Selection::All, Some(Source::Stdin)) =>
+        Err(Error::new("--input can only be used with a single day, use --input-dir with --all")),
+      (_, source) => Ok((selection, source.unwrap_or_default())),
+    }
+  }
+}
+
+fn parse_run<I>(mut args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  let mut common = SelectionArgs::default();
+  let mut format = Format::Table;
+
+  while let Some(arg) = args.next() {
+    if common.accept(&arg, &mut args)? {
+      continue;
+    }
+
+    match arg.as_str() {
+      "--format" | "-f" => format = parse_format(args.next())?,
+      _ => Err(Error::new(&format!("Unknown argument: {}", arg)))?
+    }
+  }
+
+  let (selection, source) = common.finish()?;
+  let mut options = RunOptions::new(selection);
+  options.source = source;
+  options.format = format;
+
+  Ok(Command::Run(options))
+}
+
+fn parse_bench<I>(mut args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  let mut common = SelectionArgs::default();
+  let mut runs = DEFAULT_BENCH_RUNS;
+  let mut baseline = None;
+  let mut save = None;
+
+  while let Some(arg) = args.next() {
+    if common.accept(&arg, &mut args)? {
+      continue;
+    }
+
+    match arg.as_str() {
+      "--runs" | "-n" => runs = match args.next().map(|v| v.parse::<usize>()) {
+        Some(Ok(n)) if n > 0 => n,
+        _ => Err(Error::new("--runs expects a positive number"))?
+      },
+      "--baseline" | "-b" => baseline = Some(parse_path("--baseline", args.next())?),
+      "--save" | "-s" => save = Some(parse_path("--save", args.next())?),
+      _ => Err(Error::new(&format!("Unknown argument: {}", arg)))?
+    }
+  }
+
+  let (selection, source) = common.finish()?;
+
+  Ok(Command::Bench(BenchOptions { selection, source, runs, baseline, save }))
+}
+
+fn parse_verify<I>(mut args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  let mut common = SelectionArgs::default();
+  let mut answers = None;
+  let mut format = Format::Table;
+
+  while let Some(arg) = args.next() {
+    if common.accept(&arg, &mut args)? {
+      continue;
+    }
+
+    match arg.as_str() {
+      "--answers" => answers = Some(parse_path("--answers", args.next())?),
+      "--format" | "-f" => format = parse_format(args.next())?,
+      _ => Err(Error::new(&format!("Unknown argument: {}", arg)))?
+    }
+  }
+
+  let (selection, source) = common.finish()?;
+
+  Ok(Command::Verify(VerifyOptions { selection, source, answers, format }))
+}
+
+// Parses the arguments that pick a program. Everything else is handed to
+// `other`, which returns false for arguments it doesn't know either.
+fn parse_program<I, F>(mut args: I, mut other: F) -> Result<ProgramOptions, Error>
+where I: Iterator<Item = String>, F: FnMut(&str, &mut I) -> Result<bool, Error> {
+  let mut day = None;
+  let mut source = None;
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--day" | "-d" => day = Some(match parse_number("--day", args.next(), 25)? {
+        d if PROGRAM_DAYS.contains(&d) => d,
+        d => Err(Error::new(&format!("Day {} has no ElfCode program", d)))?
+      }),
+      "--input" | "-i" => source = Some(match parse_path("--input", args.next())? {
+        ref p if p.as_os_str() == "-" => Source::Stdin,
+        p => Source::File(p),
+      }),
+      _ if other(&arg, &mut args)? => {},
+      _ => Err(Error::new(&format!("Unknown argument: {}", arg)))?
+    }
+  }
+
+  match (day, source) {
+    (None, None) => Err(Error::new("Either --day or --input is required")),
+    (day, source) => Ok(ProgramOptions { day, source: source.unwrap_or_default() }),
+  }
+}
+
+fn parse_decompile<I>(args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  Ok(Command::Decompile(parse_program(args, |_, _| Ok(false))?))
+}
+
+fn parse_transpile<I>(args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  Ok(Command::Transpile(parse_program(args, |_, _| Ok(false))?))
+}
+
+fn parse_cfg<I>(args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  Ok(Command::Cfg(parse_program(args, |_, _| Ok(false))?))
+}
+
+fn parse_debug<I>(args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  let mut script = None;
+  let program = parse_program(args, |arg, args| {
+    match arg {
+      "--script" | "-s" => script = Some(parse_path("--script", args.next())?),
+      _ => return Ok(false),
+    }
+    Ok(true)
+  })?;
+
+  if program.source == Source::Stdin && script.is_none() {
+    Err(Error::new("The program can only be read from stdin together with --script"))?
+  }
+
+  Ok(Command::Debug(DebugOptions { program, script }))
+}
+
+fn parse_profile<I>(args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  let mut max_cycles = None;
+  let mut arithmetic = Arithmetic::default();
+  let mut trace = None;
+  let mut limits = TraceLimits::default();
+  let program = parse_program(args, |arg, args| {
+    match arg {
+      "--max-cycles" => max_cycles = Some(parse_limit(arg, args.next())?),
+      "--arithmetic" => arithmetic = match args.next() {
+        Some(v) => v.parse()?,
+        None => Err(Error::new("Missing value for --arithmetic"))?
+      },
+      "--trace" | "-t" => trace = Some(parse_path("--trace", args.next())?),
+      "--trace-cycles" => limits.cycles = Some(parse_limit(arg, args.next())?),
+      "--trace-bytes" => limits.bytes = Some(parse_limit(arg, args.next())?),
+      _ => return Ok(false),
+    }
+    Ok(true)
+  })?;
+
+  if trace.is_none() && limits != TraceLimits::default() {
+    Err(Error::new("--trace-cycles and --trace-bytes need --trace"))?
+  }
+
+  Ok(Command::Profile(ProfileOptions { program, max_cycles, arithmetic, trace, limits }))
+}
+
+fn parse_samples<I>(mut args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  let mut common = SelectionArgs::default();
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--input" | "-i" | "--input-dir" => { common.accept(&arg, &mut args)?; },
+      _ => Err(Error::new(&format!("Unknown argument: {}", arg)))?
+    }
+  }
+
+  Ok(Command::Samples(common.source.unwrap_or_default()))
+}
+
+pub fn parse_args<I>(mut args: I) -> Result<Command, Error>
+where I: Iterator<Item = String> {
+  match args.next().as_deref() {
+    Some("run") => parse_run(args),
+    Some("bench") => parse_bench(args),
+    Some("verify") => parse_verify(args),
+    Some("decompile") => parse_decompile(args),
+    Some("debug") => parse_debug(args),
+    Some("profile") => parse_profile(args),
+    Some("transpile") => parse_transpile(args),
+    Some("cfg") => parse_cfg(args),
+    Some("samples") => parse_samples(args),
+    Some("list") => Ok(Command::List),
+    Some("help") | Some("--help") | Some("-h") | None => Ok(Command::Help),
+    Some(other) => Err(Error::new(&format!("Unknown command: {}", other))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Result<Command, Error> {
+    parse_args(args.iter().map(|v| v.to_string()))
+  }
+
+  #[test]
+  fn parses_day_and_part() {
+    assert_eq!(parse(&["run", "--day", "15", "--part", "2"]).unwrap(), Command::Run(RunOptions::new(Selection::Day(15, Some(2)))));
+  }
+
+  #[test]
+  fn parses_day_without_part() {
+    assert_eq!(parse(&["run", "-d", "3"]).unwrap(), Command::Run(RunOptions::new(Selection::Day(3, None))));
+  }
+
+  #[test]
+  fn parses_all() {
+    assert_eq!(parse(&["run", "--all"]).unwrap(), Command::Run(RunOptions::new(Selection::All)));
+  }
+
+  #[test]
+  fn parses_list() {
+    assert_eq!(parse(&["list"]).unwrap(), Command::List);
+  }
+
+  #[test]
+  fn rejects_invalid_day() {
+    assert!(parse(&["run", "--day", "26"]).is_err());
+    assert!(parse(&["run", "--day", "x"]).is_err());
+  }
+
+  #[test]
+  fn rejects_all_with_day() {
+    assert!(parse(&["run", "--all", "--day", "1"]).is_err());
+  }
+
+  #[test]
+  fn parses_input_file_and_stdin() {
+    assert_eq!(parse(&["run", "-d", "1", "--input", "my/day1.txt"]).unwrap(),
+      Command::Run(RunOptions { source: Source::File(PathBuf::from("my/day1.txt")), ..RunOptions::new(Selection::Day(1, None)) }));
+    assert_eq!(parse(&["run", "-d", "1", "-i", "-"]).unwrap(),
+      Command::Run(RunOptions { source: Source::Stdin, ..RunOptions::new(Selection::Day(1, None)) }));
+  }
+
+  #[test]
+  fn parses_input_dir() {
+    assert_eq!(parse(&["run", "--all", "--input-dir", "mine"]).unwrap(),
+      Command::Run(RunOptions { source: Source::Directory(PathBuf::from("mine")), ..RunOptions::new(Selection::All) }));
+  }
+
+  #[test]
+  fn parses_format() {
+    assert_eq!(parse(&["run", "--all", "--format", "json"]).unwrap(),
+      Command::Run(RunOptions { format: Format::Json, ..RunOptions::new(Selection::All) }));
+    assert!(parse(&["run", "--all", "--format", "yaml"]).is_err());
+  }
+
+  #[test]
+  fn parses_bench() {
+    assert_eq!(parse(&["bench", "-d", "5", "--runs", "10", "--baseline", "old.txt", "--save", "new.txt"]).unwrap(),
+      Command::Bench(BenchOptions {
+        selection: Selection::Day(5, None),
+        source: Source::default(),
+        runs: 10,
+        baseline: Some(PathBuf::from("old.txt")),
+        save: Some(PathBuf::from("new.txt")),
+      }));
+    assert!(parse(&["bench", "--all", "--runs", "0"]).is_err());
+    assert!(parse(&["bench", "--runs", "3"]).is_err());
+  }
+
+  #[test]
+  fn parses_verify() {
+    assert_eq!(parse(&["verify", "--all", "--input-dir", "mine", "--answers", "mine.txt"]).unwrap(),
+      Command::Verify(VerifyOptions {
+        selection: Selection::All,
+        source: Source::Directory(PathBuf::from("mine")),
+        answers: Some(PathBuf::from("mine.txt")),
+        format: Format::Table,
+      }));
+  }
+
+  #[test]
+  fn parses_decompile() {
+    assert_eq!(parse(&["decompile", "--day", "19"]).unwrap(),
+      Command::Decompile(ProgramOptions { day: Some(19), source: Source::default() }));
+    assert_eq!(parse(&["decompile", "-i", "-"]).unwrap(),
+      Command::Decompile(ProgramOptions { day: None, source: Source::Stdin }));
+    assert!(parse(&["decompile", "--day", "16"]).is_err());
+    assert!(parse(&["decompile"]).is_err());
+  }
+
+  #[test]
+  fn parses_debug() {
+    assert_eq!(parse(&["debug", "-d", "21", "--script", "halt.edb"]).unwrap(),
+      Command::Debug(DebugOptions {
+        program: ProgramOptions { day: Some(21), source: Source::default() },
+        script: Some(PathBuf::from("halt.edb")),
+      }));
+    assert!(parse(&["debug", "--input", "-"]).is_err());
+    assert!(parse(&["debug", "-d", "19", "--runs", "3"]).is_err());
+  }
+
+  #[test]
+  fn parses_profile() {
+    assert_eq!(parse(&["profile", "-d", "19", "--max-cycles", "1000", "--trace", "t.txt", "--trace-bytes", "4096"]).unwrap(),
+      Command::Profile(ProfileOptions {
+        program: ProgramOptions { day: Some(19), source: Source::default() },
+        max_cycles: Some(1000),
+        arithmetic: Arithmetic::Checked,
+        trace: Some(PathBuf::from("t.txt")),
+        limits: TraceLimits { cycles: None, bytes: Some(4096) },
+      }));
+    match parse(&["profile", "-i", "-", "--arithmetic", "36"]).unwrap() {
+      Command::Profile(options) => assert_eq!(options.arithmetic, Arithmetic::WrappingBits(36)),
+      other => panic!("{:?}", other),
+    }
+    assert!(parse(&["profile", "-d", "19", "--arithmetic", "unchecked"]).is_err());
+    assert!(parse(&["profile", "-d", "19", "--trace-cycles", "10"]).is_err());
+    assert!(parse(&["profile", "-d", "19", "--max-cycles", "0"]).is_err());
+  }
+
+  #[test]
+  fn parses_transpile() {
+    assert_eq!(parse(&["transpile", "--input", "prog.txt"]).unwrap(),
+      Command::Transpile(ProgramOptions { day: None, source: Source::File(PathBuf::from("prog.txt")) }));
+    assert!(parse(&["transpile", "--day", "16"]).is_err());
+  }
+
+  #[test]
+  fn parses_cfg() {
+    assert_eq!(parse(&["cfg", "-d", "19"]).unwrap(), Command::Cfg(ProgramOptions { day: Some(19), source: Source::default() }));
+    assert!(parse(&["cfg"]).is_err());
+  }
+
+  #[test]
+  fn parses_samples() {
+    assert_eq!(parse(&["samples"]).unwrap(), Command::Samples(Source::default()));
+    assert_eq!(parse(&["samples", "-i", "-"]).unwrap(), Command::Samples(Source::Stdin));
+    assert_eq!(parse(&["samples", "--input-dir", "mine"]).unwrap(), Command::Samples(Source::Directory(PathBuf::from("mine"))));
+    assert!(parse(&["samples", "--day", "16"]).is_err());
+  }
+
+  #[test]
+  fn rejects_input_file_for_all_days() {
+    assert!(parse(&["run", "--all", "--input", "day1.txt"]).is_err());
+    assert!(parse(&["run", "-d", "1", "--input", "a.txt", "--input-dir", "b"]).is_err());
+  }
+}
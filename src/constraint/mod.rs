@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+// One piece of evidence about a key: the key has to map to one of `allowed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restriction<V, E> {
+  pub allowed: BTreeSet<V>,
+  pub evidence: E,
+}
+
+// Why there is no single assignment that fits all restrictions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unsolvable<K, V, E> {
+  // The `keys` can only map to `values` between them, which are fewer than
+  // the keys. `evidence` rules out everything else.
+  Contradictory { keys: Vec<K>, values: Vec<V>, evidence: Vec<E> },
+  // Two different assignments fit, they differ in `keys`. `evidence` is
+  // everything that is known about those keys.
+  Ambiguous { keys: Vec<K>, first: BTreeMap<K, V>, second: BTreeMap<K, V>, evidence: Vec<E> },
+}
+
+// Finds the one assignment of keys to values where every key gets a
+// different value and every restriction holds, e.g. which opcode number
+// stands for which instruction.
+#[derive(Debug, Clone)]
+pub struct Solver<K, V, E> {
+  keys: Vec<K>,
+  values: BTreeSet<V>,
+  restrictions: Vec<Vec<Restriction<V, E>>>,
+}
+
+impl<K, V, E> Solver<K, V, E>
+where K: Ord + Copy, V: Ord + Copy, E: Ord + Clone {
+  pub fn new<I, J>(keys: I, values: J) -> Solver<K, V, E>
+  where I: IntoIterator<Item = K>, J: IntoIterator<Item = V> {
+    let keys = keys.into_iter().collect::<BTreeSet<K>>().into_iter().collect::<Vec<K>>();
+    let restrictions = keys.iter().map(|_| vec![]).collect();
+
+    Solver { keys, values: values.into_iter().collect(), restrictions }
+  }
+
+  fn index(&mut self, key: K) -> usize {
+    match self.keys.binary_search(&key) {
+      Ok(index) => index,
+      Err(index) => {
+        self.keys.insert(index, key);
+        self.restrictions.insert(index, vec![]);
+        index
+      }
+    }
+  }
+
+  // Unknown keys are added as needed.
+  pub fn restrict<I: IntoIterator<Item = V>>(&mut self, key: K, allowed: I, evidence: E) {
+    let index = self.index(key);
+    self.restrictions[index].push(Restriction { allowed: allowed.into_iter().collect(), evidence });
+  }
+
+  pub fn restrictions(&self, key: K) -> &[Restriction<V, E>] {
+    match self.keys.binary_search(&key) {
+      Ok(index) => &self.restrictions[index],
+      Err(_) => &[],
+    }
+  }
+
+  fn domain(&self, index: usize) -> BTreeSet<V> {
+    self.restrictions[index]
+      .iter()
+      .fold(self.values.clone(), |domain, r| domain.intersection(&r.allowed).cloned().collect())
+  }
+
+  // The values a key can map to going by its own restrictions only.
+  pub fn candidates(&self, key: K) -> BTreeSet<V> {
+    match self.keys.binary_search(&key) {
+      Ok(index) => self.domain(index),
+      Err(_) => self.values.clone(),
+    }
+  }
+
+  pub fn solve(&self) -> Result<BTreeMap<K, V>, Unsolvable<K, V, E>> {
+    let domains = (0..self.keys.len()).map(|i| self.domain(i)).collect::<Vec<BTreeSet<V>>>();
+
+    if let Some(keys) = hall_violation(&domains) {
+      let values = keys.iter().fold(BTreeSet::new(), |all, &k| all.union(&domains[k]).cloned().collect::<BTreeSet<V>>());
+      let evidence = self.explain(&keys, |_| &values);
+
+      return Err(Unsolvable::Contradictory {
+        keys: keys.iter().map(|&k| self.keys[k]).collect(),
+        values: values.into_iter().collect(),
+        evidence,
+      });
+    }
+
+    let mut found = vec![];
+    search(domains.clone(), &mut found);
+    let assignment = |values: &[V]| self.keys.iter().cloned().zip(values.iter().cloned()).collect::<BTreeMap<K, V>>();
+
+    match found.as_slice() {
+      [first, second] => {
+        let differ = (0..self.keys.len()).filter(|&k| first[k] != second[k]).collect::<Vec<usize>>();
+        Err(Unsolvable::Ambiguous {
+          keys: differ.iter().map(|&k| self.keys[k]).collect(),
+          first: assignment(first),
+          second: assignment(second),
+          evidence: self.explain(&differ, |k| &domains[k]),
+        })
+      },
+      // A complete matching exists, so there is at least one solution.
+      _ => Ok(assignment(&found[0])),
+    }
+  }
+
+  // Picks restrictions of the keys until each key is limited to its
+  // target, greedily taking the one that rules out the most first.
+  fn explain<'a, F>(&self, keys: &[usize], target: F) -> Vec<E>
+  where F: Fn(usize) -> &'a BTreeSet<V>, V: 'a {
+    let mut evidence = BTreeSet::new();
+
+    for &key in keys {
+      let target = target(key);
+      let mut current = self.values.clone();
+      while !current.is_subset(target) {
+        let best = self.restrictions[key]
+          .iter()
+          .max_by_key(|r| current.iter().filter(|v| !target.contains(v) && !r.allowed.contains(v)).count());
+        let best = match best {
+          Some(r) => r,
+          None => break,
+        };
+
+        current = current.intersection(&best.allowed).cloned().collect();
+        evidence.insert(best.evidence.clone());
+      }
+    }
+
+    evidence.into_iter().collect()
+  }
+}
+
+// Tries to give every key its own value along augmenting paths.
+fn augment<V: Ord + Copy>(key: usize, domains: &[BTreeSet<V>], owner: &mut BTreeMap<V, usize>, seen: &mut BTreeSet<V>) -> bool {
+  for &value in &domains[key] {
+    if !seen.insert(value) {
+      continue;
+    }
+
+    let free = match owner.get(&value) {
+      Some(&other) => augment(other, domains, owner, seen),
+      None => true,
+    };
+    if free {
+      owner.insert(value, key);
+      return true;
+    }
+  }
+
+  false
+}
+
+// If the keys can't all get different values, finds a set of keys that
+// share fewer values than there are keys. These are the keys reachable
+// from one that could not be matched.
+fn hall_violation<V: Ord + Copy>(domains: &[BTreeSet<V>]) -> Option<Vec<usize>> {
+  let mut owner = BTreeMap::new();
+  let unmatched = (0..domains.len()).find(|&key| !augment(key, domains, &mut owner, &mut BTreeSet::new()))?;
+
+  let mut keys = BTreeSet::new();
+  let mut queue = vec![unmatched];
+  while let Some(key) = queue.pop() {
+    if keys.insert(key) {
+      queue.extend(domains[key].iter().filter_map(|v| owner.get(v)));
+    }
+  }
+
+  Some(keys.into_iter().collect())
+}
+
+// Removes the values of decided keys from all others, until nothing changes.
+fn propagate<V: Ord + Copy>(domains: &mut [BTreeSet<V>]) -> bool {
+  let mut changed = true;
+  while changed {
+    changed = false;
+    for k in 0..domains.len() {
+      let value = match domains[k].iter().next() {
+        Some(&v) if domains[k].len() == 1 => v,
+        Some(_) => continue,
+        None => return false,
+      };
+
+      for (other, domain) in domains.iter_mut().enumerate() {
+        if other != k && domain.remove(&value) {
+          if domain.is_empty() {
+            return false;
+          }
+          changed = true;
+        }
+      }
+    }
+  }
+
+  true
+}
+
+// Collects up to two solutions, trying the key with the fewest candidates first.
+fn search<V: Ord + Copy>(mut domains: Vec<BTreeSet<V>>, found: &mut Vec<Vec<V>>) {
+  if found.len() >= 2 || !propagate(&mut domains) {
+    return;
+  }
+
+  let open = (0..domains.len())
+    .filter(|&k| domains[k].len() > 1)
+    .min_by_key(|&k| domains[k].len());
+  let key = match open {
+    Some(k) => k,
+    None => {
+      found.push(domains.iter().map(|d| *d.iter().next().unwrap()).collect());
+      return;
+    }
+  };
+
+  for &value in &domains[key] {
+    let mut next = domains.clone();
+    next[key] = vec![value].into_iter().collect();
+    search(next, found);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn propagates_decided_keys() {
+    let mut solver = Solver::new(0..3, vec!['a', 'b', 'c']);
+    solver.restrict(0, vec!['a'], 1);
+    solver.restrict(1, vec!['a', 'b'], 2);
+
+    let solution = solver.solve().unwrap();
+    assert_eq!(solution.into_iter().collect::<Vec<(i32, char)>>(), vec![(0, 'a'), (1, 'b'), (2, 'c')]);
+  }
+
+  #[test]
+  fn reports_ambiguity_when_propagation_stalls() {
+    // Nothing is decided up front, so propagation gets nowhere. The search
+    // finds key 2 can only be 'c', but keys 0 and 1 may swap 'a' and 'b'.
+    let mut solver = Solver::new(0..3, vec!['a', 'b', 'c']);
+    solver.restrict(0, vec!['a', 'b'], 1);
+    solver.restrict(1, vec!['a', 'b'], 2);
+
+    match solver.solve() {
+      Err(Unsolvable::Ambiguous { keys, first, second, evidence }) => {
+        assert_eq!(keys, vec![0, 1]);
+        assert_eq!((first[&2], second[&2]), ('c', 'c'));
+        assert_eq!((first[&0], second[&0]), ('a', 'b'));
+        assert_eq!(evidence, vec![1, 2]);
+      },
+      other => panic!("{:?}", other),
+    }
+  }
+
+  #[test]
+  fn contradictions_name_the_keys_and_evidence() {
+    let mut solver = Solver::new(0..3, vec!['a', 'b', 'c']);
+    solver.restrict(0, vec!['a', 'b'], 1);
+    solver.restrict(1, vec!['a', 'b', 'c'], 2);
+    solver.restrict(1, vec!['a'], 3);
+    solver.restrict(2, vec!['a', 'c'], 4);
+    solver.restrict(2, vec!['a', 'b'], 5);
+
+    assert_eq!(solver.solve(), Err(Unsolvable::Contradictory { keys: vec![1, 2], values: vec!['a'], evidence: vec![3, 4, 5] }));
+  }
+
+  #[test]
+  fn ambiguities_name_the_keys_and_evidence() {
+    let mut solver = Solver::new(0..3, vec!['a', 'b', 'c']);
+    solver.restrict(0, vec!['c'], 1);
+    solver.restrict(1, vec!['a', 'b'], 2);
+    solver.restrict(2, vec!['a', 'b', 'c'], 3);
+
+    match solver.solve() {
+      Err(Unsolvable::Ambiguous { keys, first, second, evidence }) => {
+        assert_eq!(keys, vec![1, 2]);
+        assert_ne!(first, second);
+        assert_eq!(evidence, vec![2]);
+      },
+      other => panic!("{:?}", other),
+    }
+  }
+
+  #[test]
+  fn keys_may_get_fewer_values_than_there_are() {
+    let mut solver = Solver::new(vec!["x"], vec![1, 2]);
+    solver.restrict("x", vec![2], ());
+    solver.restrict("y", vec![1, 2], ());
+
+    assert_eq!(solver.solve().unwrap().get("y"), Some(&1));
+    assert_eq!(solver.candidates("y").len(), 2);
+  }
+}
@@ -1,34 +1,71 @@
 use std::collections::BTreeSet;
+use super::utils::{parse_lines, parse_number, Error};
+use super::solution::{Answer, Solution};
 
-pub fn problem1() {
-  let input = include_str!("./data/input_1-1.txt");
-  let result: i32 = input
-    .split("\n")
-    .filter(|v| *v != "")
-    .map(|v| v.parse::<i32>().unwrap())
-    .sum();
+pub struct Day1;
 
-  println!("Result of 1-1: {}", result);
+impl Solution for Day1 {
+  type Input = Vec<i32>;
+
+  const DAY: u8 = 1;
+  const TITLE: &'static str = "Chronal Calibration";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input_1-1.txt"));
+
+  fn parse(input: &str) -> Result<Vec<i32>, Error> {
+    let numbers = parse_lines(input, parse_number::<i32>)?;
+
+    Ok(numbers)
+  }
+
+  fn part1(numbers: &Vec<i32>) -> Result<Answer, Error> {
+    let result: i64 = numbers.iter().map(|&n| n as i64).sum();
+
+    Ok(Answer::from(result))
+  }
+
+  fn part2(numbers: &Vec<i32>) -> Result<Answer, Error> {
+    if numbers.is_empty() {
+      Err(Error::new("No frequency changes given"))?
+    }
+
+    // Every pass shifts the frequencies by the same drift. Two frequencies of
+    // a pass that are n drifts apart meet after n passes, so if nothing
+    // repeated once the spread of a pass is covered, nothing ever will.
+    let frequencies = numbers.iter().scan(0i64, |sum, &n| { *sum += n as i64; Some(*sum) });
+    let (low, high, drift) = frequencies.fold((0, 0, 0), |(low, high, _), f| (low.min(f), high.max(f), f));
+    let passes = if drift == 0 { 2 } else { (high - low) / drift.abs() + 2 };
+
+    let mut visited_frequencies = BTreeSet::new();
+    let mut result = 0i64;
+    for _ in 0..passes {
+      for &n in numbers {
+        if !visited_frequencies.insert(result) {
+          return Ok(Answer::from(result));
+        }
+        result += n as i64;
+      }
+    }
+
+    Err(Error::new("The frequency never repeats"))
+  }
 }
 
-pub fn problem2() {
-  let input = include_str!("./data/input_1-1.txt");
-  let numbers = input
-    .split("\n")
-    .filter(|v| *v != "")
-    .map(|v| v.parse::<i32>().unwrap())
-    .collect::<Vec<_>>();
-
-  let mut visited_frequencies = BTreeSet::new();
-
-  let mut result = 0;
-  let mut index = 0;
-  while !visited_frequencies.contains(&result) {
-    visited_frequencies.insert(result);
-    result += numbers[index];
-    index = (index + 1) % numbers.len();
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn finds_the_first_repeated_frequency() {
+    for (input, expected) in &[("+1\n-1\n", 0), ("+3\n+3\n+4\n-2\n-4\n", 10), ("-6\n+3\n+8\n+5\n-6\n", 5), ("+7\n+7\n-2\n-7\n-4\n", 14)] {
+      let numbers = Day1::parse(input).unwrap();
+      assert_eq!(Day1::part2(&numbers).unwrap(), Answer::Number(*expected), "{}", input);
+    }
   }
 
-  println!("First result visited twice: {}", result);
-  println!("Number of results generated: {}", visited_frequencies.len());
-}
\ No newline at end of file
+  #[test]
+  fn drifting_frequencies_are_errors() {
+    let numbers = Day1::parse("+1\n").unwrap();
+    assert_eq!(Day1::part2(&numbers).unwrap_err().to_string(), "The frequency never repeats");
+  }
+}
@@ -1,9 +1,11 @@
 use std::str::FromStr;
 use regex::Regex;
-use super::utils::{preprocess_input, ParseError, Error};
+use super::grid::Grid;
+use super::utils::{parse_lines, parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
 
-#[derive(Debug)]
-struct Light {
+#[derive(Debug, Clone)]
+pub struct Light {
   pub position: (i32, i32),
   pub velocity: (i32, i32),
 }
@@ -15,9 +17,12 @@ impl FromStr for Light {
     lazy_static!{
       static ref RE: Regex = Regex::new(r"position=<\s*(-?\d+)\s*,\s*(-?\d+)\s*>\s*velocity=<\s*(-?\d+)\s*,\s*(-?\d+)\s*>").unwrap();
     }
-    let cap = RE.captures(s).unwrap();
+    let cap = match RE.captures(s) {
+      Some(c) => c,
+      None => Err(ParseError::new("Could not parse light"))?
+    };
 
-    let parse = |v: &str| v.parse::<i32>();
+    let parse = |v: &str| parse_number::<i32>(v);
 
     let x = parse(&cap[1])?;
     let y = parse(&cap[2])?;
@@ -28,23 +33,29 @@ impl FromStr for Light {
   }
 }
 
-fn forward(lights: &mut Vec<Light>) {
+fn forward(lights: &mut [Light]) -> Result<(), Error> {
   for light in lights {
-    light.position.0 += light.velocity.0;
-    light.position.1 += light.velocity.1;
+    let x = light.position.0.checked_add(light.velocity.0);
+    let y = light.position.1.checked_add(light.velocity.1);
+    light.position = match (x, y) {
+      (Some(x), Some(y)) => (x, y),
+      _ => Err(Error::new("A light moved out of range"))?
+    };
   }
+
+  Ok(())
 }
 
-fn backward(lights: &mut Vec<Light>) {
+fn backward(lights: &mut [Light]) {
   for light in lights {
     light.position.0 -= light.velocity.0;
     light.position.1 -= light.velocity.1;
   }
 }
 
-fn bounding_box(lights: &Vec<Light>) -> ((i32, i32), (i32, i32)) {
-  let mut max = (0, 0);
-  let mut min = (100000, 100000);
+fn bounding_box(lights: &[Light]) -> ((i32, i32), (i32, i32)) {
+  let mut max = (i32::MIN, i32::MIN);
+  let mut min = (i32::MAX, i32::MAX);
   for l in lights {
     if l.position.0 > max.0 {
       max.0 = l.position.0;
@@ -66,74 +77,109 @@ fn bounding_box(lights: &Vec<Light>) -> ((i32, i32), (i32, i32)) {
   (min, max)
 }
 
-fn print_lights(lights: &Vec<Light>, size: (i32, i32), min: (i32, i32)) {
-  let mut display: Vec<Vec<char>> = vec![];
+// Far more than a message needs, the lights are still scattered.
+const MAX_CELLS: i64 = 1_000_000;
 
-  for _i in 0..(size.1 + 1) {
-    let line = vec![' '; (size.0 + 1) as usize];
-    display.push(line);
+fn render_lights(lights: &[Light]) -> Result<String, Error> {
+  let (min, _) = bounding_box(lights);
+  let size = bounding_box_size(lights);
+  let (width, height) = (size.0 + 1, size.1 + 1);
+  if width.saturating_mul(height) > MAX_CELLS {
+    Err(Error::new(&format!("The lights are spread over {}x{} cells and don't form a message", width, height)))?
   }
 
-  for l in lights {
-    let pos = l.position;
-    let rx = pos.0 - min.0;
-    let ry = pos.1 - min.1;
-    display[ry as usize][rx as usize] = 'x';
-  }
+  let mut display = Grid::with_origin(min, width as usize, height as usize, ' ');
 
-  for line in display {
-    for c in line {
-      print!("{}", c);
+  for l in lights {
+    if let Some(cell) = display.get_mut(l.position) {
+      *cell = 'x';
     }
-    println!("");
   }
+
+  let message = display
+    .render(|&c| c)
+    .lines()
+    .map(|line| line.trim_end())
+    .collect::<Vec<&str>>()
+    .join("\n");
+
+  Ok(message)
 }
 
-fn bounding_box_size(lights: &Vec<Light>) -> (i32, i32) {
+fn bounding_box_size(lights: &[Light]) -> (i64, i64) {
   let (min, max) = bounding_box(lights);
-  (max.0 - min.0, max.1 - min.1)
+  (max.0 as i64 - min.0 as i64, max.1 as i64 - min.1 as i64)
 }
 
-pub fn problem1() -> Result<i32, Error> {
-  let mut lights = preprocess_input(include_str!("./data/input.txt"))
-    .iter()
-    .cloned()
-    .map(|s| Light::from_str(s))
-    .collect::<Result<Vec<Light>, ParseError>>()?;
-
-  let mut previous_bb_size = (1000000, 1000000);
-  let mut number_of_seconds = 0;
+// Moves the lights until their bounding box stops shrinking and returns
+// them together with the number of seconds it took.
+fn align(lights: &[Light]) -> Result<(Vec<Light>, i32), Error> {
+  let mut lights = lights.to_vec();
+  let mut previous_bb_size = bounding_box_size(&lights);
   for i in 0..100000 {
-    forward(&mut lights);
+    forward(&mut lights)?;
     let bb_size = bounding_box_size(&lights);
 
     if previous_bb_size.0 < bb_size.0 || previous_bb_size.1 < bb_size.1 {
       backward(&mut lights);
-
-      let bb = bounding_box(&lights);
-      print_lights(&lights, previous_bb_size, bb.0);
-      number_of_seconds = i;
-      break;
+      return Ok((lights, i));
     }
     previous_bb_size = bb_size;
   }
 
-  Ok(number_of_seconds)
+  Err(Error::new("Lights never aligned"))
 }
 
-pub fn problem2() -> Result<i32, Error> {
-  let wait = problem1()?;
-  println!("Wait: {} seconds", wait);
+pub struct Day10;
+
+impl Solution for Day10 {
+  type Input = Vec<Light>;
+
+  const DAY: u8 = 10;
+  const TITLE: &'static str = "The Stars Align";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Vec<Light>, Error> {
+    let lights = parse_lines(input, Light::from_str)?;
+
+    Ok(lights)
+  }
+
+  fn part1(lights: &Vec<Light>) -> Result<Answer, Error> {
+    let (lights, _) = align(lights)?;
+
+    Ok(Answer::from(render_lights(&lights)?))
+  }
+
+  fn part2(lights: &Vec<Light>) -> Result<Answer, Error> {
+    let (_, seconds) = align(lights)?;
 
-  Ok(wait)
+    Ok(Answer::from(seconds))
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn lights_have_to_stay_in_range() {
+    let lights = Day10::parse("position=<2147483647, 0> velocity=<1, 0>\n").unwrap();
+    assert_eq!(Day10::part2(&lights).err().unwrap().to_string(), "A light moved out of range");
+  }
+
+  #[test]
+  fn scattered_lights_are_not_rendered() {
+    let lights = Day10::parse("position=<0, 0> velocity=<-1, 0>\nposition=<2000000, 0> velocity=<1, 0>\n").unwrap();
+    assert_eq!(Day10::part2(&lights).unwrap(), Answer::Number(0));
+    let err = Day10::part1(&lights).err().unwrap().to_string();
+    assert_eq!(err, "The lights are spread over 2000001x1 cells and don't form a message");
+  }
+
   #[test]
   fn check_problem2() {
-    assert_eq!(problem2().unwrap(), 10880);
+    let lights = Day10::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day10::part2(&lights).unwrap(), Answer::Number(10880));
   }
 }
\ No newline at end of file
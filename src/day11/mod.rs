@@ -1,56 +1,48 @@
-fn get_index(cell: (usize, usize)) -> usize {
-  let (x, y) = cell;
+use super::grid::{Grid, Point};
+use super::utils::{parse_number, Error};
+use super::solution::{Answer, Solution};
 
-  (x - 1) * 300 + y - 1
-}
+// The cells are numbered from 1 to 300 in both directions.
+const SIZE: usize = 300;
 
-fn cell_power_level(cell: (usize, usize), serial: i32) -> i32 {
-  let (x, y) = cell;
-  let rack_id = (x + 10) as i32;
+// Computed in 64 bits so any serial fits, only the hundreds digit is kept.
+fn cell_power_level(cell: Point, serial: i32) -> i32 {
+  let (x, y) = (cell.0 as i64, cell.1 as i64);
+  let rack_id = x + 10;
 
   let mut powerlevel = rack_id;
-  powerlevel *= y as i32;
-  powerlevel += serial;
+  powerlevel *= y;
+  powerlevel += serial as i64;
   powerlevel *= rack_id;
 
   let above_hundreds = (powerlevel / 1000) * 10;
   let below_hundreds = powerlevel / 100;
   powerlevel = below_hundreds - above_hundreds;
 
-  powerlevel - 5
+  powerlevel as i32 - 5
 }
 
-fn powerlevel_grid(serial: i32) -> Vec<i32> {
-  let mut result = vec![0; 300*300];
-
-  for x in 1..301 {
-    for y in 1..301 {
-      let i = get_index((x, y));
-      result[i] = cell_power_level((x, y), serial);
-    }
-  }
-
-  result
+fn powerlevel_grid(serial: i32) -> Grid<i32> {
+  Grid::from_fn((1, 1), SIZE, SIZE, |cell| cell_power_level(cell, serial))
 }
 
-fn power_level_square(grid: &Vec<i32>, top_left: (usize, usize)) -> i32 {
+fn power_level_square(grid: &Grid<i32>, top_left: Point) -> i32 {
   let mut powerlevel = 0;
   let (tx, ty) = top_left;
   for x in 0..3 {
     for y in 0..3 {
-      let i = get_index((tx + x, ty + y));
-      powerlevel += grid[i];
+      powerlevel += grid[(tx + x, ty + y)];
     }
   }
 
   powerlevel
 }
 
-pub fn problem1() -> ((usize, usize), i32) {
+fn largest_square(serial: i32) -> (Point, i32) {
   let mut max_tl = (1, 1);
   let mut max_pl = 0;
 
-  let levels_grid = powerlevel_grid(5177);
+  let levels_grid = powerlevel_grid(serial);
 
   for x in 1..298 {
     for y in 1..298 {
@@ -62,60 +54,42 @@ pub fn problem1() -> ((usize, usize), i32) {
     }
   }
 
-
-  println!("Max powerlevel of {} at {:?}", max_pl, max_tl);
-
   (max_tl, max_pl)
 }
 
-fn get_column(grid: &Vec<i32>, top_left: (usize, usize), size: usize) -> i32 {
-  let (tx, ty) = top_left;
-
-  let mut sum = 0;
-  for y in ty..(ty + size) {
-    let i = get_index((tx + size, y + 1));
-    sum += grid[i];
-  }
-
-  sum
-}
-
-fn get_row(grid: &Vec<i32>, top_left: (usize, usize), size: usize) -> i32 {
-  let (tx, ty) = top_left;
-
-  let mut sum = 0;
-  for x in tx..(tx + size) {
-    let i = get_index((x + 1, ty + size));
-    sum += grid[i];
+// The sum of the `size` cells of row `y` starting at column `x`.
+fn row_sum(grid: &Grid<i32>, (x, y): Point, size: i32) -> i32 {
+  let start = (x - grid.origin().0) as usize;
+  match grid.row(y) {
+    Some(row) => row[start..start + size as usize].iter().sum(),
+    None => 0,
   }
-
-  sum
 }
 
-pub fn problem2() -> ((usize, usize), usize, i32) {
+fn largest_square_of_any_size(serial: i32) -> (Point, i32, i32) {
   let mut max_tl = (1, 1);
   let mut max_pl = 0;
   let mut max_size = 1;
 
-  let grid = powerlevel_grid(5177);
-  let mut levels_grid = powerlevel_grid(5177);
+  let grid = powerlevel_grid(serial);
+  // Columns of the grid are the rows of this one, so both sums run over slices.
+  let transposed = Grid::from_fn((1, 1), SIZE, SIZE, |(x, y)| grid[(y, x)]);
+  let mut levels_grid = powerlevel_grid(serial);
 
   for size in 2..301 {
-    println!("size: {}", size);
     let end = 300 - size;
     for x in 0..end {
       for y in 0..end {
-        let col = get_column(&grid, (x, y), size);
-        let row = get_row(&grid, (x, y), size);
-        let corner_index = get_index((x + size, y + size));
-        let corner = grid[corner_index];
+        let col = row_sum(&transposed, (y + 1, x + size), size);
+        let row = row_sum(&grid, (x + 1, y + size), size);
+        let corner = grid[(x + size, y + size)];
 
-        let cell_index = get_index((x + 1, y + 1));
-        levels_grid[cell_index] += col + row - corner;
-        let powerlevel = levels_grid[cell_index];
+        let cell = (x + 1, y + 1);
+        levels_grid[cell] += col + row - corner;
+        let powerlevel = levels_grid[cell];
 
         if max_pl < powerlevel {
-          max_tl = (x + 1, y + 1);
+          max_tl = cell;
           max_pl = powerlevel;
           max_size = size;
         }
@@ -123,11 +97,36 @@ pub fn problem2() -> ((usize, usize), usize, i32) {
     }
   }
 
-  println!("Max powerlevel of {} at {:?} with size {}", max_pl, max_tl, max_size);
-
   (max_tl, max_size, max_pl)
 }
 
+pub struct Day11;
+
+impl Solution for Day11 {
+  type Input = i32;
+
+  const DAY: u8 = 11;
+  const TITLE: &'static str = "Chronal Charge";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<i32, Error> {
+    Ok(parse_number::<i32>(input)?)
+  }
+
+  fn part1(serial: &i32) -> Result<Answer, Error> {
+    let ((x, y), _) = largest_square(*serial);
+
+    Ok(Answer::from(format!("{},{}", x, y)))
+  }
+
+  fn part2(serial: &i32) -> Result<Answer, Error> {
+    let ((x, y), size, _) = largest_square_of_any_size(*serial);
+
+    Ok(Answer::from(format!("{},{},{}", x, y, size)))
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -152,15 +151,23 @@ mod tests {
     assert_eq!(cell_power_level((101, 153), 71), 4);
   }
 
+  #[test]
+  fn large_serials_fit() {
+    assert_eq!(cell_power_level((295, 300), i32::MAX), 3);
+    assert!(Day11::part1(&i32::MAX).is_ok());
+  }
+
   #[test]
   fn check_problem1() {
-    assert_eq!(problem1(), ((235, 22), 30));
+    let serial = Day11::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(largest_square(serial), ((235, 22), 30));
   }
 
   // don't run this in debug mode, it takes too long (~2.5min)
   #[cfg(not(debug_assertions))]
   #[test]
   fn check_problem2() {
-    assert_eq!(problem2(), ((231, 135), 8, 80));
+    let serial = Day11::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(largest_square_of_any_size(serial), ((231, 135), 8, 80));
   }
 }
\ No newline at end of file
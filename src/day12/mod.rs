@@ -1,22 +1,57 @@
 use std::collections::BTreeMap;
+use super::utils::{numbered_lines, ParseError, Error};
+use super::solution::{Answer, Solution};
 
-fn initialize() -> Vec<char> {
-  let initial_state = ".#####.##.#.##...#.#.###..#.#..#..#.....#..####.#.##.#######..#...##.#..#.#######...#.#.#..##..#.#.#";
+type Rules = BTreeMap<String, char>;
 
-  let plants = initial_state
-    .chars()
-    .collect();
+pub struct Garden {
+  pub initial_state: Vec<char>,
+  pub rules: Rules,
+}
+
+fn is_pot(c: char) -> bool {
+  c == '#' || c == '.'
+}
+
+fn parse_initial_state(line: &str) -> Result<Vec<char>, ParseError> {
+  let state = match line.strip_prefix("initial state: ") {
+    Some(v) => v.trim(),
+    None => Err(ParseError::new("Invalid initial state"))?
+  };
+
+  if !state.chars().all(is_pot) {
+    Err(ParseError::new("Invalid initial state"))?
+  }
 
-  plants
+  Ok(state.chars().collect())
 }
 
-fn process(current: Vec<char>, rules: &BTreeMap<&'static str, char>) -> Vec<char> {
+fn parse_rule(line: &str) -> Result<(String, char), ParseError> {
+  let mut parts = line.split(" => ");
+  let (pattern, result) = match (parts.next(), parts.next(), parts.next()) {
+    (Some(p), Some(r), None) => (p.trim(), r.trim()),
+    _ => Err(ParseError::new("Invalid rule"))?
+  };
+
+  let result = match result.parse::<char>() {
+    Ok(c) if is_pot(c) => c,
+    _ => Err(ParseError::new("Invalid rule"))?
+  };
+
+  if pattern.len() != 5 || !pattern.chars().all(is_pot) {
+    Err(ParseError::new("Invalid rule"))?
+  }
+
+  Ok((pattern.to_string(), result))
+}
+
+fn process(current: Vec<char>, rules: &Rules) -> Vec<char> {
   let mut new_state = vec!['.'; current.len()];
 
   for i in 2..(current.len() - 2) {
     let extract: String = current[(i-2)..(i+3)].iter().collect();
     for (key, value) in rules {
-      if extract == key.to_string() {
+      if extract == *key {
         new_state[i] = *value;
         break;
       }
@@ -26,12 +61,16 @@ fn process(current: Vec<char>, rules: &BTreeMap<&'static str, char>) -> Vec<char
   new_state
 }
 
-fn checksum(v: &Vec<char>) -> usize {
+// Pots left of the initial state are padded in front of it.
+const PADDING: usize = 500;
+
+// Plants can spread to the left of pot 0, so their numbers can be negative.
+fn checksum(v: &[char]) -> i64 {
   let mut sum = 0;
 
   for (i, c) in v.iter().enumerate() {
     sum += match c {
-      '#' => i - 500,
+      '#' => i as i64 - PADDING as i64,
       _ => 0
     };
   }
@@ -39,17 +78,16 @@ fn checksum(v: &Vec<char>) -> usize {
   sum
 }
 
-pub fn iterate(iterations: usize) -> (usize, usize) {
-  let mut state = vec!['.'; 500];
-  let mut initial_state = initialize();
-  state.append(&mut initial_state);
-  state.append(&mut vec!['.'; 1500]);
-  let rules = get_rules();
+pub fn iterate(garden: &Garden, iterations: usize) -> (i64, i64) {
+  let mut state = vec!['.'; PADDING];
+  state.extend(&garden.initial_state);
+  state.extend(vec!['.'; 1500]);
+  let rules = &garden.rules;
   let mut previous_checksum = 0;
   let mut diff = 0;
 
   for i in 0..iterations {
-    state = process(state, &rules);
+    state = process(state, rules);
 
     if (i + 1) % 100 == 0 {
       let checksum = checksum(&state);
@@ -62,96 +100,74 @@ pub fn iterate(iterations: usize) -> (usize, usize) {
   (result, diff)
 }
 
-pub fn problem1() -> usize {
-  let result = iterate(20).0;
-
-  println!("Result: {}", result);
-  result
-}
-
-pub fn problem2() -> usize {
-  let iterations = 300;
-  let (r, d) = iterate(iterations);
-
-  let result = (50_000_000_000 - 300) / 100 * d + r;
-
-  println!("Result: {}", result);
-
-  result
-}
-
+pub struct Day12;
 
-fn get_rules() -> BTreeMap<&'static str, char> {
-  let mut map = BTreeMap::new();
+impl Solution for Day12 {
+  type Input = Garden;
 
-  // ##... => #
-  map.insert("##...", '#');
+  const DAY: u8 = 12;
+  const TITLE: &'static str = "Subterranean Sustainability";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  // #...# => #
-  map.insert("#...#", '#');
-
-  // .#..# => #
-  map.insert(".#..#", '#');
-
-  // ##.## => #
-  map.insert("##.##", '#');
-
-  // ##.#. => #
-  map.insert("##.#.", '#');
-
-  // ###.. => #
-  map.insert("###..", '#');
-
-  // #.#.. => #
-  map.insert("#.#..", '#');
-
-  // ##..# => #
-  map.insert("##..#", '#');
-
-  // ..#.# => #
-  map.insert("..#.#", '#');
-
-  // ...## => #
-  map.insert("...##", '#');
-
-  // #.### => #
-  map.insert("#.###", '#');
-
-  // #..## => #
-  map.insert("#..##", '#');
+  fn parse(input: &str) -> Result<Garden, Error> {
+    let mut lines = numbered_lines(input);
+    let initial_state = match lines.next() {
+      Some((number, line)) => parse_initial_state(line).map_err(|err| err.at_line(number, line))?,
+      None => Err(ParseError::new("Input is empty"))?
+    };
 
-  // ..### => #
-  map.insert("..###", '#');
+    let rules = lines
+      .map(|(number, line)| parse_rule(line).map_err(|err| err.at_line(number, line)))
+      .collect::<Result<Rules, ParseError>>()?;
 
-  // ####. => #
-  map.insert("####.", '#');
+    Ok(Garden { initial_state, rules })
+  }
 
-  // .#.#. => #
-  map.insert(".#.#.", '#');
+  fn part1(garden: &Garden) -> Result<Answer, Error> {
+    let result = iterate(garden, 20).0;
 
-  // ###.# => #
-  map.insert("###.#", '#');
+    Ok(Answer::from(result))
+  }
 
-  // ##### => #
-  map.insert("#####", '#');
+  fn part2(garden: &Garden) -> Result<Answer, Error> {
+    let iterations = 300;
+    let (r, d) = iterate(garden, iterations);
 
-  // .#... => #
-  map.insert(".#...", '#');
+    let result = (50_000_000_000 - 300) / 100 * d + r;
 
-  map
+    Ok(Answer::from(result))
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn plants_may_spread_to_the_left() {
+    // The plant moves two pots to the left every generation.
+    let garden = Day12::parse("initial state: #\n\n....# => #\n").unwrap();
+    assert_eq!(Day12::part1(&garden).unwrap(), Answer::Number(-40));
+    assert_eq!(iterate(&garden, 200), (-400, -200));
+  }
+
+  #[test]
+  fn parse_rejects_invalid_rules() {
+    assert!(Day12::parse("initial state: #..#\n\n#..#. => x\n").is_err());
+    assert!(Day12::parse("initial state: #..#\n\n#..# => #\n").is_err());
+    assert!(Day12::parse("#..#\n").is_err());
+  }
+
   #[test]
   fn check_problem1() {
-    assert_eq!(problem1(), 3725);
+    let garden = Day12::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day12::part1(&garden).unwrap(), Answer::Number(3725));
   }
 
   #[test]
   fn check_problem2() {
-    assert_eq!(problem2(), 3100000000293);
+    let garden = Day12::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day12::part2(&garden).unwrap(), Answer::Number(3100000000293));
   }
 }
\ No newline at end of file
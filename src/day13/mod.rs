@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
-use super::utils::{preprocess_input, Error};
+use super::grid::{Grid, Point};
+use super::utils::Error;
+use super::solution::{Answer, Solution};
 
-type Position = (i32, i32);
+type Position = Point;
+type Tracks = Grid<char>;
 
 #[derive(Debug, Clone)]
 enum Turn {
@@ -37,23 +40,21 @@ impl Cart {
   }
 }
 
-fn load_tracks() -> Vec<Vec<char>> {
-  let input = include_str!("./data/input.txt");
-
-  preprocess_input(input)
-    .iter()
-    .map(|v| v.chars().collect())
-    .collect()
+fn load_tracks(input: &str) -> Result<Tracks, Error> {
+  Ok(Grid::parse(input, Ok)?)
 }
 
 fn new_pos(p: Position, d: Position) -> Position {
   (p.0 + d.0, p.1 + d.1)
 }
 
-fn drive(cart: Cart, tracks: &Vec<Vec<char>>) -> Cart {
+fn drive(cart: Cart, tracks: &Tracks) -> Result<Cart, Error> {
   let pos = new_pos(cart.position, cart.direction);
 
-  let next = tracks[pos.1 as usize][pos.0 as usize];
+  let next = match tracks.get(pos) {
+    Some(&v) => v,
+    None => Err(Error::new("Cart left the track"))?
+  };
   let (direction, next_turn) = match (next, cart.direction, cart.next_turn) {
     ('/', (0, 1), t) => ((-1, 0), t),
     ('/', (0, -1), t) => ((1, 0), t),
@@ -75,24 +76,18 @@ fn drive(cart: Cart, tracks: &Vec<Vec<char>>) -> Cart {
     (_, d, t) => (d, t)
   };
 
-  Cart::from_cart(pos, direction, next_turn, cart.broken, cart.tick)
+  Ok(Cart::from_cart(pos, direction, next_turn, cart.broken, cart.tick))
 }
 
-fn find_carts(tracks: &Vec<Vec<char>>) -> Vec<Cart> {
-  let mut carts = vec![];
-
-  for (y, line) in tracks.iter().enumerate() {
-    for (x, &c) in line.iter().enumerate() {
-      if c == 'v' || c == '>' || c == '^' || c == '<' {
-        carts.push(Cart::new((x as i32, y as i32), c));
-      }
-    }
-  }
-
-  carts
+fn find_carts(tracks: &Tracks) -> Vec<Cart> {
+  tracks
+    .iter()
+    .filter(|(_, c)| "v>^<".contains(**c))
+    .map(|(position, &c)| Cart::new(position, c))
+    .collect()
 }
 
-fn find_collision(carts: &Vec<Cart>) -> Vec<usize> {
+fn find_collision(carts: &[Cart]) -> Vec<usize> {
   let mut map = BTreeMap::new();
 
   for (index, cart) in carts.iter().enumerate() {
@@ -103,43 +98,34 @@ fn find_collision(carts: &Vec<Cart>) -> Vec<usize> {
     map
       .entry(cart.position)
       .and_modify(|v: &mut Vec<usize> | v.push(index))
-      .or_insert(vec![index]);
+      .or_insert_with(|| vec![index]);
   }
 
-  let result = vec![];
   for (_key, value) in map {
     if value.len() > 1 {
       return value;
     }
   }
 
-  result
+  vec![]
 }
 
-pub fn problem1() -> Result<Position, Error> {
-  let tracks = load_tracks();
-  let mut carts = find_carts(&tracks);
+fn first_collision(tracks: &Tracks) -> Result<Position, Error> {
+  let mut carts = find_carts(tracks);
 
   for i in 0..200 {
-    let carts_copy: Vec<Cart> = carts
-      .iter()
-      .map(|v| v.clone())
-      .collect();
-
-    for y in 0..150 {
-      for x in 0..150 {
-        for (idx, c) in carts_copy.iter().enumerate() {
-          let (cx, cy) = c.position;
-          if cx == x && cy == y && c.tick < i {
-            carts[idx] = drive(c.clone(), &tracks);
-            carts[idx].tick = i;
-
-            let collided = find_collision(&carts);
-            if collided.len() > 0 {
-              let position = carts[collided[0] as usize].position;
-              println!("First collision detected at: {:?}", position);
-              return Ok(position)
-            }
+    let carts_copy: Vec<Cart> = carts.to_vec();
+
+    for position in tracks.points() {
+      for (idx, c) in carts_copy.iter().enumerate() {
+        if c.position == position && c.tick < i {
+          carts[idx] = drive(c.clone(), tracks)?;
+          carts[idx].tick = i;
+
+          let collided = find_collision(&carts);
+          if !collided.is_empty() {
+            let position = carts[collided[0]].position;
+            return Ok(position)
           }
         }
       }
@@ -149,52 +135,37 @@ pub fn problem1() -> Result<Position, Error> {
   Err(Error::new("No collision occurred"))
 }
 
-fn print_carts(carts: &Vec<Cart>) {
-  for cart in carts {
-    println!("{:?}", cart);
-  }
-}
-
-pub fn problem2() -> Result<Position, Error> {
-  let tracks = load_tracks();
-  let mut carts = find_carts(&tracks);
+fn last_cart(tracks: &Tracks) -> Result<Position, Error> {
+  let mut carts = find_carts(tracks);
   let mut last_cart: Position = (0, 0);
 
   for i in 0..15000 {
-    for y in 0..150 {
-      for x in 0..150 {
-        let num_carts = carts.len();
-        for idx in 0..num_carts {
-          let c = carts[idx].clone();
-          let (cx, cy) = c.position;
-          if cx == x && cy == y && !c.broken && c.tick < i {
-            carts[idx] = drive(c, &tracks);
-            carts[idx].tick = i;
-
-            let collided = find_collision(&carts);
-            for q in collided {
-              carts[q].broken = true;
-            }
+    for position in tracks.points() {
+      let num_carts = carts.len();
+      for idx in 0..num_carts {
+        let c = carts[idx].clone();
+        if c.position == position && !c.broken && c.tick < i {
+          carts[idx] = drive(c, tracks)?;
+          carts[idx].tick = i;
+
+          let collided = find_collision(&carts);
+          for q in collided {
+            carts[q].broken = true;
           }
         }
       }
     }
 
     let unbroken_carts = carts
-      .iter()
-      .cloned()
-      .filter(|v| !v.broken)
+      .iter().filter(|&v| !v.broken).cloned()
       .collect::<Vec<Cart>>();
 
     if unbroken_carts.len() == 1 {
-      println!("Only one cart left!");
-      print_carts(&unbroken_carts);
       last_cart = unbroken_carts[0].position;
       break;
     }
 
-    if unbroken_carts.len() == 0 {
-      println!("No carts left!");
+    if unbroken_carts.is_empty() {
       break;
     }
   }
@@ -202,20 +173,55 @@ pub fn problem2() -> Result<Position, Error> {
   Ok(last_cart)
 }
 
-#[cfg(not(debug_assertions))]
+pub struct Day13;
+
+impl Solution for Day13 {
+  type Input = Grid<char>;
+
+  const DAY: u8 = 13;
+  const TITLE: &'static str = "Mine Cart Madness";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Grid<char>, Error> {
+    load_tracks(input)
+  }
+
+  fn part1(tracks: &Grid<char>) -> Result<Answer, Error> {
+    let (x, y) = first_collision(tracks)?;
+
+    Ok(Answer::from(format!("{},{}", x, y)))
+  }
+
+  fn part2(tracks: &Grid<char>) -> Result<Answer, Error> {
+    let (x, y) = last_cart(tracks)?;
+
+    Ok(Answer::from(format!("{},{}", x, y)))
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn carts_leaving_the_track_are_errors() {
+    let tracks = Day13::parse("/>\n").unwrap();
+    assert_eq!(Day13::part1(&tracks).unwrap_err().to_string(), "Cart left the track");
+    assert_eq!(Day13::part2(&tracks).unwrap_err().to_string(), "Cart left the track");
+  }
+
   #[cfg(not(debug_assertions))]
   #[test]
   fn check_problem1() {
-    assert_eq!(problem1().unwrap(), (64, 57));
+    let tracks = Day13::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(first_collision(&tracks).unwrap(), (64, 57));
   }
 
   #[cfg(not(debug_assertions))]
   #[test]
   fn check_problem2() {
-    assert_eq!(problem2().unwrap(), (136, 8));
+    let tracks = Day13::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(last_cart(&tracks).unwrap(), (136, 8));
   }
 }
\ No newline at end of file
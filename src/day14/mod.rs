@@ -1,46 +1,29 @@
-use super::utils::{ParseError};
-use std::num::ParseIntError;
+use super::utils::{parse_number, Error};
+use super::solution::{Answer, Solution};
 use std::vec::Vec;
 
-fn initialize() -> Result<Vec<u8>, ParseError> {
-  let input = "37";
-
-  let result = input
-    .chars()
-    .map(|c| c.to_string().parse::<u8>())
-    .collect::<Result<Vec<u8>, ParseIntError>>()?;
-
-  Ok(result)
-}
+// The two elves always start with the recipes 3 and 7.
+const INITIAL_SCORES: [u8; 2] = [3, 7];
 
 fn concat(numbers: &[u8]) -> String {
   numbers.iter().map(|n| n.to_string()).collect::<String>()
 }
 
-fn compare(v1: &[u8], v2: &[u8]) -> bool {
-  if v1.len() != v2.len() {
-    return false;
-  }
+struct Scoreboard {
+  pub scores: Vec<u8>,
+  current: (usize, usize),
+}
 
-  let l = v1.len();
-  for i in 0..l {
-    if v1[i] != v2[i] {
-      return false;
-    }
+impl Scoreboard {
+  pub fn new() -> Scoreboard {
+    let scores = INITIAL_SCORES.to_vec();
+    Scoreboard { scores, current: (0, 1) }
   }
-  true
-}
 
-pub fn problems() -> Result<(usize, usize), ParseError> {
-  let mut scores = initialize()?;
-  let pattern: Vec<u8> = vec![2, 0, 9, 2, 3, 1];
+  pub fn step(&mut self) {
+    let scores = &mut self.scores;
+    let current = &mut self.current;
 
-  let mut current: (usize, usize) = (0, 1);
-  let mut solution_2 = 0;
-  for i in 0..100_000_000_000usize {
-    if i % 1_000_000 == 0 {
-      println!("Iteration {}", i);
-    }
     let new_score = scores[current.0] + scores[current.1];
     if new_score > 9 {
       let next_1 = new_score / 10;
@@ -53,37 +36,110 @@ pub fn problems() -> Result<(usize, usize), ParseError> {
 
     current.0 = (current.0 + 1 + scores[current.0] as usize) % scores.len();
     current.1 = (current.1 + 1 + scores[current.1] as usize) % scores.len();
+  }
+}
+
+// One byte each, the puzzle inputs need about 20 million.
+const MAX_RECIPES: usize = 100_000_000;
+
+fn scores_after(recipes: usize) -> Result<usize, Error> {
+  if recipes > MAX_RECIPES - 10 {
+    Err(Error::new(&format!("Can't make more than {} recipes", MAX_RECIPES)))?
+  }
+
+  let mut board = Scoreboard::new();
+
+  while board.scores.len() < recipes + 10 {
+    board.step();
+  }
+
+  let result = parse_number::<usize>(&concat(&board.scores[recipes..recipes + 10]))?;
+  Ok(result)
+}
+
+fn recipes_before(pattern: &[u8]) -> Result<usize, Error> {
+  let mut board = Scoreboard::new();
 
+  while board.scores.len() < MAX_RECIPES {
+    board.step();
+
+    let scores = &board.scores;
     let l = scores.len();
     let pl = pattern.len();
     if l > pl + 2 {
       let first = &scores[l-pl-1..l-1];
-      if compare(first, &pattern[..]) {
-        solution_2 = l - pl - 1;
-        break;
+      if first == pattern {
+        return Ok(l - pl - 1);
       }
 
       let second = &scores[l-pl..l];
-      if compare(second, &pattern[..]) {
-        solution_2 = l - pl - 1;
-        break;
+      if second == pattern {
+        return Ok(l - pl);
       }
     }
   }
 
-  let solution_1 = concat(&scores[209231..209241]).parse::<usize>()?;
-  println!("Solution for problem 1: {}", solution_1);
-  println!("Solution for problem 2: {}", solution_2);
+  Err(Error::new(&format!("Pattern not found in the first {} recipes", MAX_RECIPES)))
+}
+
+pub struct Recipes {
+  pub count: usize,
+  pub digits: Vec<u8>,
+}
+
+pub struct Day14;
+
+impl Solution for Day14 {
+  type Input = Recipes;
+
+  const DAY: u8 = 14;
+  const TITLE: &'static str = "Chocolate Charts";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Recipes, Error> {
+    let input = input.trim();
+    let count = parse_number::<usize>(input)?;
+    let digits = input
+      .chars()
+      .map(|c| c.to_digit(10).map(|d| d as u8))
+      .collect::<Option<Vec<u8>>>()
+      .ok_or_else(|| Error::new("Input must only contain digits"))?;
+
+    Ok(Recipes { count, digits })
+  }
+
+  fn part1(recipes: &Recipes) -> Result<Answer, Error> {
+    Ok(Answer::from(scores_after(recipes.count)?))
+  }
 
-  Ok((solution_1, solution_2))
+  fn part2(recipes: &Recipes) -> Result<Answer, Error> {
+    Ok(Answer::from(recipes_before(&recipes.digits)?))
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn check_examples() {
+    assert_eq!(scores_after(9).unwrap(), 5158916779);
+    assert_eq!(scores_after(2018).unwrap(), 5941429882);
+    assert_eq!(recipes_before(&[5, 1, 5, 8, 9]).unwrap(), 9);
+    assert_eq!(recipes_before(&[5, 9, 4, 1, 4]).unwrap(), 2018);
+  }
+
+  #[test]
+  fn recipes_are_limited() {
+    let recipes = Day14::parse("4294967295\n").unwrap();
+    assert_eq!(Day14::part1(&recipes).err().unwrap().to_string(), "Can't make more than 100000000 recipes");
+  }
+
   #[test]
   fn check_results() {
-    assert_eq!(problems().unwrap(), (6126491027, 20191616));
+    let recipes = Day14::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day14::part1(&recipes).unwrap(), Answer::Number(6126491027));
+    assert_eq!(Day14::part2(&recipes).unwrap(), Answer::Number(20191616));
   }
-}
\ No newline at end of file
+}
@@ -1,15 +1,15 @@
 use std::str::FromStr;
 use super::utils::Error;
+use super::solution::{Answer, Solution};
 
-mod cave;
-mod unit;
+pub mod cave;
+pub mod unit;
 
-pub fn problem1() -> Result<i32, Error> {
-  let input = include_str!("./data/input.txt");
-  let mut cave = cave::Cave::from_str(input)?;
+fn outcome(cave: &cave::Cave) -> Result<i32, Error> {
+  let mut cave = cave.clone();
 
   for i in 0..1000 {
-    if i % 10 == 0 {
+    if cave.debug && i % 10 == 0 {
       println!("After {} rounds:", i);
       cave.print_with_units();
     }
@@ -21,29 +21,25 @@ pub fn problem1() -> Result<i32, Error> {
 
     if number_of_elfs == 0 || number_of_goblins == 0 {
       let remaining_health: i32 = cave.units.iter().map(|v| v.health).sum();
-      let result = remaining_health * i;
-      println!("After the final round:");
-      cave.print_with_units();
-      println!("Finished! Result is {} * {} = {}", remaining_health, i, result);
-      return Ok(result);
+      if cave.debug {
+        println!("After the final round:");
+        cave.print_with_units();
+      }
+      return Ok(remaining_health * i);
     }
   }
 
-  Ok(0)
+  Err(Error::new("Combat did not end"))
 }
 
-pub fn problem2() -> Result<i32, Error> {
-  let input = include_str!("./data/input.txt");
+fn outcome_without_elf_losses(initial: &cave::Cave) -> Result<i32, Error> {
   let mut number_of_surviving_elves = 0;
-  let mut remaining_health = 0;
   let mut result = 0;
-  let mut rounds = 0;
 
   for attack_power in 4..50 {
-    let mut cave = cave::Cave::from_str(input)?;
+    let mut cave = initial.clone();
     let mut number_of_elves = 0;
 
-    println!("Simulating with attack power {} for the elves", attack_power);
     for (index, unit) in cave.units.clone().iter().enumerate() {
       if unit.kind == unit::Kind::Elf {
         number_of_elves += 1;
@@ -58,22 +54,41 @@ pub fn problem2() -> Result<i32, Error> {
       number_of_surviving_elves = cave.units.iter().filter(|v| v.kind == unit::Kind::Elf).count();
 
       if number_of_surviving_elves == 0 || number_of_goblins == 0 {
-        remaining_health = cave.units.iter().map(|v| v.health).sum();
+        let remaining_health: i32 = cave.units.iter().map(|v| v.health).sum();
         result = remaining_health * i;
-        rounds = i;
         break;
       }
     }
 
-    println!("Simulation finished! Result is {} * {} = {}", remaining_health, rounds, result);
-    println!("Number of elves died: {}", number_of_elves - number_of_surviving_elves);
-
     if number_of_surviving_elves == number_of_elves {
       return Ok(result);
     }
   }
 
-  Ok(0)
+  Err(Error::new("The elves cannot win without losses"))
+}
+
+pub struct Day15;
+
+impl Solution for Day15 {
+  type Input = cave::Cave;
+
+  const DAY: u8 = 15;
+  const TITLE: &'static str = "Beverage Bandits";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<cave::Cave, Error> {
+    Ok(cave::Cave::from_str(input)?)
+  }
+
+  fn part1(cave: &cave::Cave) -> Result<Answer, Error> {
+    Ok(Answer::from(outcome(cave)?))
+  }
+
+  fn part2(cave: &cave::Cave) -> Result<Answer, Error> {
+    Ok(Answer::from(outcome_without_elf_losses(cave)?))
+  }
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 use std::collections::{BTreeSet, BTreeMap};
 use std::cmp::min;
 use super::cave::{Board, Cave, Tile};
+use super::super::grid::Point;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
 pub enum Kind {
@@ -8,7 +9,7 @@ pub enum Kind {
   Goblin
 }
 
-pub type Position = (usize, usize);
+pub type Position = Point;
 
 fn log(s: String, out: bool) {
   if out {
@@ -22,16 +23,14 @@ pub struct Unit {
   pub kind: Kind,
   pub attack: i32,
   pub health: i32,
-  pub tick: u64,
 }
 
 impl Unit {
   pub fn new(position: Position, kind: Kind) -> Self {
     let attack = 3;
     let health = 200;
-    let tick = 0;
 
-    Unit { position, kind, attack, health, tick }
+    Unit { position, kind, attack, health }
   }
 
   fn reachable_positions(&self, board: &Board) -> BTreeMap<Position, u32> {
@@ -47,10 +46,10 @@ impl Unit {
     while last_count != result.len() {
       last_count = result.len();
       let mut additional_positions = BTreeSet::new();
-      for (pos, _) in &result {
+      for pos in result.keys() {
         let range = Unit::in_range_of_position(*pos, board);
         for p in range {
-          if board.get(&p) == Some(&Tile::Floor) {
+          if board.get(p) == Some(&Tile::Floor) {
             additional_positions.insert(p);
           }
         }
@@ -66,29 +65,10 @@ impl Unit {
   }
 
   fn in_range_of_position(position: Position, board: &Board) -> Vec<Position> {
-    let mut result = vec![];
-
-    let pos = (position.0 - 1, position.1);
-    if board.get(&pos) == Some(&Tile::Floor) {
-      result.push(pos);
-    }
-
-    let pos = (position.0, position.1 - 1);
-    if board.get(&pos) == Some(&Tile::Floor) {
-      result.push(pos);
-    }
-
-    let pos = (position.0 + 1, position.1);
-    if board.get(&pos) == Some(&Tile::Floor) {
-      result.push(pos);
-    }
-
-    let pos = (position.0, position.1 + 1);
-    if board.get(&pos) == Some(&Tile::Floor) {
-      result.push(pos);
-    }
-
-    result
+    board
+      .neighbours4(position)
+      .filter(|&pos| board[pos] == Tile::Floor)
+      .collect()
   }
 
   fn in_range(&self, board: &Board) -> Vec<Position> {
@@ -112,24 +92,24 @@ impl Unit {
 
   fn find_closest_positions(target_positions: &Vec<Position>, distance_map: &BTreeMap<Position, u32>) -> (Vec<Position>, u32) {
     let mut target_distance_map = BTreeMap::new();
-    let mut closest_distance = std::u32::MAX;
+    let mut closest_distance = u32::MAX;
     for pos in target_positions {
-      if !distance_map.contains_key(&pos) {
+      if !distance_map.contains_key(pos) {
         continue;
       }
 
-      let distance = distance_map[&pos];
+      let distance = distance_map[pos];
       target_distance_map
         .entry(distance)
-        .and_modify(|v: &mut Vec<Position>| v.push(pos.clone()))
-        .or_insert(vec![pos.clone()]);
+        .and_modify(|v: &mut Vec<Position>| v.push(*pos))
+        .or_insert(vec![*pos]);
       if distance < closest_distance {
         closest_distance = distance;
       }
     }
 
     if target_distance_map.is_empty() {
-      return (vec![], std::u32::MAX);
+      return (vec![], u32::MAX);
     }
 
     (target_distance_map[&closest_distance].clone(), closest_distance)
@@ -137,9 +117,7 @@ impl Unit {
 
   pub fn move_unit(&self, cave: &Cave) -> Option<Position> {
     let enemies: Vec<Unit> = cave.units
-      .iter()
-      .cloned()
-      .filter(|v| v.kind != self.kind)
+      .iter().filter(|&v| v.kind != self.kind).cloned()
       .collect();
 
     if enemies.is_empty() {
@@ -172,10 +150,7 @@ impl Unit {
     // find the closest ones
     let (target_distances, _) = Unit::find_closest_positions(&target_positions, &reachable_positions);
     log(format!("closest positions: {:?}", target_distances), cave.debug);
-    let target = match Unit::first_position(&target_distances) {
-      Some(v) => v,
-      None => return None
-    };
+    let target = Unit::first_position(&target_distances)?;
 
     log(format!("Target position: {:?}", target), cave.debug);
 
@@ -205,7 +180,7 @@ impl Unit {
     }
 
     // find enemies with the lowest number of health
-    let mut lowest_health = std::i32::MAX;
+    let mut lowest_health = i32::MAX;
     let mut enemy_health_map = BTreeMap::new();
     for entry in enemies_in_range {
       let (_, enemy) = entry;
@@ -223,22 +198,12 @@ impl Unit {
     // find the position of the enemy to attack
     let attack_targets = &enemy_health_map[&lowest_health];
     let positions: Vec<Position> = attack_targets.iter().map(|(_, v)| v.position).collect();
-    let position_to_attack = match Unit::first_position(&positions) {
-      Some(v) => v,
-      None => return None
-    };
+    let position_to_attack = Unit::first_position(&positions)?;
 
     // find the index of the enemy on that position
-    let target: Vec<(usize, &Unit)> = attack_targets.iter().cloned().filter(|(_, v)| v.position == position_to_attack).collect();
-
-    if target.len() > 1 {
-      panic!("Two units in the same spot?!?");
-    }
-
-    if target.is_empty() {
-      return None;
-    }
+    // Units are placed from the map, so no two of them share a position.
+    let (target, _) = attack_targets.iter().find(|(_, v)| v.position == position_to_attack)?;
 
-    Some((target[0].0, self.attack))
+    Some((*target, self.attack))
   }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
-mod ops;
-mod data;
-pub mod problems;
\ No newline at end of file
+pub mod data;
+pub mod problems;
+pub mod validate;
+
+pub use self::problems::Day16;
@@ -1,169 +1,194 @@
-use std::collections::BTreeMap;
 use super::data::*;
-use super::super::utils::{preprocess_input, ParseError, Error};
+use super::super::constraint::{Solver, Unsolvable};
+use super::super::elfcode::{opcode, Instruction, Opcode, Program};
+use super::super::utils::{numbered_lines, parse_number, ParseError, Error};
+use super::super::solution::{Answer, Solution};
 use regex::{Captures, Regex};
 
-fn state_from_capture(capture: &Captures) -> Result<State, ParseError> {
-  let r0 = capture[1].parse::<i32>()?;
-  let r1 = capture[2].parse::<i32>()?;
-  let r2 = capture[3].parse::<i32>()?;
-  let r3 = capture[4].parse::<i32>()?;
+fn registers_from_capture(capture: &Captures) -> Result<Vec<i32>, ParseError> {
+  let r0 = parse_number::<i32>(&capture[1])?;
+  let r1 = parse_number::<i32>(&capture[2])?;
+  let r2 = parse_number::<i32>(&capture[3])?;
+  let r3 = parse_number::<i32>(&capture[4])?;
 
-  Ok(State::new(r0, r1, r2, r3))
+  Ok(vec![r0, r1, r2, r3])
 }
 
 fn instruction_from_capture(capture: &Captures) -> Result<[i32; 4], ParseError> {
-  let r0 = capture[1].parse::<i32>()?;
-  let r1 = capture[2].parse::<i32>()?;
-  let r2 = capture[3].parse::<i32>()?;
-  let r3 = capture[4].parse::<i32>()?;
+  let r0 = parse_number::<i32>(&capture[1])?;
+  let r1 = parse_number::<i32>(&capture[2])?;
+  let r2 = parse_number::<i32>(&capture[3])?;
+  let r3 = parse_number::<i32>(&capture[4])?;
 
   Ok([r0, r1, r2, r3])
 }
 
-fn parse_state(s: &str) -> Result<State, ParseError> {
+fn parse_registers(s: &str) -> Result<Vec<i32>, ParseError> {
   lazy_static!{
-    static ref RE_state: Regex = Regex::new(r"\[(\d+), (\d+), (\d+), (\d+)\]").unwrap();
+    static ref RE_STATE: Regex = Regex::new(r"\[(\d+), (\d+), (\d+), (\d+)\]").unwrap();
   }
 
-  let capture = match RE_state.captures(s) {
+  let capture = match RE_STATE.captures(s) {
     Some(c) => c,
     None => Err(ParseError::new("Could not parse state"))?
   };
 
-  state_from_capture(&capture)
+  registers_from_capture(&capture)
 }
 
 fn parse_instruction(s: &str) -> Result<[i32; 4], ParseError> {
   lazy_static!{
-    static ref RE_instruction: Regex = Regex::new(r"(\d+) (\d+) (\d+) (\d+)").unwrap();
+    static ref RE_INSTRUCTION: Regex = Regex::new(r"(\d+) (\d+) (\d+) (\d+)").unwrap();
   }
 
-  let capture = match RE_instruction.captures(s) {
+  let capture = match RE_INSTRUCTION.captures(s) {
     Some(c) => c,
     None => Err(ParseError::new("Could not parse instruction"))?
   };
 
-  instruction_from_capture(&capture)
+  let instruction = instruction_from_capture(&capture)?;
+  if !(0..16).contains(&instruction[0]) {
+    Err(ParseError::new(&format!("Opcode {} is out of range", instruction[0])))?
+  }
+
+  Ok(instruction)
+}
+
+pub struct Sample {
+  // The line of the manual the sample starts at.
+  pub line: usize,
+  pub before: Vec<i32>,
+  pub instruction: [i32; 4],
+  pub after: Vec<i32>,
 }
 
-fn find_matching_ops(results: &BTreeMap<&'static str, Option<State>>, expected_state: &State) -> Vec<&'static str> {
-  let mut valid_ops = vec![];
+pub struct Manual {
+  pub samples: Vec<Sample>,
+  pub program: Vec<[i32; 4]>,
+}
 
-  for (op, r) in results {
-    let state = match r {
-      Some(v) => v,
-      None => continue
-    };
+fn parse_manual(input: &str) -> Result<Manual, ParseError> {
+  lazy_static!{
+    static ref RE_BEFORE: Regex = Regex::new(r"Before:").unwrap();
+  }
+
+  let mut line_iterator = numbered_lines(input);
+  let mut samples = vec![];
+  let mut program = vec![];
+  while let Some((number, line)) = line_iterator.next() {
+    if RE_BEFORE.is_match(line) {
+      let before = parse_registers(line).map_err(|err| err.at_line(number, line))?;
+      let instruction = match line_iterator.next() {
+        Some((n, l)) => parse_instruction(l).map_err(|err| err.at_line(n, l))?,
+        None => Err(ParseError::new("Expected instruction").at_line(number, line))?
+      };
+
+      let after = match line_iterator.next() {
+        Some((n, l)) => parse_registers(l).map_err(|err| err.at_line(n, l))?,
+        None => Err(ParseError::new("Expected state after instruction").at_line(number, line))?
+      };
 
-    if state == expected_state {
-      valid_ops.push(*op);
+      samples.push(Sample { line: number, before, instruction, after });
+    } else {
+      program.push(parse_instruction(line).map_err(|err| err.at_line(number, line))?);
     }
   }
 
-  valid_ops
+  Ok(Manual { samples, program })
 }
 
-fn intersect(a: &Vec<&'static str>, b: &Vec<&'static str>) -> Vec<&'static str> {
-  let mut intersection = vec![];
+pub fn matching_ops(sample: &Sample) -> Vec<Opcode> {
+  let [_, a, b, c] = sample.instruction;
+
+  opcode::ALL
+    .iter()
+    .cloned()
+    .filter(|&op| {
+      let mut state = State::with_registers(sample.before.clone());
+      state.execute(&Instruction::new(op, a, b, c)).is_ok() && state.registers == sample.after
+    })
+    .collect()
+}
 
-  for entry in a {
-    if b.contains(entry) {
-      intersection.push(*entry);
-    }
+// Every sample limits its opcode number to the instructions that match it.
+// Samples are numbered from 1 in the order they appear in the manual.
+pub fn opcode_solver(samples: &[Sample]) -> Solver<usize, Opcode, usize> {
+  let mut solver = Solver::new(0..16, opcode::ALL.iter().cloned());
+  for (index, sample) in samples.iter().enumerate() {
+    solver.restrict(sample.instruction[0] as usize, matching_ops(sample), index + 1);
   }
 
-  intersection
+  solver
 }
 
-pub fn problem1() -> Result<Vec<Vec<&'static str>>, Error> {
-  let input = include_str!("./data/input.txt");
-  let lines = preprocess_input(input);
+fn list<T: ToString>(items: &[T]) -> String {
+  items.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(", ")
+}
 
-  lazy_static!{
-    static ref RE_before: Regex = Regex::new(r"Before:").unwrap();
-  }
-
-  let mut line_iterator = lines.iter();
-  let mut number_of_samples_match_three_or_more = 0;
-  let mut total_samples = 0;
-  let mut opcode_map = vec![State::all_ops(); 16];
-  while let Some(line) = line_iterator.next() {
-    if RE_before.is_match(line) {
-      let before_state = parse_state(line)?;
-      let instruction_line = match line_iterator.next() {
-        Some(l) => l,
-        None => Err(ParseError::new("Expected instruction"))?
+pub fn explain(unsolvable: Unsolvable<usize, Opcode, usize>) -> Error {
+  match unsolvable {
+    Unsolvable::Contradictory { keys, values, evidence } => {
+      let problem = if values.is_empty() {
+        format!("no instruction fits opcode {}", list(&keys))
+      } else {
+        format!("opcodes {} can only be {}", list(&keys), list(&values))
       };
-      let instruction = parse_instruction(instruction_line)?;
+      Error::new(&format!("Samples contradict each other: {}, see samples {}", problem, list(&evidence)))
+    },
+    Unsolvable::Ambiguous { keys, first, second, evidence } => {
+      let options = keys
+        .iter()
+        .map(|k| format!("{} could be {} or {}", k, first[k], second[k]))
+        .collect::<Vec<String>>();
+      Error::new(&format!(
+        "Samples are not sufficient to identify all opcodes: {}, see samples {}",
+        options.join(", "), if evidence.is_empty() { "none".to_string() } else { list(&evidence) }))
+    },
+  }
+}
 
-      let after_state_line = match line_iterator.next() {
-        Some(l) => l,
-        None => Err(ParseError::new("Expected instruction"))?
-      };
-      let expected_after_state = parse_state(after_state_line)?;
+fn identify(samples: &[Sample]) -> Result<Vec<Opcode>, Error> {
+  let opcodes = opcode_solver(samples).solve().map_err(explain)?;
+  Ok(opcodes.values().cloned().collect())
+}
 
-      let results = before_state.apply_all_ops(instruction[1], instruction[2], instruction[3]);
-      let matching_ops = find_matching_ops(&results, &expected_after_state);
+pub struct Day16;
 
-      opcode_map[instruction[0] as usize] = intersect(&opcode_map[instruction[0] as usize], &matching_ops);
+impl Solution for Day16 {
+  type Input = Manual;
 
-      total_samples += 1;
-      if matching_ops.len() >= 3 {
-        number_of_samples_match_three_or_more += 1;
-      }
-    }
+  const DAY: u8 = 16;
+  const TITLE: &'static str = "Chronal Classification";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Manual, Error> {
+    Ok(parse_manual(input)?)
   }
 
-  println!("Total number of samples: {}", total_samples);
-  println!("Of those, {} match three or more ops", number_of_samples_match_three_or_more);
+  fn part1(manual: &Manual) -> Result<Answer, Error> {
+    let number_of_samples_match_three_or_more = manual.samples
+      .iter()
+      .filter(|sample| matching_ops(sample).len() >= 3)
+      .count();
 
-  Ok(opcode_map)
-}
+    Ok(Answer::from(number_of_samples_match_three_or_more))
+  }
 
-pub fn problem2() -> Result<i32, Error> {
-  let mut opcode_map = problem1()?;
+  fn part2(manual: &Manual) -> Result<Answer, Error> {
+    let opcodes = identify(&manual.samples)?;
 
-  let mut reduced = false;
-  while !reduced {
-    let singles = opcode_map
+    let instructions = manual.program
       .iter()
-      .enumerate()
-      .filter(|(_, value)| value.len() == 1)
-      .map(|(index, _)| index)
-      .collect::<Vec<usize>>();
-
-    for i in singles {
-      let instruction = opcode_map[i as usize][0];
-      for k in 0..opcode_map.len() {
-        if k != i {
-          opcode_map[k] = opcode_map[k].iter().cloned().filter(|v| v != &instruction).collect::<Vec<&str>>();
-        }
-      }
-    }
-    reduced = opcode_map.iter().map(|v| v.len()).sum::<usize>() <= 16;
-  }
-
-  let opcodes = opcode_map.iter().map(|v| v[0]).collect::<Vec<&str>>();
+      .map(|&[op, a, b, c]| Instruction::new(opcodes[op as usize], a, b, c))
+      .collect();
+    let program = Program::new(None, instructions);
 
-  let input = include_str!("./data/program.txt");
-  let program = preprocess_input(input);
+    let mut state = State::new(REGISTERS);
+    state.run(&program)?;
 
-  let mut state = State::new(0, 0, 0, 0);
-  for line in program {
-    let instruction = parse_instruction(line)?;
-    let op = opcodes[instruction[0] as usize];
-    state = match state.apply(op, instruction[1], instruction[2], instruction[3])? {
-      Some(s) => s,
-      None => Err(Error::new("Illegal instruction?"))?
-    };
+    Ok(Answer::from(state.registers[0]))
   }
-
-  println!("final state: {:?}", state);
-  let result = state.registers[0];
-  println!("Result: {}", result);
-
-  Ok(result)
 }
 
 #[cfg(test)]
@@ -172,6 +197,53 @@ mod tests {
 
   #[test]
   fn check_problem2() {
-    assert_eq!(problem2().unwrap(), 674);
+    let manual = Day16::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day16::part2(&manual).unwrap(), Answer::Number(674));
+  }
+
+  #[test]
+  fn overflowing_samples_do_not_match() {
+    // The wrapped product would fit, but the device checks its arithmetic.
+    let sample = Sample { line: 1, before: vec![i32::MAX, 2, 0, 0], instruction: [0, 0, 1, 2], after: vec![i32::MAX, 2, -2, 0] };
+    assert_eq!(matching_ops(&sample), vec![]);
+  }
+
+  #[test]
+  fn opcodes_out_of_range_are_rejected() {
+    let err = Day16::parse("16 0 0 0\n").err().unwrap();
+    assert_eq!(err.to_string(), "line 1: Opcode 16 is out of range in \"16 0 0 0\"");
+  }
+
+  #[test]
+  fn insufficient_samples_are_reported() {
+    let manual = Day16::parse("0 1 2 3\n").unwrap();
+    let err = Day16::part2(&manual).err().unwrap().to_string();
+    assert!(err.starts_with("Samples are not sufficient to identify all opcodes: "), "{}", err);
+    assert!(err.ends_with(", see samples none"), "{}", err);
+  }
+
+  #[test]
+  fn contradicting_samples_are_reported() {
+    // Both samples use opcode 3, but no instruction fits both of them.
+    let input = "Before: [0, 7, 0, 0]\n\
+                 3 1 0 2\n\
+                 After:  [0, 7, 1, 0]\n\
+                 \n\
+                 Before: [2, 1, 0, 0]\n\
+                 3 1 1 3\n\
+                 After:  [2, 1, 0, 2]\n";
+    let manual = Day16::parse(input).unwrap();
+    let err = Day16::part2(&manual).err().unwrap().to_string();
+    assert_eq!(err, "Samples contradict each other: no instruction fits opcode 3, see samples 1, 2");
+  }
+
+  #[test]
+  fn shared_instructions_are_contradictions() {
+    // Three opcodes that can only be addr or addi.
+    let sample = "Before: [2, 1, 0, 0]\n{} 1 1 3\nAfter:  [2, 1, 0, 2]\n\n";
+    let input = ["3", "4", "5"].iter().map(|op| sample.replace("{}", op)).collect::<String>();
+    let manual = Day16::parse(&input).unwrap();
+    let err = Day16::part2(&manual).err().unwrap().to_string();
+    assert_eq!(err, "Samples contradict each other: opcodes 3, 4, 5 can only be addr, addi, see samples 1, 2, 3");
   }
 }
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use super::data::REGISTERS;
+use super::problems::{explain, matching_ops, opcode_solver, Manual, Sample};
+use super::super::constraint::Unsolvable;
+use super::super::elfcode::{opcode, Opcode};
+use super::super::elfcode::opcode::Operand;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleCheck {
+  // Samples are numbered from 1, like in the solver.
+  pub number: usize,
+  pub line: usize,
+  pub opcode: usize,
+  pub matches: Vec<Opcode>,
+  // Operands (A, B or C) with a value that is not a register of the device.
+  // A and B only count if the opcode can't stand for an instruction that
+  // reads them as immediates.
+  pub out_of_range: Vec<(char, i32)>,
+}
+
+// A sample that ruled out some instructions for its opcode number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elimination {
+  pub sample: usize,
+  pub removed: Vec<Opcode>,
+  pub remaining: Vec<Opcode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validation {
+  pub samples: Vec<SampleCheck>,
+  // The eliminations of every opcode number, in the order of the samples.
+  pub history: Vec<Vec<Elimination>>,
+  pub opcodes: Result<BTreeMap<usize, Opcode>, Unsolvable<usize, Opcode, usize>>,
+}
+
+// `candidates` are the instructions the opcode of the sample can stand for.
+fn check(number: usize, sample: &Sample, matches: Vec<Opcode>, candidates: &[Opcode]) -> SampleCheck {
+  let [opcode, a, b, c] = sample.instruction;
+  let reads_a = candidates.iter().all(|op| op.operands().0 == Operand::Register);
+  let reads_b = candidates.iter().all(|op| op.operands().1 == Operand::Register);
+  let out_of_range = vec![('A', a, reads_a), ('B', b, reads_b), ('C', c, true)]
+    .into_iter()
+    .filter(|&(_, value, register)| register && value as usize >= REGISTERS)
+    .map(|(operand, value, _)| (operand, value))
+    .collect();
+
+  SampleCheck { number, line: sample.line, opcode: opcode as usize, matches, out_of_range }
+}
+
+fn names(opcodes: &[Opcode]) -> String {
+  opcodes.iter().map(|op| op.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+pub fn validate(manual: &Manual) -> Validation {
+  let matches = manual.samples.iter().map(matching_ops).collect::<Vec<Vec<Opcode>>>();
+
+  // What each opcode can stand for, going by the samples that match at all.
+  // Every instruction if they contradict each other.
+  let mut candidates = vec![opcode::ALL.to_vec(); 16];
+  for (sample, ops) in manual.samples.iter().zip(&matches).filter(|(_, ops)| !ops.is_empty()) {
+    candidates[sample.instruction[0] as usize].retain(|op| ops.contains(op));
+  }
+  for ops in candidates.iter_mut().filter(|ops| ops.is_empty()) {
+    *ops = opcode::ALL.to_vec();
+  }
+
+  let samples = manual.samples
+    .iter()
+    .zip(matches)
+    .enumerate()
+    .map(|(index, (sample, matches))| check(index + 1, sample, matches, &candidates[sample.instruction[0] as usize]))
+    .collect::<Vec<SampleCheck>>();
+
+  let solver = opcode_solver(&manual.samples);
+  let history = (0..16)
+    .map(|opcode| {
+      let mut remaining = opcode::ALL.to_vec();
+      let mut steps = vec![];
+      for restriction in solver.restrictions(opcode) {
+        let (kept, removed) = remaining.iter().partition::<Vec<Opcode>, _>(|op| restriction.allowed.contains(op));
+        if !removed.is_empty() {
+          steps.push(Elimination { sample: restriction.evidence, removed, remaining: kept.clone() });
+        }
+        remaining = kept;
+      }
+      steps
+    })
+    .collect();
+
+  Validation { samples, history, opcodes: solver.solve() }
+}
+
+impl Validation {
+  // Samples no instruction fits, the input is probably corrupted.
+  pub fn unmatched(&self) -> Vec<&SampleCheck> {
+    self.samples.iter().filter(|s| s.matches.is_empty()).collect()
+  }
+
+  pub fn out_of_range(&self) -> Vec<&SampleCheck> {
+    self.samples.iter().filter(|s| !s.out_of_range.is_empty()).collect()
+  }
+
+  pub fn is_valid(&self) -> bool {
+    self.unmatched().is_empty() && self.out_of_range().is_empty() && self.opcodes.is_ok()
+  }
+
+  pub fn report(&self) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "Samples:");
+    for s in &self.samples {
+      let _ = writeln!(out, "  {:>4}  line {:>5}  opcode {:>2}  {:>2} matches  {}",
+        s.number, s.line, s.opcode, s.matches.len(), names(&s.matches));
+    }
+
+    let _ = writeln!(out, "\nSamples without a matching instruction:");
+    if self.unmatched().is_empty() {
+      let _ = writeln!(out, "  none");
+    }
+    for s in self.unmatched() {
+      let _ = writeln!(out, "  {} (line {})", s.number, s.line);
+    }
+
+    let _ = writeln!(out, "\nSamples with operands out of range (registers 0 to {}):", REGISTERS - 1);
+    if self.out_of_range().is_empty() {
+      let _ = writeln!(out, "  none");
+    }
+    for s in self.out_of_range() {
+      let operands = s.out_of_range.iter().map(|(name, value)| format!("{} = {}", name, value)).collect::<Vec<String>>();
+      let _ = writeln!(out, "  {} (line {}): {}", s.number, s.line, operands.join(", "));
+    }
+
+    let _ = writeln!(out, "\nEliminations:");
+    for (opcode, steps) in self.history.iter().enumerate() {
+      let _ = writeln!(out, "  opcode {}", opcode);
+      for step in steps {
+        let _ = writeln!(out, "    sample {:>4} rules out {}, leaves {}", step.sample, names(&step.removed), names(&step.remaining));
+      }
+    }
+
+    match &self.opcodes {
+      Ok(opcodes) => {
+        let _ = writeln!(out, "\nOpcodes:");
+        for (number, opcode) in opcodes {
+          let _ = writeln!(out, "  {:>2}  {}", number, opcode);
+        }
+      },
+      Err(unsolvable) => {
+        let _ = writeln!(out, "\n{}", explain(unsolvable.clone()));
+      },
+    }
+
+    out
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::Day16;
+  use super::super::super::solution::Solution;
+
+  #[test]
+  fn the_manual_is_valid() {
+    let manual = Day16::parse(include_str!("./data/input.txt")).unwrap();
+    let validation = validate(&manual);
+
+    assert!(validation.is_valid());
+    assert_eq!(validation.samples.len(), 778);
+    assert_eq!(validation.samples[0].line, 1);
+    assert!(validation.history.iter().all(|steps| !steps.is_empty()));
+    let report = validation.report();
+    assert!(report.contains("Samples without a matching instruction:\n  none\n"));
+    assert!(report.contains("\nOpcodes:\n"));
+  }
+
+  #[test]
+  fn broken_samples_are_reported() {
+    let input = "Before: [3, 2, 1, 1]\n\
+                 9 2 1 2\n\
+                 After:  [3, 2, 2, 1]\n\
+                 \n\
+                 Before: [0, 0, 0, 0]\n\
+                 3 1 0 7\n\
+                 After:  [0, 0, 0, 0]\n\
+                 \n\
+                 Before: [2, 1, 0, 0]\n\
+                 4 1 1 3\n\
+                 After:  [2, 1, 0, 9]\n";
+    let validation = validate(&Day16::parse(input).unwrap());
+
+    assert_eq!(validation.samples[0].matches, vec![Opcode::Addi, Opcode::Mulr, Opcode::Seti]);
+    assert_eq!(validation.history[9], vec![Elimination {
+      sample: 1,
+      removed: opcode::ALL.iter().cloned().filter(|op| ![Opcode::Addi, Opcode::Mulr, Opcode::Seti].contains(op)).collect(),
+      remaining: vec![Opcode::Addi, Opcode::Mulr, Opcode::Seti],
+    }]);
+    assert_eq!(validation.out_of_range().iter().map(|s| (s.number, s.out_of_range.clone())).collect::<Vec<_>>(), vec![(2, vec![('C', 7)])]);
+    assert_eq!(validation.unmatched().iter().map(|s| (s.number, s.line)).collect::<Vec<_>>(), vec![(2, 5), (3, 9)]);
+    assert!(!validation.is_valid());
+
+    let report = validation.report();
+    assert!(report.contains("\n  2 (line 5): C = 7\n"), "{}", report);
+    assert!(report.contains("    sample    1 rules out addr"), "{}", report);
+    assert!(report.contains("Samples contradict each other: no instruction fits opcode 3, see samples 2"), "{}", report);
+  }
+
+  #[test]
+  fn immediates_are_not_registers() {
+    // Only addi fits opcode 9, only addr and borr fit opcode 4.
+    let input = "Before: [3, 2, 1, 1]\n\
+                 9 0 7 1\n\
+                 After:  [3, 10, 1, 1]\n\
+                 \n\
+                 Before: [0, 3, 4, 0]\n\
+                 4 1 2 3\n\
+                 After:  [0, 3, 4, 7]\n\
+                 \n\
+                 Before: [1, 1, 1, 1]\n\
+                 4 7 0 1\n\
+                 After:  [1, 5, 1, 1]\n";
+    let validation = validate(&Day16::parse(input).unwrap());
+
+    assert_eq!(validation.samples[0].matches, vec![Opcode::Addi]);
+    assert_eq!(validation.samples[1].matches, vec![Opcode::Addr, Opcode::Borr]);
+    assert!(validation.samples[2].matches.is_empty());
+    assert_eq!(validation.out_of_range().iter().map(|s| (s.number, s.out_of_range.clone())).collect::<Vec<_>>(), vec![(3, vec![('A', 7)])]);
+  }
+}
@@ -1,26 +1,28 @@
 use std::ops::Range;
 use regex::Regex;
 use std::cmp::{min, max};
-use super::utils::{Error, ParseError};
+use super::grid::{Grid, Point};
+use super::utils::{numbered_lines, parse_number, Error, ParseError};
+use super::solution::{Answer, Solution};
 
-type Position = (i32, i32);
+type Position = Point;
 
-struct Board {
-  pub offset: Position,
+#[derive(Clone)]
+pub struct Board {
   pub bbox: (Position, Position),
   pub size: Position,
-  pub map: Vec<Vec<char>>,
+  // Starts a few columns left of the clay, so water can flow around it.
+  pub map: Grid<char>,
 }
 
 impl Board {
+  // Water that leaves the map to the side falls through sand.
   pub fn get(&self, pos: Position) -> char {
-    let p = (pos.0 - self.offset.0, pos.1 - self.offset.1);
-    self.map[p.1 as usize][p.0 as usize]
+    self.map.get(pos).cloned().unwrap_or('.')
   }
 
   pub fn set(&mut self, pos: Position, c: char) {
-    let p = (pos.0 - self.offset.0, pos.1 - self.offset.1);
-    self.map[p.1 as usize][p.0 as usize] = c;
+    self.map[pos] = c;
   }
 
   pub fn is_sand(&self, pos: Position) -> bool {
@@ -47,13 +49,17 @@ fn parse_range(regex: &Regex, line: &str) -> Result<Range<i32>, ParseError> {
   };
 
   let start = match capture.get(1) {
-    Some(v) => v.as_str().parse::<i32>()?,
+    Some(v) => parse_number::<i32>(v.as_str())?,
     None => Err(ParseError::new("Could not parse start"))?
   };
 
-  let range = match capture.get(3) {
-    Some(v) => start..v.as_str().parse::<i32>()?+1,
-    None => start..start+1
+  let last = match capture.get(3) {
+    Some(v) => parse_number::<i32>(v.as_str())?,
+    None => start,
+  };
+  let range = match last.checked_add(1) {
+    Some(end) => start..end,
+    None => Err(ParseError::new("Range is too large"))?
   };
 
   Ok(range)
@@ -61,12 +67,15 @@ fn parse_range(regex: &Regex, line: &str) -> Result<Range<i32>, ParseError> {
 
 fn parse_line(map: &mut Vec<Position>, line: &str) -> Result<(), ParseError> {
   lazy_static!{
-    static ref RE_x: Regex = Regex::new(r"x=(\d+)(\.\.)?(\d+)?").unwrap();
-    static ref RE_y: Regex = Regex::new(r"y=(\d+)(\.\.)?(\d+)?").unwrap();
+    static ref RE_X: Regex = Regex::new(r"x=(\d+)(\.\.)?(\d+)?").unwrap();
+    static ref RE_Y: Regex = Regex::new(r"y=(\d+)(\.\.)?(\d+)?").unwrap();
   }
 
-  let x_range = parse_range(&RE_x, line)?;
-  let y_range = parse_range(&RE_y, line)?;
+  let x_range = parse_range(&RE_X, line)?;
+  let y_range = parse_range(&RE_Y, line)?;
+  if x_range.len().saturating_mul(y_range.len()) > MAX_CELLS {
+    Err(ParseError::new("Range is too large"))?
+  }
 
   for x in x_range.start..x_range.end {
     for y in y_range.start..y_range.end {
@@ -77,9 +86,9 @@ fn parse_line(map: &mut Vec<Position>, line: &str) -> Result<(), ParseError> {
   Ok(())
 }
 
-fn bounding_box(positions: &Vec<Position>) -> (Position, Position) {
-  let mut minp = (std::i32::MAX, std::i32::MAX);
-  let mut maxp = (std::i32::MIN, std::i32::MIN);
+fn bounding_box(positions: &[Position]) -> (Position, Position) {
+  let mut minp = (i32::MAX, i32::MAX);
+  let mut maxp = (i32::MIN, i32::MIN);
 
   for pos in positions {
     minp.0 = min(pos.0, minp.0);
@@ -91,50 +100,43 @@ fn bounding_box(positions: &Vec<Position>) -> (Position, Position) {
   (minp, maxp)
 }
 
-fn initialize() -> Result<Board, Error> {
-  let input = include_str!("./data/input.txt");
+const SPRING: Position = (500, 0);
+
+// Upper limit for the size of the map, the puzzle inputs need less than a million cells.
+const MAX_CELLS: usize = 50_000_000;
+
+fn initialize(input: &str) -> Result<Board, Error> {
   let mut positions = vec![];
 
-  for line in input.split('\n') {
-    parse_line(&mut positions, line)?;
+  for (number, line) in numbered_lines(input) {
+    parse_line(&mut positions, line).map_err(|err| err.at_line(number, line))?;
+  }
+
+  if positions.is_empty() {
+    Err(ParseError::new("There is no clay in the scan"))?
   }
 
   let bbox = bounding_box(&positions);
   let (min_point, max_point) = bbox;
-  let offset = (min_point.0 - 3, 0);
   let size = (max_point.0 - min_point.0, max_point.1 - min_point.1);
+  // The spring has to be on the map even if the clay is far from it.
+  let (left, right) = (min(min_point.0, SPRING.0) as i64 - 3, max(max_point.0, SPRING.0) as i64 + 3);
+  let offset = (left as i32, 0);
+  let (width, height) = (right - left, max_point.1 as i64 + 15);
+  if width * height > MAX_CELLS as i64 {
+    Err(ParseError::new(&format!("The scan is too large ({}x{})", width, height)))?
+  }
 
-  let mut map = vec![vec!['.'; (size.0 + 6) as usize]; (size.1 + 15) as usize];
+  let mut map = Grid::with_origin(offset, width as usize, height as usize, '.');
 
   for pos in positions {
-    let p = (pos.0 - offset.0, pos.1 - offset.1);
-    map[p.1 as usize][p.0 as usize] = '#';
+    map[pos] = '#';
   }
 
-  let board = Board { offset, bbox, map, size };
+  let board = Board { bbox, map, size };
   Ok(board)
 }
 
-fn print(board: &Board) {
-  let offset = board.offset;
-  let cols = board.map[0].len() as i32;
-  for l in 0..3 {
-    print!("    ");
-    for x in 0..cols {
-      let n = x + offset.0;
-      let c = (n / (10 as i32).pow(2 - l)) % 10;
-      print!("{}", c);
-    }
-    println!("");
-  }
-
-  for (row, line) in board.map.iter().enumerate() {
-    let s: String = line.iter().collect();
-    print!("{:04}", row);
-    println!("{}", s);
-  }
-}
-
 fn is_falling_water(c: char) -> bool {
   c == '|'
 }
@@ -162,17 +164,15 @@ fn is_edge(dir: i32, p: Position, board: &Board) -> bool {
   let water = (ex - dir, ey);
   let sand = (ex, ey + 1);
 
-  let result = board.can_flow(p) &&
+  board.can_flow(p) &&
   board.can_flow(water) &&
   board.can_flow(sand) &&
-  !board.can_flow(clay);
-
-  result
+  !board.can_flow(clay)
 }
 
 fn find_next_stop_down(seed: Position, board: &mut Board) -> Option<Position> {
   let (x, mut y) = seed;
-  let maxh = board.map.len() as i32;
+  let maxh = board.map.end().1;
 
   y += 1;
   while y < maxh && board.can_flow((x, y)) {
@@ -194,13 +194,14 @@ fn fill_bucket(seed: Position, board: &mut Board) -> Vec<Position> {
   let mut flow_left = true;
   let mut flow_right = true;
   let mut new_seeds = vec![];
-  let width = board.offset.0 + (board.map[0].len() as i32);
+  let start = board.map.origin().0;
+  let width = board.map.end().0;
   for dx in 0..width {
     if !flow_left && !flow_right {
       break;
     }
 
-    if x - dx < board.offset.0 {
+    if x - dx < start {
       flow_left = false;
     }
 
@@ -208,7 +209,7 @@ fn fill_bucket(seed: Position, board: &mut Board) -> Vec<Position> {
       flow_right = false;
     }
 
-    if flow_left && is_edge(-1, (x - dx, y), &board) {
+    if flow_left && is_edge(-1, (x - dx, y), board) {
       new_seeds.push((x - dx, y));
       flow_left = false;
     }
@@ -221,7 +222,7 @@ fn fill_bucket(seed: Position, board: &mut Board) -> Vec<Position> {
       board.set((x - dx, y), fill);
     }
 
-    if flow_right && is_edge(1, (x + dx, y), &board) {
+    if flow_right && is_edge(1, (x + dx, y), board) {
       new_seeds.push((x + dx, y));
       flow_right = false;
     }
@@ -239,8 +240,7 @@ fn fill_bucket(seed: Position, board: &mut Board) -> Vec<Position> {
 }
 
 fn settle_water(y: i32, board: &mut Board) -> bool {
-  let start = board.offset.0;
-  let end = start + board.map[0].len() as i32;
+  let (start, end) = (board.map.origin().0, board.map.end().0);
   let mut result = false;
 
   // new strategy here:
@@ -270,9 +270,7 @@ fn settle_water(y: i32, board: &mut Board) -> bool {
     let mut can_be_settled = true;
 
     if let Some(pos) = cluster.first() {
-      if pos.0 - 1 < 0 {
-        can_be_settled = false;
-      } else if board.can_flow((pos.0 - 1, pos.1)) {
+      if pos.0 - 1 < 0 || board.can_flow((pos.0 - 1, pos.1)) {
         can_be_settled = false;
       }
     }
@@ -300,64 +298,84 @@ fn settle_water(y: i32, board: &mut Board) -> bool {
   result
 }
 
-fn trace(seed: Position, mut board: &mut Board) {
+fn trace(seed: Position, board: &mut Board) {
   let mut seeds = vec![seed];
-  let mut previous_seeds = vec![];
 
   while let Some(next) = seeds.pop() {
-    previous_seeds.push(next.clone());
-
     board.set(next, '|');
-    let (x, mut y) = match find_next_stop_down(next, &mut board) {
+    let (x, mut y) = match find_next_stop_down(next, board) {
       Some(v) => v,
       None => continue
     };
 
     y -= 1;
-    let mut next_seeds = fill_bucket((x, y), &mut board);
-    while y > 0 && settle_water(y, &mut board) && next_seeds.is_empty() {
+    let mut next_seeds = fill_bucket((x, y), board);
+    while y > 0 && settle_water(y, board) && next_seeds.is_empty() {
       y -= 1;
-      next_seeds = fill_bucket((x, y), &mut board);
+      next_seeds = fill_bucket((x, y), board);
     }
 
     seeds.append(&mut next_seeds);
-
-    if false {
-      print(&board);
-    }
   }
 }
 
 fn count_water<F>(board: &Board, f: F) -> i32
   where F: Fn(char) -> bool {
-  let skip_rows = (board.bbox.0).1;
-  let size = board.size;
-
-  let mut count = 0;
-  for row in board.map.iter().skip(skip_rows as usize).take((size.1 + 1) as usize) {
-    for chr in row.iter() {
-      if f(*chr) {
-        count += 1;
-      }
-    }
-  }
+  let rows = (board.bbox.0).1..=(board.bbox.1).1;
 
-  count
+  board.map
+    .iter()
+    .filter(|((_, y), chr)| rows.contains(y) && f(**chr))
+    .count() as i32
 }
 
-pub fn problems() -> Result<(), Error> {
-  let mut board = initialize()?;
+fn flood(board: &Board) -> Board {
+  let mut board = board.clone();
+  trace(SPRING, &mut board);
+  board
+}
 
-  println!("Tracing water…");
+pub struct Day17;
 
-  trace((500, 0), &mut board);
-  print(&board);
+impl Solution for Day17 {
+  type Input = Board;
 
-  let result = count_water(&board, is_water);
-  println!("Result for problem 1: {}", result);
+  const DAY: u8 = 17;
+  const TITLE: &'static str = "Reservoir Research";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  let result = count_water(&board, is_settled_water);
-  println!("Result for problem 2: {}", result);
+  fn parse(input: &str) -> Result<Board, Error> {
+    initialize(input)
+  }
 
-  Ok(())
+  fn part1(board: &Board) -> Result<Answer, Error> {
+    let board = flood(board);
+
+    Ok(Answer::from(count_water(&board, is_water)))
+  }
+
+  fn part2(board: &Board) -> Result<Answer, Error> {
+    let board = flood(board);
+
+    Ok(Answer::from(count_water(&board, is_settled_water)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_spring_may_be_far_from_the_clay() {
+    let board = Day17::parse("x=5, y=1\n").unwrap();
+    assert_eq!(Day17::part1(&board).unwrap(), Answer::Number(1));
+    assert_eq!(Day17::part2(&board).unwrap(), Answer::Number(0));
+  }
+
+  #[test]
+  fn ranges_have_to_fit() {
+    let err = Day17::parse("x=2147483647, y=1\n").err().unwrap();
+    assert_eq!(err.to_string(), "line 1: Range is too large in \"x=2147483647, y=1\"");
+  }
 }
@@ -1,7 +1,8 @@
 use std::str::FromStr;
+use super::super::grid::{Grid, Point};
 use super::super::utils::ParseError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Stats {
   pub grows_tree: bool,
   pub constructs_lumberyard: bool,
@@ -10,59 +11,35 @@ pub struct Stats {
 
 impl Stats {
   pub fn new() -> Stats {
-    Stats { grows_tree: false, constructs_lumberyard: false, stays_lumberyard: false }
+    Stats::default()
   }
 }
 
+#[derive(Clone)]
 pub struct Board {
-  pub map: Vec<Vec<char>>,
-  pub stats_map: Vec<Vec<Stats>>,
+  pub map: Grid<char>,
+  pub stats_map: Grid<Stats>,
   pub ticks: usize,
-  pub size: (usize, usize),
 }
 
 impl Board {
   pub fn checksum(&self) -> usize {
-    let mut lumberyards = 0;
-    let mut trees = 0;
-
-    for line in &self.map {
-      for c in line {
-        match *c {
-          '|' => trees += 1,
-          '#' => lumberyards += 1,
-          _ => {}
-        }
-      }
-    }
+    let trees = self.map.values().filter(|&&c| c == '|').count();
+    let lumberyards = self.map.values().filter(|&&c| c == '#').count();
 
     trees * lumberyards
   }
 
-  fn analyze_neighbors(&self, pos: (usize, usize)) -> Stats {
+  fn analyze_neighbors(&self, pos: Point) -> Stats {
     let mut trees = 0;
     let mut lumberyards = 0;
 
-    let (x, y) = pos;
-    let x = x as i32;
-    let y = y as i32;
-
-    for dy in -1i32..2i32 {
-      for dx in -1i32..2i32 {
-        if dy == 0 && dx == 0 {
-          continue;
-        }
-
-        match self.map[(y + dy) as usize][(x + dx) as usize] {
-          '|' => trees += 1,
-          '#' => lumberyards += 1,
-          _ => {}
-        };
-
-        if trees >= 3 && lumberyards >= 3 {
-          break;
-        }
-      }
+    for neighbour in self.map.neighbours8(pos) {
+      match self.map[neighbour] {
+        '|' => trees += 1,
+        '#' => lumberyards += 1,
+        _ => {}
+      };
     }
 
     Stats {
@@ -73,10 +50,9 @@ impl Board {
   }
 
   fn update_stats(&mut self) {
-    for (y, line) in self.map.iter().skip(1).take(self.size.1).enumerate() {
-      for (x, _) in line.iter().skip(1).take(self.size.0).enumerate() {
-        self.stats_map[y][x] = self.analyze_neighbors((x + 1, y + 1));
-      }
+    for pos in self.map.points() {
+      let stats = self.analyze_neighbors(pos);
+      self.stats_map[pos] = stats;
     }
   }
 
@@ -84,26 +60,15 @@ impl Board {
     self.ticks += 1;
     self.update_stats();
 
-    for y in 1..self.map.len()-1 {
-      for x in 1..self.map[y].len()-1 {
-        let stats = &self.stats_map[y-1][x-1];
-        let c = self.map[y][x];
-        self.map[y][x] = match c {
-          '.' => if stats.grows_tree { '|' } else { '.' },
-          '|' => if stats.constructs_lumberyard { '#' } else { '|' },
-          '#' => if stats.stays_lumberyard { '#' } else { '.' },
-          v => v
-        };
-      }
-    }
-  }
-
-  pub fn debug(&self) {
-    println!("");
-    println!("After {} minutes", self.ticks);
-    for line in &self.map {
-      let s: String = line.iter().collect();
-      println!("{}", s);
+    for pos in self.map.points() {
+      let stats = &self.stats_map[pos];
+      let c = self.map[pos];
+      self.map[pos] = match c {
+        '.' => if stats.grows_tree { '|' } else { '.' },
+        '|' => if stats.constructs_lumberyard { '#' } else { '|' },
+        '#' => if stats.stays_lumberyard { '#' } else { '.' },
+        v => v
+      };
     }
   }
 }
@@ -112,18 +77,14 @@ impl FromStr for Board {
   type Err = ParseError;
 
   fn from_str(s: &str) -> Result<Board, ParseError> {
-    let raw_map: Vec<Vec<char>> = s.split('\n').map(|v| v.chars().collect()).collect();
-    let ticks = 0;
-    let size = (raw_map[0].len(), raw_map.len());
-    let stats_map = vec![vec![Stats::new(); size.0]; size.1];
+    let map = Grid::parse(s, |c| match c {
+      '#' | '|' | '.' => Ok(c),
+      _ => Err(ParseError::new(&format!("Unknown acre '{}'", c))),
+    })?;
 
-    let mut map = vec![vec!['.'; size.0 + 2]; size.1 + 2];
-    for y in 0..size.1 {
-      for x in 0..size.0 {
-        map[y + 1][x + 1] = raw_map[y][x];
-      }
-    }
+    let ticks = 0;
+    let stats_map = map.map(|_| Stats::new());
 
-    Ok(Board { map, stats_map, ticks, size })
+    Ok(Board { map, stats_map, ticks })
   }
-}
\ No newline at end of file
+}
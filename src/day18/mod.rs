@@ -1,2 +1,4 @@
 pub mod problems;
-mod board;
\ No newline at end of file
+pub mod board;
+
+pub use self::problems::Day18;
@@ -1,39 +1,21 @@
 use std::str::FromStr;
 use super::board::Board;
 use super::super::utils::Error;
+use super::super::solution::{Answer, Solution};
 
-fn run_simulation(minutes: usize) -> Result<Vec<usize>, Error> {
-  let input = include_str!("./data/input.txt");
-  let mut board = Board::from_str(input)?;
-
-  board.debug();
-
+fn run_simulation(board: &Board, minutes: usize) -> Vec<usize> {
+  let mut board = board.clone();
   let mut checksums = vec![];
 
-  for i in 0..minutes {
-    if i % 1_000 == 0 {
-      println!("Round {}", i);
-    }
+  for _ in 0..minutes {
     checksums.push(board.checksum());
     board.tick();
   }
-  board.debug();
-
-  Ok(checksums)
-}
-
-pub fn problem1() -> Result<usize, Error> {
-  let mut checksums = run_simulation(10)?;
 
-  let result = match checksums.pop() {
-    Some(v) => v,
-    None => Err(Error::new("No checksums found"))?
-  };
-
-  Ok(result)
+  checksums
 }
 
-fn find_cycle(checksums: &Vec<usize>) -> Option<usize> {
+fn find_cycle(checksums: &[usize]) -> Option<usize> {
   if checksums.len() < 1000 {
     return None;
   }
@@ -49,20 +31,42 @@ fn find_cycle(checksums: &Vec<usize>) -> Option<usize> {
   None
 }
 
-pub fn problem2() -> Result<usize, Error> {
-  // takes too long
-  // let result = run_simulation(1_000_000_000)?;
+pub struct Day18;
 
-  let simulated = 10_000;
-  let checksums = run_simulation(simulated)?;
+impl Solution for Day18 {
+  type Input = Board;
 
-  let iterations = 1_000_000_000;
-  let cycle = find_cycle(&checksums).unwrap();
+  const DAY: u8 = 18;
+  const TITLE: &'static str = "Settlers of The North Pole";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  let offset = (iterations - 1_000) % cycle;
-  let result = checksums[1000 + offset];
+  fn parse(input: &str) -> Result<Board, Error> {
+    Ok(Board::from_str(input)?)
+  }
 
-  println!("Result: {}", result);
+  fn part1(board: &Board) -> Result<Answer, Error> {
+    let checksums = run_simulation(board, 11);
 
-  Ok(result)
+    match checksums.last() {
+      Some(v) => Ok(Answer::from(*v)),
+      None => Err(Error::new("No checksums found"))
+    }
+  }
+
+  fn part2(board: &Board) -> Result<Answer, Error> {
+    let simulated = 10_000;
+    let checksums = run_simulation(board, simulated);
+
+    let iterations = 1_000_000_000;
+    let cycle = match find_cycle(&checksums) {
+      Some(v) => v,
+      None => Err(Error::new("No cycle found"))?
+    };
+
+    let offset = (iterations - 1_000) % cycle;
+    let result = checksums[1000 + offset];
+
+    Ok(Answer::from(result))
+  }
 }
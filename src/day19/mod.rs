@@ -1,3 +1,7 @@
-mod ops;
-mod data;
-pub mod problems;
\ No newline at end of file
+pub mod data;
+pub mod problems;
+// Generated from the input, only the tests and the benchmark need it.
+#[cfg(any(test, feature = "transpiled"))]
+pub mod transpiled;
+
+pub use self::problems::Day19;
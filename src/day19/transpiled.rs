@@ -0,0 +1,236 @@
+// Generated from src/day19/data/input.txt by `elfcode::transpiler`, don't edit.
+use super::super::elfcode::arithmetic::{jump_overflow, overflow};
+use super::super::elfcode::compiled::Exit;
+use super::super::elfcode::{Machine, Opcode};
+use super::super::utils::Error;
+
+// Behaves like `Compiled::run_until` without a breakpoint.
+pub fn run(machine: &mut Machine<i128>, max_cycles: Option<u64>) -> Result<Exit, Error> {
+  if machine.registers.len() != 6 || machine.ip_register != Some(5) {
+    Err(Error::new("The machine doesn't fit the program"))?
+  }
+
+  let mut r0 = machine.registers[0];
+  let mut r1 = machine.registers[1];
+  let mut r2 = machine.registers[2];
+  let mut r3 = machine.registers[3];
+  let mut r4 = machine.registers[4];
+  let mut r5 = machine.registers[5];
+  let mut ip = machine.ip;
+  let limit = max_cycles.map_or(u64::MAX, |max| machine.cycles.saturating_add(max));
+  let mut cycles = machine.cycles;
+  let arithmetic = machine.arithmetic;
+
+  // Sums and products follow the arithmetic mode of the machine, an
+  // overflow stops the program at the instruction.
+  macro_rules! apply {
+    ($opcode:ident, $a:expr, $b:expr) => {
+      match arithmetic.apply(Opcode::$opcode, $a, $b) {
+        Some(v) => v,
+        None => break Err(Error::new(&format!("ip {}: {}", ip, overflow(Opcode::$opcode, $a, $b)))),
+      }
+    };
+  }
+
+  // The instruction after a jump, a jump past the largest word stops the
+  // program at the instruction whatever the arithmetic mode.
+  macro_rules! next {
+    ($target:expr) => {{
+      let target: i128 = $target;
+      match target.checked_add(1) {
+        Some(v) => v,
+        None => break Err(Error::new(&format!("ip {}: {}", ip, jump_overflow(target)))),
+      }
+    }};
+  }
+
+  let exit = loop {
+    if cycles >= limit {
+      break Ok(Exit::Limit);
+    }
+
+    match ip {
+      0 => {
+        // addi 5 16 5
+        ip = next!(apply!(Addi, 0, 16));
+      },
+      1 => {
+        // seti 1 1 4
+        r4 = 1;
+        ip = 2;
+      },
+      2 => {
+        // seti 1 8 2
+        r2 = 1;
+        ip = 3;
+      },
+      3 => {
+        // mulr 4 2 3
+        r3 = apply!(Mulr, r4, r2);
+        ip = 4;
+      },
+      4 => {
+        // eqrr 3 1 3
+        r3 = i128::from(r3 == r1);
+        ip = 5;
+      },
+      5 => {
+        // addr 3 5 5
+        ip = next!(apply!(Addr, r3, 5));
+      },
+      6 => {
+        // addi 5 1 5
+        ip = next!(apply!(Addi, 6, 1));
+      },
+      7 => {
+        // addr 4 0 0
+        r0 = apply!(Addr, r4, r0);
+        ip = 8;
+      },
+      8 => {
+        // addi 2 1 2
+        r2 = apply!(Addi, r2, 1);
+        ip = 9;
+      },
+      9 => {
+        // gtrr 2 1 3
+        r3 = i128::from(r2 > r1);
+        ip = 10;
+      },
+      10 => {
+        // addr 5 3 5
+        ip = next!(apply!(Addr, 10, r3));
+      },
+      11 => {
+        // seti 2 6 5
+        ip = 3;
+      },
+      12 => {
+        // addi 4 1 4
+        r4 = apply!(Addi, r4, 1);
+        ip = 13;
+      },
+      13 => {
+        // gtrr 4 1 3
+        r3 = i128::from(r4 > r1);
+        ip = 14;
+      },
+      14 => {
+        // addr 3 5 5
+        ip = next!(apply!(Addr, r3, 14));
+      },
+      15 => {
+        // seti 1 4 5
+        ip = 2;
+      },
+      16 => {
+        // mulr 5 5 5
+        ip = next!(apply!(Mulr, 16, 16));
+      },
+      17 => {
+        // addi 1 2 1
+        r1 = apply!(Addi, r1, 2);
+        ip = 18;
+      },
+      18 => {
+        // mulr 1 1 1
+        r1 = apply!(Mulr, r1, r1);
+        ip = 19;
+      },
+      19 => {
+        // mulr 5 1 1
+        r1 = apply!(Mulr, 19, r1);
+        ip = 20;
+      },
+      20 => {
+        // muli 1 11 1
+        r1 = apply!(Muli, r1, 11);
+        ip = 21;
+      },
+      21 => {
+        // addi 3 7 3
+        r3 = apply!(Addi, r3, 7);
+        ip = 22;
+      },
+      22 => {
+        // mulr 3 5 3
+        r3 = apply!(Mulr, r3, 22);
+        ip = 23;
+      },
+      23 => {
+        // addi 3 8 3
+        r3 = apply!(Addi, r3, 8);
+        ip = 24;
+      },
+      24 => {
+        // addr 1 3 1
+        r1 = apply!(Addr, r1, r3);
+        ip = 25;
+      },
+      25 => {
+        // addr 5 0 5
+        ip = next!(apply!(Addr, 25, r0));
+      },
+      26 => {
+        // seti 0 9 5
+        ip = 1;
+      },
+      27 => {
+        // setr 5 8 3
+        r3 = 27;
+        ip = 28;
+      },
+      28 => {
+        // mulr 3 5 3
+        r3 = apply!(Mulr, r3, 28);
+        ip = 29;
+      },
+      29 => {
+        // addr 5 3 3
+        r3 = apply!(Addr, 29, r3);
+        ip = 30;
+      },
+      30 => {
+        // mulr 5 3 3
+        r3 = apply!(Mulr, 30, r3);
+        ip = 31;
+      },
+      31 => {
+        // muli 3 14 3
+        r3 = apply!(Muli, r3, 14);
+        ip = 32;
+      },
+      32 => {
+        // mulr 3 5 3
+        r3 = apply!(Mulr, r3, 32);
+        ip = 33;
+      },
+      33 => {
+        // addr 1 3 1
+        r1 = apply!(Addr, r1, r3);
+        ip = 34;
+      },
+      34 => {
+        // seti 0 4 0
+        r0 = 0;
+        ip = 35;
+      },
+      35 => {
+        // seti 0 3 5
+        ip = 1;
+      },
+      _ => break Ok(Exit::Halted),
+    }
+    cycles += 1;
+  };
+
+  if exit.is_err() {
+    r5 = ip;
+  } else if cycles != machine.cycles {
+    r5 = ip - 1;
+  }
+  machine.registers.copy_from_slice(&[r0, r1, r2, r3, r4, r5]);
+  machine.ip = ip;
+  machine.cycles = cycles;
+  exit
+}
@@ -1,5 +1,7 @@
 use std::option::Option;
 use std::collections::BTreeMap;
+use super::utils::{preprocess_input, Error};
+use super::solution::{Answer, Solution};
 
 fn number_of_different_letters(a: &str, b: &str) -> i32 {
   let mut diffs = 0;
@@ -13,29 +15,16 @@ fn number_of_different_letters(a: &str, b: &str) -> i32 {
   diffs
 }
 
-fn find_similar<'a>(list: &Vec<&'a str>, s: &'a str) -> Option<&'a str> {
-  for l in list {
-    if number_of_different_letters(l, s) == 1 {
-      return Some(l);
-    }
-  }
-
-  None
+fn find_similar<'a>(list: &[&'a str], s: &'a str) -> Option<&'a str> {
+  list.iter().find(|&l| number_of_different_letters(l, s) == 1).map(|v| v as _)
 }
 
-pub fn problem2() {
-  let input = include_str!("./data/input_2-1.txt");
-  let serials = input
-    .split("\n")
-    .filter(|v| *v != "")
-    .collect();
-
-  for serial in &serials {
-    match find_similar(&serials, serial) {
-      Some(v) => println!("{} is close to {}", serial, v),
-      None => (),
-    }
-  }
+fn common_letters(a: &str, b: &str) -> String {
+  a.chars()
+    .zip(b.chars())
+    .filter(|(x, y)| x == y)
+    .map(|(x, _)| x)
+    .collect()
 }
 
 fn count_letters(s: &str) -> BTreeMap<&str, i32> {
@@ -54,7 +43,7 @@ fn count_letters(s: &str) -> BTreeMap<&str, i32> {
 fn has_one_letter_n_times(s: &str, count: i32) -> bool {
   let map = count_letters(s);
   for entry in map {
-    if entry.0 == "" {
+    if entry.0.is_empty() {
       continue;
     }
     if entry.1 == count {
@@ -72,29 +61,52 @@ fn has_one_letter_thrice(s: &str) -> bool {
   has_one_letter_n_times(s, 3)
 }
 
-pub fn problem1() {
-  let input = include_str!("./data/input_2-1.txt");
-  let serials = input
-    .split("\n")
-    .filter(|v| *v != "");
-
-  let mut double_letters = 0;
-  let mut triple_letters = 0;
-  for serial in serials {
-    if has_one_letter_twice(serial) {
-      double_letters += 1;
-    }
+pub struct Day2;
+
+impl Solution for Day2 {
+  type Input = Vec<String>;
+
+  const DAY: u8 = 2;
+  const TITLE: &'static str = "Inventory Management System";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input_2-1.txt"));
+
+  fn parse(input: &str) -> Result<Vec<String>, Error> {
+    Ok(preprocess_input(input).into_iter().map(|v| v.to_string()).collect())
+  }
+
+  fn part1(serials: &Vec<String>) -> Result<Answer, Error> {
+    let mut double_letters = 0;
+    let mut triple_letters = 0;
+    for serial in serials {
+      if has_one_letter_twice(serial) {
+        double_letters += 1;
+      }
 
-    if has_one_letter_thrice(serial) {
-      triple_letters += 1;
+      if has_one_letter_thrice(serial) {
+        triple_letters += 1;
+      }
     }
+
+    Ok(Answer::from(double_letters * triple_letters))
   }
 
-  println!("Counts are for double {} and triple {}", double_letters, triple_letters);
-  println!("Hash sum is {}", double_letters * triple_letters);
+  fn part2(serials: &Vec<String>) -> Result<Answer, Error> {
+    let serials: Vec<&str> = serials.iter().map(|v| v.as_str()).collect();
+
+    for serial in &serials {
+      if let Some(v) = find_similar(&serials, serial) {
+        return Ok(Answer::from(common_letters(serial, v)));
+      }
+    }
+
+    Err(Error::new("No two box IDs differ by exactly one character"))
+  }
 }
 
 #[cfg(test)]
+// Kept as written, clippy would rather see `assert!` for booleans.
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
   use super::*;
 
@@ -157,4 +169,16 @@ mod tests {
     let input = "abcdddefffg";
     assert_eq!(has_one_letter_thrice(input), true);
   }
+
+  #[test]
+  fn check_part1() {
+    let input = Day2::parse(include_str!("./data/input_2-1.txt")).unwrap();
+    assert_eq!(Day2::part1(&input).unwrap(), Answer::Number(7936));
+  }
+
+  #[test]
+  fn check_part2() {
+    let input = Day2::parse(include_str!("./data/input_2-1.txt")).unwrap();
+    assert_eq!(Day2::part2(&input).unwrap(), Answer::from("lnfqdscwjyteorambzuchrgpx"));
+  }
 }
@@ -1,7 +1,11 @@
-use std::collections::HashMap;
 use super::node::Directions;
+use super::super::grid::{Grid, Point};
 
-pub type Position = (i32, i32);
+pub type Position = Point;
+
+// The number of doors to every room that has been reached, the map grows
+// as the directions are followed.
+pub type Rooms = Grid<Option<usize>>;
 
 fn delta_pos(c: char) -> Position {
   match c {
@@ -14,18 +18,20 @@ fn delta_pos(c: char) -> Position {
 }
 
 fn walk_and_count_new_rooms(
-  directions: &String,
+  directions: &str,
   pos: &mut Position,
-  map: &mut HashMap<Position, usize>
+  map: &mut Rooms
 ) -> usize {
   let mut length = 0;
   for n in directions.chars() {
-    let old_pos = pos.clone();
+    let old_pos = *pos;
     let delta = delta_pos(n);
     pos.0 += delta.0;
     pos.1 += delta.1;
-    if !map.contains_key(pos) {
-      map.insert(*pos, map[&old_pos] + 1);
+    if map.get(*pos).cloned().flatten().is_none() {
+      let doors = map[old_pos].map(|doors| doors + 1);
+      map.fit(*pos, None);
+      map[*pos] = doors;
       length += 1;
     } else {
       length = 0;
@@ -37,29 +43,21 @@ fn walk_and_count_new_rooms(
 
 pub fn count_rooms(
   direction: &Directions,
-  mut pos: &mut Position,
-  mut map: &mut HashMap<Position, usize>,
-  indent: usize,
-  rooms: &mut usize,
+  pos: &mut Position,
+  map: &mut Rooms,
 ) -> usize {
-  // let mut spaces = "".to_string();
-  // let space = ' ';
-  // for _i in 0..indent {
-  //   spaces.push(space);
-  // }
-
   match direction {
     Directions::Content(v) => {
-      let result = walk_and_count_new_rooms(&v, &mut pos, &mut map);
+      let result = walk_and_count_new_rooms(v, pos, map);
       // println!("{}-- Content: {} -> {}", spaces, v, result);
       result
     },
     Directions::Options(v) => {
       // println!("{}Options: {:?}", spaces, v);
       let mut lengths = vec![];
-      let old_pos = pos.clone();
+      let old_pos = *pos;
       for d in v {
-        lengths.push(count_rooms(d, &mut pos, &mut map, indent + 1, rooms));
+        lengths.push(count_rooms(d, pos, map));
         pos.0 = old_pos.0;
         pos.1 = old_pos.1;
       }
@@ -73,7 +71,7 @@ pub fn count_rooms(
     },
     Directions::Concat(v) => {
       // println!("{}Concat: {:?}", spaces, v);
-      let result = v.iter().map(|v| count_rooms(v, &mut pos, &mut map, indent + 1, rooms)).sum();
+      let result = v.iter().map(|v| count_rooms(v, pos, map)).sum();
       // println!("{}-- Concat: {}", spaces, result);
       result
     }
@@ -1,4 +1,6 @@
-mod node;
-mod parser;
-mod analysis;
-pub mod problems;
\ No newline at end of file
+pub mod node;
+pub mod parser;
+pub mod analysis;
+pub mod problems;
+
+pub use self::problems::Day20;
@@ -1,10 +1,13 @@
 use super::node::{Directions};
+use super::super::utils::ParseError;
 
 fn preprocess_input(s: &str) -> String {
   s.chars().rev().collect()
 }
 
-fn recursive_parse(mut s: &mut String) -> Directions {
+// Parses up to the `)` that closes the group if `nested`, otherwise up to
+// the end of the input.
+fn recursive_parse(s: &mut String, nested: bool) -> Result<Directions, ParseError> {
   let mut buffer = String::new();
   let mut directions = vec![];
   let mut concats = vec![];
@@ -16,15 +19,17 @@ fn recursive_parse(mut s: &mut String) -> Directions {
           directions.push(Directions::Content(buffer));
           buffer = String::new();
         }
-        let options = recursive_parse(&mut s);
+        let options = recursive_parse(s, true)?;
         directions.push(options);
       },
+      ')' if !nested => Err(ParseError::new("Unexpected ')' outside of a group"))?,
+      '|' if !nested => Err(ParseError::new("Unexpected '|' outside of a group"))?,
       ')' => {
         if !buffer.is_empty() {
           directions.push(Directions::Content(buffer));
         }
         concats.push(Directions::Concat(directions));
-        return Directions::Options(concats);
+        return Ok(Directions::Options(concats));
       },
       '|' => {
         if !buffer.is_empty() {
@@ -40,13 +45,17 @@ fn recursive_parse(mut s: &mut String) -> Directions {
     }
   }
 
-  if buffer.len() > 0 {
+  if nested {
+    Err(ParseError::new("A group is not closed"))?
+  }
+
+  if !buffer.is_empty() {
     directions.push(Directions::Content(buffer));
   }
-  Directions::Concat(directions)
+  Ok(Directions::Concat(directions))
 }
 
-pub fn parse(s: &str) -> Directions {
+pub fn parse(s: &str) -> Result<Directions, ParseError> {
   let mut input = preprocess_input(s);
-  recursive_parse(&mut input)
+  recursive_parse(&mut input, false)
 }
\ No newline at end of file
@@ -1,38 +1,69 @@
-use std::collections::HashMap;
+use super::super::grid::Grid;
 use super::super::utils::Error;
+use super::super::solution::{Answer, Solution};
 use super::parser::parse;
-use super::analysis::{Position, count_rooms};
+use super::node::Directions;
+use super::analysis::{Position, Rooms, count_rooms};
 
-fn run(input: &str) -> Result<(usize, usize), Error> {
-  let nodes = parse(input);
-
-  let mut map = HashMap::new();
+fn explore(nodes: &Directions) -> (usize, Rooms) {
   let mut pos: Position = (0, 0);
-  map.insert(pos, 0);
-  let mut rooms = 0;
-  let distance = count_rooms(&nodes, &mut pos, &mut map, 0, &mut rooms);
-
-  let number_of_rooms = map.iter().filter(|(_, dist)| **dist >= 1000).count();
+  let mut map = Grid::with_origin(pos, 1, 1, Some(0));
+  let distance = count_rooms(nodes, &mut pos, &mut map);
 
-  println!("Distance: {}", distance);
-  println!("Number of Rooms: {}", number_of_rooms);
+  (distance, map)
+}
 
-  Ok((distance, number_of_rooms))
+fn rooms_at_least(map: &Rooms, doors: usize) -> usize {
+  map.values().filter(|dist| dist.is_some_and(|dist| dist >= doors)).count()
 }
 
-pub fn problems() -> Result<(usize, usize), Error> {
-  let input = include_str!("./data/input.txt");
-  // let input = "^ENWWW(NEEE|SSE(EE|N))$";
-  // let input = "^ENNWSWW(NEWS|)SSSEEN(WNSE|)EE(SWEN|)NNN$";
-  // let input = "^WNE$";
+pub struct Day20;
+
+impl Solution for Day20 {
+  type Input = Directions;
+
+  const DAY: u8 = 20;
+  const TITLE: &'static str = "A Regular Map";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Directions, Error> {
+    Ok(parse(input)?)
+  }
+
+  fn part1(nodes: &Directions) -> Result<Answer, Error> {
+    let (distance, _) = explore(nodes);
+
+    Ok(Answer::from(distance))
+  }
 
-  run(input)
+  fn part2(nodes: &Directions) -> Result<Answer, Error> {
+    let (_, map) = explore(nodes);
+
+    Ok(Answer::from(rooms_at_least(&map, 1000)))
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  fn run(input: &str) -> Result<(usize, usize), Error> {
+    let nodes = parse(input)?;
+    let (distance, map) = explore(&nodes);
+
+    Ok((distance, rooms_at_least(&map, 1000)))
+  }
+
+  #[test]
+  fn unbalanced_groups_are_rejected() {
+    let error = |input| Day20::parse(input).err().map(|err| err.to_string());
+    assert_eq!(error("^(N$"), Some("A group is not closed".to_string()));
+    assert_eq!(error("^N)$"), Some("Unexpected ')' outside of a group".to_string()));
+    assert_eq!(error("^N|S$"), Some("Unexpected '|' outside of a group".to_string()));
+    assert_eq!(error("^(N|)$"), None);
+  }
+
   #[test]
   fn check_example1() {
     assert_eq!(run("^WNE$").unwrap().0, 3);
@@ -57,4 +88,4 @@ mod tests {
   fn check_example5() {
     assert_eq!(run("^WSSEESWWWNW(S|NENNEEEENN(ESSSSW(NWSW|SSEN)|WSWWN(E|WWS(E|SS))))$").unwrap().0, 31);
   }
-}
\ No newline at end of file
+}
@@ -1,88 +1,47 @@
 use super::data::*;
-use super::super::utils::{preprocess_input, ParseError, Error};
-use regex::{Captures, Regex};
-use std::collections::HashSet;
+use super::super::elfcode::{self, halting, Program};
+use super::super::utils::Error;
+use super::super::solution::{Answer, Solution};
 
-fn instruction_from_capture(capture: &Captures) -> Result<(String, [i128; 3]), ParseError> {
-  let instruction = capture[1].to_string();
-  let r1 = capture[2].parse::<i128>()?;
-  let r2 = capture[3].parse::<i128>()?;
-  let r3 = capture[4].parse::<i128>()?;
+pub struct Day21;
 
-  Ok((instruction, [r1, r2, r3]))
-}
-
-fn parse_ip(s: &str) -> Result<i128, ParseError> {
-  lazy_static!{
-    static ref RE_ip: Regex = Regex::new(r"#ip (\d+)").unwrap();
-  }
+impl Solution for Day21 {
+  type Input = Program<i128>;
 
-  let capture = match RE_ip.captures(s) {
-    Some(c) => c,
-    None => Err(ParseError::new("Could not parse instruction"))?
-  };
+  const DAY: u8 = 21;
+  const TITLE: &'static str = "Chronal Conversion";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  Ok(capture[1].parse::<i128>()?)
-}
-
-fn parse_instruction(s: &str) -> Result<(String, [i128; 3]), ParseError> {
-  lazy_static!{
-    static ref RE_instruction: Regex = Regex::new(r"([a-z]{4}) (\d+) (\d+) (\d+)").unwrap();
+  fn parse(input: &str) -> Result<Program<i128>, Error> {
+    Ok(elfcode::parse(input, REGISTERS)?)
   }
 
-  let capture = match RE_instruction.captures(s) {
-    Some(c) => c,
-    None => Err(ParseError::new("Could not parse instruction"))?
-  };
+  fn part1(program: &Program<i128>) -> Result<Answer, Error> {
+    Ok(Answer::from(halting::first_value(program, REGISTERS)?))
+  }
 
-  instruction_from_capture(&capture)
+  fn part2(program: &Program<i128>) -> Result<Answer, Error> {
+    Ok(Answer::from(halting::values(program, REGISTERS)?.last))
+  }
 }
 
-pub fn run() -> Result<(i128, i128), Error> {
-  let input = include_str!("./data/input.txt");
-  let mut program = preprocess_input(input);
-  let ip_designation = program.remove(0);
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  let ip = parse_ip(ip_designation)?;
-
-  let parsed_program: Vec<(String, [i128; 3])> = program
-    .iter()
-    .map(|v| parse_instruction(v))
-    .collect::<Result<Vec<(String, [i128; 3])>, ParseError>>()?;
-
-  let mut state = State::new(ip, 0, 0, 0, 0, 0, 0);
-  let mut cancelling_numbers = HashSet::new();
-  let mut first = None;
-  let mut last = 0;
-  loop {
-    let ip = state.registers[state.ip as usize];
-    if ip < 0 || ip >= program.len() as i128 {
-      break;
-    }
-
-    let instruction = &parsed_program[ip as usize];
-    let (op, p) = instruction;
-    if state.registers[2] == 28 {
-      let cancel = state.registers[4];
-      if !cancelling_numbers.insert(cancel) {
-        break;
-      }
-      last = cancel;
-      if first.is_none() {
-        first = Some(cancel);
-      }
-    }
-    state.apply(op.as_str(), p[0], p[1], p[2]);
+  #[test]
+  fn unknown_instructions_are_reported() {
+    let err = Day21::parse("#ip 0\nnope 0 0 0\n").err().unwrap();
+    assert_eq!(err.to_string(), "line 2, column 1: Unknown opcode 'nope' in \"nope 0 0 0\"");
   }
 
-  Ok((first.unwrap(), last))
-}
+  #[test]
+  fn check_halting_values() {
+    let program = Day21::parse(include_str!("./data/input.txt")).unwrap();
+    let values = halting::values(&program, REGISTERS).unwrap();
 
-pub fn problems() -> Result<(i128, i128), Error> {
-  let result = run()?;
-
-  println!("Solution for Problem 1: {}", result.0);
-  println!("Solution for Problem 2: {}", result.1);
-
-  Ok(result)
+    assert_eq!((values.first, values.last), (12213578, 5310683));
+    assert!(values.cycle.is_some());
+  }
 }
@@ -0,0 +1,210 @@
+// Generated from src/day21/data/input.txt by `elfcode::transpiler`, don't edit.
+use super::super::elfcode::arithmetic::{jump_overflow, overflow};
+use super::super::elfcode::compiled::Exit;
+use super::super::elfcode::{Machine, Opcode};
+use super::super::utils::Error;
+
+// Behaves like `Compiled::run_until` without a breakpoint.
+pub fn run(machine: &mut Machine<i128>, max_cycles: Option<u64>) -> Result<Exit, Error> {
+  if machine.registers.len() != 6 || machine.ip_register != Some(2) {
+    Err(Error::new("The machine doesn't fit the program"))?
+  }
+
+  let r0 = machine.registers[0];
+  let mut r1 = machine.registers[1];
+  let mut r2 = machine.registers[2];
+  let mut r3 = machine.registers[3];
+  let mut r4 = machine.registers[4];
+  let mut r5 = machine.registers[5];
+  let mut ip = machine.ip;
+  let limit = max_cycles.map_or(u64::MAX, |max| machine.cycles.saturating_add(max));
+  let mut cycles = machine.cycles;
+  let arithmetic = machine.arithmetic;
+
+  // Sums and products follow the arithmetic mode of the machine, an
+  // overflow stops the program at the instruction.
+  macro_rules! apply {
+    ($opcode:ident, $a:expr, $b:expr) => {
+      match arithmetic.apply(Opcode::$opcode, $a, $b) {
+        Some(v) => v,
+        None => break Err(Error::new(&format!("ip {}: {}", ip, overflow(Opcode::$opcode, $a, $b)))),
+      }
+    };
+  }
+
+  // The instruction after a jump, a jump past the largest word stops the
+  // program at the instruction whatever the arithmetic mode.
+  macro_rules! next {
+    ($target:expr) => {{
+      let target: i128 = $target;
+      match target.checked_add(1) {
+        Some(v) => v,
+        None => break Err(Error::new(&format!("ip {}: {}", ip, jump_overflow(target)))),
+      }
+    }};
+  }
+
+  let exit = loop {
+    if cycles >= limit {
+      break Ok(Exit::Limit);
+    }
+
+    match ip {
+      0 => {
+        // seti 123 0 4
+        r4 = 123;
+        ip = 1;
+      },
+      1 => {
+        // bani 4 456 4
+        r4 &= 456;
+        ip = 2;
+      },
+      2 => {
+        // eqri 4 72 4
+        r4 = i128::from(r4 == 72);
+        ip = 3;
+      },
+      3 => {
+        // addr 4 2 2
+        ip = next!(apply!(Addr, r4, 3));
+      },
+      4 => {
+        // seti 0 0 2
+        ip = 1;
+      },
+      5 => {
+        // seti 0 5 4
+        r4 = 0;
+        ip = 6;
+      },
+      6 => {
+        // bori 4 65536 5
+        r5 = r4 | 65536;
+        ip = 7;
+      },
+      7 => {
+        // seti 1765573 9 4
+        r4 = 1765573;
+        ip = 8;
+      },
+      8 => {
+        // bani 5 255 1
+        r1 = r5 & 255;
+        ip = 9;
+      },
+      9 => {
+        // addr 4 1 4
+        r4 = apply!(Addr, r4, r1);
+        ip = 10;
+      },
+      10 => {
+        // bani 4 16777215 4
+        r4 &= 16777215;
+        ip = 11;
+      },
+      11 => {
+        // muli 4 65899 4
+        r4 = apply!(Muli, r4, 65899);
+        ip = 12;
+      },
+      12 => {
+        // bani 4 16777215 4
+        r4 &= 16777215;
+        ip = 13;
+      },
+      13 => {
+        // gtir 256 5 1
+        r1 = i128::from(256 > r5);
+        ip = 14;
+      },
+      14 => {
+        // addr 1 2 2
+        ip = next!(apply!(Addr, r1, 14));
+      },
+      15 => {
+        // addi 2 1 2
+        ip = next!(apply!(Addi, 15, 1));
+      },
+      16 => {
+        // seti 27 0 2
+        ip = 28;
+      },
+      17 => {
+        // seti 0 8 1
+        r1 = 0;
+        ip = 18;
+      },
+      18 => {
+        // addi 1 1 3
+        r3 = apply!(Addi, r1, 1);
+        ip = 19;
+      },
+      19 => {
+        // muli 3 256 3
+        r3 = apply!(Muli, r3, 256);
+        ip = 20;
+      },
+      20 => {
+        // gtrr 3 5 3
+        r3 = i128::from(r3 > r5);
+        ip = 21;
+      },
+      21 => {
+        // addr 3 2 2
+        ip = next!(apply!(Addr, r3, 21));
+      },
+      22 => {
+        // addi 2 1 2
+        ip = next!(apply!(Addi, 22, 1));
+      },
+      23 => {
+        // seti 25 1 2
+        ip = 26;
+      },
+      24 => {
+        // addi 1 1 1
+        r1 = apply!(Addi, r1, 1);
+        ip = 25;
+      },
+      25 => {
+        // seti 17 7 2
+        ip = 18;
+      },
+      26 => {
+        // setr 1 4 5
+        r5 = r1;
+        ip = 27;
+      },
+      27 => {
+        // seti 7 6 2
+        ip = 8;
+      },
+      28 => {
+        // eqrr 4 0 1
+        r1 = i128::from(r4 == r0);
+        ip = 29;
+      },
+      29 => {
+        // addr 1 2 2
+        ip = next!(apply!(Addr, r1, 29));
+      },
+      30 => {
+        // seti 5 2 2
+        ip = 6;
+      },
+      _ => break Ok(Exit::Halted),
+    }
+    cycles += 1;
+  };
+
+  if exit.is_err() {
+    r2 = ip;
+  } else if cycles != machine.cycles {
+    r2 = ip - 1;
+  }
+  machine.registers.copy_from_slice(&[r0, r1, r2, r3, r4, r5]);
+  machine.ip = ip;
+  machine.cycles = cycles;
+  exit
+}
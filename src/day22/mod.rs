@@ -1,6 +1,10 @@
-use std::collections::{HashMap, HashSet, BTreeMap};
+use std::collections::{HashSet, BTreeMap};
+use regex::Regex;
+use super::grid::{Grid, Point};
+use super::utils::{parse_number, Error};
+use super::solution::{Answer, Solution};
 
-type Position = (u64, u64);
+type Position = Point;
 
 #[derive(PartialEq, PartialOrd, Eq, Ord, Clone, Debug, Hash, Copy)]
 enum Tool {
@@ -9,44 +13,33 @@ enum Tool {
   Neither,
 }
 
-static DEPTH: u64 = 10689;
-static TARGET: Position = (11, 722);
-
-fn geologic_index(p: Position, target: Position, lut: &HashMap<Position, u64>) -> u64 {
-  if p.0 == 0 && p.1 == 0 {
-    0
-  } else if p.0 == target.0 && p.1 == target.1 {
+fn geologic_index(p: Position, target: Position, lut: &Grid<u64>) -> u64 {
+  if p == (0, 0) || p == target {
     0
   } else if p.0 == 0 {
-    p.1 * 48271
+    p.1 as u64 * 48271
   } else if p.1 == 0 {
-    p.0 * 16807
+    p.0 as u64 * 16807
   } else {
-    lut[&(p.0 - 1, p.1)] * lut[&(p.0, p.1 - 1)]
+    lut[(p.0 - 1, p.1)] * lut[(p.0, p.1 - 1)]
   }
 }
 
-fn erosion_level(p: Position, depth: u64, target: Position, lut: &mut HashMap<Position, u64>) -> u64 {
-  let index = (geologic_index(p, target, lut) + depth) % 20183;
-  lut.insert(p, index);
-  index
-}
-
-fn risk_level(depth: u64, target: Position) -> u64 {
-  let mut risk_level = 0;
-  let mut lut = HashMap::new();
-  for x in 0..target.0+1 {
-    for y in 0..target.1+1 {
-      risk_level += erosion_level((x, y), depth, target, &mut lut) % 3;
-    }
+// The erosion levels of the region from the mouth of the cave to `max`.
+fn erosion_levels(depth: u64, max: Position, target: Position) -> Grid<u64> {
+  let mut lut = Grid::new(max.0 as usize + 1, max.1 as usize + 1, 0);
+  // Only the remainder matters, and it keeps the sum from overflowing.
+  let depth = depth % 20183;
+  // Reading order has the regions above and to the left ready.
+  for p in lut.points() {
+    lut[p] = (geologic_index(p, target, &lut) + depth) % 20183;
   }
 
-  risk_level
+  lut
 }
 
-pub fn problem1() {
-  let result = risk_level(DEPTH, TARGET);
-  println!("Risk level: {}", result);
+fn risk_level(depth: u64, target: Position) -> u64 {
+  erosion_levels(depth, target, target).values().map(|level| level % 3).sum()
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Clone, Copy)]
@@ -77,92 +70,63 @@ fn get_forbidden_tool_from_risk(risk: u64) -> Tool {
   match risk {
     0 => Tool::Neither,
     1 => Tool::Torch,
-    2 => Tool::ClimbingGear,
-    _ => panic!("This can't happen")
+    _ => Tool::ClimbingGear,
   }
 }
 
-fn build_map(depth: u64, max: Position, target: Position) -> HashMap<Position, Tool> {
-  let mut lut = HashMap::new();
-  let mut map = HashMap::new();
-  for x in 0..max.0+1 {
-    for y in 0..max.1+1 {
-      let risk = erosion_level((x, y), depth, target, &mut lut) % 3;
-      let forbidden_tool = get_forbidden_tool_from_risk(risk);
-      map.insert((x, y), forbidden_tool);
-    }
-  }
-
-  map
+fn build_map(depth: u64, max: Position, target: Position) -> Grid<Tool> {
+  erosion_levels(depth, max, target).map(|level| get_forbidden_tool_from_risk(level % 3))
 }
 
-fn find_neighbours(p: Position) -> Vec<Position> {
-  let mut neighbours = vec![];
-  if p.0 > 0 {
-    neighbours.push((p.0 - 1, p.1));
-  }
-
-  if p.1 > 0 {
-    neighbours.push((p.0, p.1 - 1));
-  }
-
-  neighbours.push((p.0 + 1, p.1));
-  neighbours.push((p.0, p.1 + 1));
-
-  neighbours
-}
-
-fn find_tool(forbidden_now: &Tool, forbidden_then: &Tool) -> Tool {
+// The tool that is allowed in both regions, if there is one.
+fn find_tool(forbidden_now: &Tool, forbidden_then: &Tool) -> Option<Tool> {
   match (forbidden_now, forbidden_then) {
-    (Tool::ClimbingGear, Tool::Neither) => Tool::Torch,
-    (Tool::Neither, Tool::ClimbingGear) => Tool::Torch,
-    (Tool::ClimbingGear, Tool::Torch) => Tool::Neither,
-    (Tool::Torch, Tool::ClimbingGear) => Tool::Neither,
-    (Tool::Neither, Tool::Torch) => Tool::ClimbingGear,
-    (Tool::Torch, Tool::Neither) => Tool::ClimbingGear,
-    _ => panic!("This should not happen!!!")
-    // (Tool::ClimbingGear, Tool::ClimbingGear) => Tool::Torch,
-    // (Tool::Torch, Tool::Torch) => Tool::Neither,
-    // (Tool::Neither, Tool::Neither) => Tool::ClimbingGear,
+    (Tool::ClimbingGear, Tool::Neither) => Some(Tool::Torch),
+    (Tool::Neither, Tool::ClimbingGear) => Some(Tool::Torch),
+    (Tool::ClimbingGear, Tool::Torch) => Some(Tool::Neither),
+    (Tool::Torch, Tool::ClimbingGear) => Some(Tool::Neither),
+    (Tool::Neither, Tool::Torch) => Some(Tool::ClimbingGear),
+    (Tool::Torch, Tool::Neither) => Some(Tool::ClimbingGear),
+    _ => None,
   }
 }
 
 fn prioritized_node(backlog: &mut BTreeMap<u64, Vec<Node>>) -> Option<Node> {
-  if let Some(first_non_empty_log) = backlog.iter_mut().filter(|(_, v)| !v.is_empty()).next() {
+  if let Some(first_non_empty_log) = backlog.iter_mut().find(|(_, v)| !v.is_empty()) {
     Some(first_non_empty_log.1.remove(0))
   } else {
     None
   }
 }
 
-pub fn problem2() {
-  let max = (1000, 1000);
-  let target = TARGET;
-  let map = build_map(DEPTH, max, target);
+// Leaves enough room around the target to walk around obstacles.
+fn search_area(target: Position) -> Position {
+  (target.0 + target.1 + 100, target.1 + target.0 + 100)
+}
 
-  // let max = (20, 20);
-  // let target = (10, 10);
-  // let map = build_map(510, max, (10, 10));
+// The search area grows with the square of the distance to the target, the
+// puzzle inputs need less than a million regions.
+const MAX_REGIONS: i64 = 20_000_000;
+
+fn rescue_time(depth: u64, target: Position) -> Result<u64, Error> {
+  let max = search_area(target);
+  let map = build_map(depth, max, target);
 
   let mut visited: HashSet<Visited> = HashSet::new();
   let mut backlog: BTreeMap<u64, Vec<Node>> = BTreeMap::new();
   backlog.insert(0, vec![Node::new(0, (0, 0), Tool::Torch)]);
 
-  let mut i = 0;
   while let Some(current) = prioritized_node(&mut backlog) {
-    i += 1;
     if current.pos == target {
       // We are at the target position and have a torch in hand
       if current.tool == Tool::Torch {
-        println!("Found target in {} minutes", current.time);
-        break;
+        return Ok(current.time);
       // we are at the target pos but have to switch to a torch
       } else {
         let new_node = Node::new(current.time + 7, current.pos, Tool::Torch);
-        println!("Final switch {:?}", new_node);
         backlog.entry(current.time + 7)
-          .and_modify(|v| v.push(new_node))
-          .or_insert(vec![new_node]);
+          .or_default()
+          .push(new_node);
       }
     }
 
@@ -172,20 +136,71 @@ pub fn problem2() {
     }
     visited.insert(v);
 
-    let neighbours = find_neighbours(current.pos);
-    for n in neighbours {
-      let new_node = if current.tool != map[&n] {
+    for n in map.neighbours4(current.pos) {
+      let new_node = if current.tool != map[n] {
         Node::new(current.time + 1, n, current.tool)
       } else {
-        Node::new(current.time + 7, current.pos, find_tool(&map[&current.pos], &map[&n]))
+        match find_tool(&map[current.pos], &map[n]) {
+          Some(tool) => Node::new(current.time + 7, current.pos, tool),
+          None => continue,
+        }
       };
       backlog.entry(new_node.time)
-        .and_modify(|v| v.push(new_node))
-        .or_insert(vec![new_node]);
+        .or_default()
+        .push(new_node);
+    }
+  }
+
+  Err(Error::new("Target is unreachable"))
+}
+
+pub struct Scan {
+  pub depth: u64,
+  pub target: Position,
+}
+
+pub struct Day22;
+
+impl Solution for Day22 {
+  type Input = Scan;
+
+  const DAY: u8 = 22;
+  const TITLE: &'static str = "Mode Maze";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Scan, Error> {
+    lazy_static!{
+      static ref RE_DEPTH: Regex = Regex::new(r"depth: (\d+)").unwrap();
+      static ref RE_TARGET: Regex = Regex::new(r"target: (\d+),(\d+)").unwrap();
     }
+
+    let depth = match RE_DEPTH.captures(input) {
+      Some(c) => parse_number::<u64>(&c[1])?,
+      None => Err(Error::new("Could not find the depth"))?
+    };
+
+    let target = match RE_TARGET.captures(input) {
+      Some(c) => (parse_number::<i32>(&c[1])?, parse_number::<i32>(&c[2])?),
+      None => Err(Error::new("Could not find the target"))?
+    };
+
+    // Checked here so `search_area` fits as well.
+    let side = target.0 as i64 + target.1 as i64 + 101;
+    if side.saturating_mul(side) > MAX_REGIONS {
+      Err(Error::new(&format!("The target {},{} is too far from the mouth of the cave", target.0, target.1)))?
+    }
+
+    Ok(Scan { depth, target })
   }
 
-  println!("Ran for {} iterations", i);
+  fn part1(scan: &Scan) -> Result<Answer, Error> {
+    Ok(Answer::from(risk_level(scan.depth, scan.target)))
+  }
+
+  fn part2(scan: &Scan) -> Result<Answer, Error> {
+    Ok(Answer::from(rescue_time(scan.depth, scan.target)?))
+  }
 }
 
 #[cfg(test)]
@@ -194,6 +209,29 @@ mod tests {
 
   #[test]
   fn check_example() {
-    assert_eq!(risk_level(510, (10, 10)), 114);
+    let scan = Day22::parse("depth: 510\ntarget: 10,10\n").unwrap();
+    assert_eq!(Day22::part1(&scan).unwrap(), Answer::Number(114));
+    assert_eq!(Day22::part2(&scan).unwrap(), Answer::Number(45));
+  }
+
+  #[test]
+  fn targets_far_away_are_rejected() {
+    let err = Day22::parse("depth: 510\ntarget: 10,2147483647\n").err().unwrap();
+    assert_eq!(err.to_string(), "The target 10,2147483647 is too far from the mouth of the cave");
+    assert!(Day22::parse("depth: 510\ntarget: 3000,3000\n").is_err());
+  }
+
+  #[test]
+  fn deep_caves_work() {
+    let scan = Day22::parse("depth: 18446744073709551615\ntarget: 10,10\n").unwrap();
+    let same = Day22::parse(&format!("depth: {}\ntarget: 10,10\n", u64::MAX % 20183)).unwrap();
+    assert_eq!(Day22::part1(&scan).unwrap(), Day22::part1(&same).unwrap());
+    assert_eq!(Day22::part2(&scan).unwrap(), Day22::part2(&same).unwrap());
+  }
+
+  #[test]
+  fn numbers_that_dont_fit_are_quoted() {
+    let err = Day22::parse("depth: 510\ntarget: 10,99999999999\n").err().unwrap();
+    assert_eq!(err.to_string(), "Unable to parse number '99999999999'");
   }
 }
\ No newline at end of file
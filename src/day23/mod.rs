@@ -1,12 +1,13 @@
 use std::str::FromStr;
-use super::utils::{ParseError, Error};
+use super::utils::{parse_lines, parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
 use regex::Regex;
 use std::fmt::{Formatter, Display};
 
 type Position = (f64, f64, f64);
 
 #[derive(Debug, Clone)]
-struct NanoBot {
+pub struct NanoBot {
   pub pos: Position,
   pub radius: f64,
 }
@@ -22,16 +23,16 @@ impl FromStr for NanoBot {
 
   fn from_str(s: &str) -> Result<NanoBot, ParseError> {
     lazy_static!{
-      static ref RE_bot: Regex = Regex::new(r"pos=<(-?\d+),(-?\d+),(-?\d+)>, r=(\d+)").unwrap();
+      static ref RE_BOT: Regex = Regex::new(r"pos=<(-?\d+),(-?\d+),(-?\d+)>, r=(\d+)").unwrap();
     }
 
-    let capture = match RE_bot.captures(s) {
+    let capture = match RE_BOT.captures(s) {
       Some(c) => c,
       None => Err(ParseError::new("Could not parse bot"))?
     };
 
-    let pos = (capture[1].parse::<f64>()?, capture[2].parse::<f64>()?, capture[3].parse::<f64>()?);
-    let radius = capture[4].parse::<f64>()?;
+    let pos = (parse_number::<f64>(&capture[1])?, parse_number::<f64>(&capture[2])?, parse_number::<f64>(&capture[3])?);
+    let radius = parse_number::<f64>(&capture[4])?;
 
     Ok(NanoBot { pos, radius })
   }
@@ -41,17 +42,11 @@ fn manhattan_distance(p: Position, q: Position) -> f64 {
   (p.0 - q.0).abs() + (p.1 - q.1).abs() + (p.2 - q.2).abs()
 }
 
-fn load_bots() -> Result<Vec<NanoBot>, ParseError> {
-  let input = include_str!("./data/input.txt");
-
-  input
-    .split("\n")
-    .map(|s| NanoBot::from_str(s))
-    .collect::<Result<Vec<NanoBot>, ParseError>>()
-}
+fn bots_in_reach_of_strongest(bots: &[NanoBot]) -> Result<usize, Error> {
+  if bots.is_empty() {
+    Err(Error::new("No nanobots given"))?
+  }
 
-pub fn problem1() -> Result<(), Error> {
-  let bots = load_bots()?;
   let mut strongest = 0;
   let mut strongest_signal = 0f64;
   for (index, bot) in bots.iter().enumerate() {
@@ -61,23 +56,20 @@ pub fn problem1() -> Result<(), Error> {
     }
   }
 
-  println!("Strongest bot is {:?}", bots[strongest]);
   let strongest_bot = &bots[strongest];
 
   let number_in_reach = bots
     .iter()
     .filter(|b| {
-      manhattan_distance(b.pos, strongest_bot.pos.clone()) <= strongest_bot.radius
+      manhattan_distance(b.pos, strongest_bot.pos) <= strongest_bot.radius
     })
     .count();
 
-  println!("Strongest bot can reach {} other bots", number_in_reach);
-
-  Ok(())
+  Ok(number_in_reach)
 }
 
-fn scale_bots(bots: &Vec<NanoBot>, scale: f64) -> Vec<NanoBot> {
-  let mut scaled_bots = bots.clone();
+fn scale_bots(bots: &[NanoBot], scale: f64) -> Vec<NanoBot> {
+  let mut scaled_bots = bots.to_vec();
 
   for bot in scaled_bots.iter_mut() {
     bot.pos.0 /= scale;
@@ -89,7 +81,7 @@ fn scale_bots(bots: &Vec<NanoBot>, scale: f64) -> Vec<NanoBot> {
   scaled_bots
 }
 
-fn find_bots_in_range(bots: &Vec<NanoBot>, p: &Position) -> usize {
+fn find_bots_in_range(bots: &[NanoBot], p: &Position) -> usize {
   let mut in_range = 0;
   for b in bots {
     if manhattan_distance(b.pos, *p) < b.radius + 1f64 {
@@ -100,15 +92,15 @@ fn find_bots_in_range(bots: &Vec<NanoBot>, p: &Position) -> usize {
   in_range
 }
 
-fn find_intersection(bots: &Vec<NanoBot>, seed: Position, scale: f64) -> Position {
+fn find_intersection(bots: &[NanoBot], seed: Position, scale: f64) -> Position {
   let scaled_bots = scale_bots(bots, scale);
   let buffer = 16;
   let from = -buffer;
   let to = buffer;
 
   let mut max_intersect = 0;
-  let mut closest_point = (std::f64::MAX, std::f64::MAX, std::f64::MAX);
-  let mut closest_manhattan = std::f64::MAX;
+  let mut closest_point = (f64::MAX, f64::MAX, f64::MAX);
+  let mut closest_manhattan = f64::MAX;
   for z in from..to {
     for y in from..to {
       for x in from..to {
@@ -128,34 +120,46 @@ fn find_intersection(bots: &Vec<NanoBot>, seed: Position, scale: f64) -> Positio
     }
   }
 
-  println!("Max intersection: {}", max_intersect);
-  println!("Closest point: {:?} (distance: {})", closest_point, closest_manhattan);
-
   closest_point
 }
 
-pub fn problem2() -> Result<(), Error> {
-  let bots = load_bots()?;
-
+fn closest_best_position(bots: &[NanoBot]) -> f64 {
   let mut seed = (0f64, 0f64, 0f64);
-  // for i in 0..24 {
+  let mut closest = seed;
   for i in 0..8 {
-    // let scale = 2f64.powf(23f64-i as f64);
     let scale = 10f64.powf(7f64-i as f64);
-    println!("seeds: {:?}, scale: {}", seed, scale);
 
-    seed = find_intersection(&bots, seed, scale);
+    closest = find_intersection(bots, seed, scale);
+
+    seed.0 = closest.0 * 10f64;
+    seed.1 = closest.1 * 10f64;
+    seed.2 = closest.2 * 10f64;
+  }
+
+  closest.0.abs() + closest.1.abs() + closest.2.abs()
+}
+
+pub struct Day23;
+
+impl Solution for Day23 {
+  type Input = Vec<NanoBot>;
+
+  const DAY: u8 = 23;
+  const TITLE: &'static str = "Experimental Emergency Teleportation";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Vec<NanoBot>, Error> {
+    let bots = parse_lines(input, NanoBot::from_str)?;
 
-    // seed.0 *= 2f64; //10f64;
-    // seed.1 *= 2f64; //10f64;
-    // seed.2 *= 2f64; //10f64;
-    seed.0 *= 10f64;
-    seed.1 *= 10f64;
-    seed.2 *= 10f64;
+    Ok(bots)
   }
-  // seed = find_intersection(&bots, seed, 1_000_000);
-  // println!("Seed: {}, distance: {}", seeds.len(), manhattan_distance((0f64, 0f64, 0f64), seed));
-  
 
-  Ok(())
-}
\ No newline at end of file
+  fn part1(bots: &Vec<NanoBot>) -> Result<Answer, Error> {
+    Ok(Answer::from(bots_in_reach_of_strongest(bots)?))
+  }
+
+  fn part2(bots: &Vec<NanoBot>) -> Result<Answer, Error> {
+    Ok(Answer::from(closest_best_position(bots) as i64))
+  }
+}
@@ -46,7 +46,7 @@ impl Group {
     self.units * self.damage * factor
   }
 
-  pub fn select_target(&self, groups: &Vec<Group>, already_selected: &Vec<usize>) -> Option<usize> {
+  pub fn select_target(&self, groups: &[Group], already_selected: &[usize]) -> Option<usize> {
     let mut available_enemies = groups.iter()
       .filter(|v| v.id != self.id)
       .filter(|v| v.affiliation != self.affiliation)
@@ -67,7 +67,7 @@ impl Group {
         })
     });
 
-    if let Some(group) = available_enemies.get(0) {
+    if let Some(group) = available_enemies.first() {
       // some special case that's not mentioned in the description
       // but only with this my answer is accepted
       if self.calculate_damage(group) == 0 {
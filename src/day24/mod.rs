@@ -1,3 +1,5 @@
-mod parse;
-mod group;
-pub mod problems;
\ No newline at end of file
+pub mod parse;
+pub mod group;
+pub mod problems;
+
+pub use self::problems::Day24;
@@ -1,5 +1,5 @@
 use super::group::{Group, AttackType, Affiliation};
-use super::super::utils::{ParseError, Error};
+use super::super::utils::{numbered_lines, parse_number, ParseError, Error};
 use regex::Regex;
 use std::collections::HashSet;
 
@@ -10,12 +10,12 @@ fn parse_attack(attack: &str) -> Result<AttackType, ParseError> {
     "fire" => AttackType::Fire,
     "radiation" => AttackType::Radiation,
     "slashing" => AttackType::Slashing,
-    _ => Err(ParseError::new("Unknown attack type"))?
+    _ => Err(ParseError::new(&format!("Unknown attack type '{}'", attack)))?
   })
 }
 
 fn parse_weakness_or_immunity(input: &str, re: &Regex) -> Result<HashSet<AttackType>, ParseError> {
-    if !re.is_match(input) {
+  if !re.is_match(input) {
     return Ok(HashSet::new());
   }
 
@@ -25,10 +25,8 @@ fn parse_weakness_or_immunity(input: &str, re: &Regex) -> Result<HashSet<AttackT
   };
 
   let mut attack_type = HashSet::new();
-  for at in capture.iter().skip(1) {
-    if at.is_some() {
-      attack_type.insert(parse_attack(at.unwrap().as_str())?);
-    }
+  for at in capture.iter().skip(1).flatten() {
+    attack_type.insert(parse_attack(at.as_str())?);
   }
 
   Ok(attack_type)
@@ -36,80 +34,75 @@ fn parse_weakness_or_immunity(input: &str, re: &Regex) -> Result<HashSet<AttackT
 
 fn parse_weakness(input: &str) -> Result<HashSet<AttackType>, ParseError> {
   lazy_static!{
-    static ref RE_weak: Regex = Regex::new(r"weak to (\w+),?\s?(\w+)?,?\s?(\w+)?,?\s?(\w+)?").unwrap();
+    static ref RE_WEAK: Regex = Regex::new(r"weak to (\w+),?\s?(\w+)?,?\s?(\w+)?,?\s?(\w+)?").unwrap();
   }
 
-  parse_weakness_or_immunity(input, &RE_weak)
+  parse_weakness_or_immunity(input, &RE_WEAK)
 }
 
 fn parse_immunity(input: &str) -> Result<HashSet<AttackType>, ParseError> {
   lazy_static!{
-    static ref RE_immune: Regex = Regex::new(r"immune to (\w+),?\s?(\w+)?,?\s?(\w+)?,?\s?(\w+)?").unwrap();
+    static ref RE_IMMUNE: Regex = Regex::new(r"immune to (\w+),?\s?(\w+)?,?\s?(\w+)?,?\s?(\w+)?").unwrap();
   }
 
-  parse_weakness_or_immunity(input, &RE_immune)
+  parse_weakness_or_immunity(input, &RE_IMMUNE)
 }
 
-fn parse_group(s: &str, affiliation: Affiliation) -> Result<Option<Group>, ParseError> {
+fn parse_group(s: &str, affiliation: Affiliation) -> Result<Group, ParseError> {
   lazy_static!{
-    static ref RE_group: Regex = Regex::new(r"(\d+) units each with (\d+) hit points (\(.*\))? with an attack that does (\d+) (\w+) damage at initiative (\d+)").unwrap();
-    static ref RE_group_nospecial: Regex = Regex::new(r"(\d+) units each with (\d+) hit points (with) an attack that does (\d+) (\w+) damage at initiative (\d+)").unwrap();
-  }
-
-  if !RE_group.is_match(s) && !RE_group_nospecial.is_match(s) {
-    return Ok(None);
+    static ref RE_GROUP: Regex = Regex::new(r"(\d+) units each with (\d+) hit points (\(.*\))? with an attack that does (\d+) (\w+) damage at initiative (\d+)").unwrap();
+    static ref RE_GROUP_NOSPECIAL: Regex = Regex::new(r"(\d+) units each with (\d+) hit points (with) an attack that does (\d+) (\w+) damage at initiative (\d+)").unwrap();
   }
 
-  let capture = match RE_group_nospecial.captures(s) {
+  let capture = match RE_GROUP_NOSPECIAL.captures(s) {
     Some(c) => c,
-    None => match RE_group.captures(s) {
+    None => match RE_GROUP.captures(s) {
       Some(d) => d,
       None => Err(ParseError::new("Could not parse group"))?
     }
   };
 
-  let units = capture[1].parse::<u64>()?;
-  let hitpoints = capture[2].parse::<u64>()?;
+  let units = parse_number::<u64>(&capture[1])?;
+  let hitpoints = parse_number::<u64>(&capture[2])?;
   let immunities_and_weaknesses = capture[3].to_string();
-  let damage = capture[4].parse::<u64>()?;
+  let damage = parse_number::<u64>(&capture[4])?;
   let attack = parse_attack(&capture[5])?;
-  let initiative = capture[6].parse::<u64>()?;
+  let initiative = parse_number::<u64>(&capture[6])?;
   let immunity = parse_immunity(immunities_and_weaknesses.as_str())?;
   let weakness = parse_weakness(immunities_and_weaknesses.as_str())?;
 
   let id = 0;
 
-  Ok(Some(Group { id, affiliation, units, hitpoints, damage, attack, initiative, immunity, weakness }))
-}
-
-fn read_groups(input: &str, affiliation: Affiliation) -> Result<Vec<Group>, Error> {
-  let groups = input.split("\n")
-    .map(|v| parse_group(v, affiliation.clone()))
-    .collect::<Result<Vec<Option<Group>>, ParseError>>()?
-    .iter()
-    .cloned()
-    .filter(|v| v.is_some())
-    .map(|v| v.unwrap())
-    .collect::<Vec<Group>>();
-
-  Ok(groups)
+  Ok(Group { id, affiliation, units, hitpoints, damage, attack, initiative, immunity, weakness })
 }
 
-fn load_infections() -> Result<Vec<Group>, Error> {
-  let input = include_str!("./data/infection.txt");
-  // let input = include_str!("./data/ex_infection.txt");
-  read_groups(input, Affiliation::Infection)
-}
+pub fn load_groups(input: &str) -> Result<Vec<Group>, Error> {
+  let mut immune = vec![];
+  let mut groups = vec![];
+  let mut affiliation = None;
+
+  for (number, line) in numbered_lines(input) {
+    match line.trim() {
+      "Immune System:" => affiliation = Some(Affiliation::Immune),
+      "Infection:" => affiliation = Some(Affiliation::Infection),
+      _ => {
+        let group = match &affiliation {
+          Some(a) => parse_group(line, a.clone()),
+          None => Err(ParseError::new("Group is not part of the immune system or the infection")),
+        }.map_err(|err| err.at_line(number, line))?;
+
+        match group.affiliation {
+          Affiliation::Immune => immune.push(group),
+          Affiliation::Infection => groups.push(group),
+        }
+      }
+    }
+  }
 
-fn load_immune() -> Result<Vec<Group>, Error> {
-  let input = include_str!("./data/immune_system.txt");
-  // let input = include_str!("./data/ex_immune.txt");
-  read_groups(input, Affiliation::Immune)
-}
+  if groups.is_empty() {
+    Err(ParseError::new("Could not find the infection groups"))?
+  }
 
-pub fn load_groups() -> Result<Vec<Group>, Error> {
-  let mut immune = load_immune()?;
-  let mut groups = load_infections()?;
   groups.append(&mut immune);
 
   for (index, group) in groups.iter_mut().enumerate() {
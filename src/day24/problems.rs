@@ -1,11 +1,11 @@
 use super::super::utils::Error;
+use super::super::solution::{Answer, Solution};
 use super::parse::load_groups;
 use super::group::{Affiliation, Group};
 use std::collections::HashMap;
 use std::cmp::min;
 
-
-fn _print(groups: &Vec<Group>) {
+fn _print(groups: &[Group]) {
   println!("Immune system");
   for group in groups.iter().filter(|v| v.affiliation == Affiliation::Immune) {
     println!("{} - {:?}", group.effective_power(), group);
@@ -17,8 +17,8 @@ fn _print(groups: &Vec<Group>) {
   }
 }
 
-fn target_selection_order(groups: &Vec<Group>) -> Vec<usize> {
-  let mut target_selection_groups = groups.clone();
+fn target_selection_order(groups: &[Group]) -> Vec<usize> {
+  let mut target_selection_groups = groups.to_vec();
   target_selection_groups.sort_unstable_by(&Group::target_selection_order);
 
   target_selection_groups
@@ -27,13 +27,16 @@ fn target_selection_order(groups: &Vec<Group>) -> Vec<usize> {
     .collect()
 }
 
-fn target_selection_phase(groups: &Vec<Group>) -> HashMap<usize, usize> {
-  let selection_order = target_selection_order(&groups);
+fn target_selection_phase(groups: &[Group]) -> HashMap<usize, usize> {
+  let selection_order = target_selection_order(groups);
   let mut already_selected = vec![];
   let mut pairings = HashMap::new();
   for id in selection_order {
-    let group = groups.iter().filter(|v| v.id == id).next().unwrap();
-    if let Some(target) = group.select_target(&groups, &already_selected) {
+    let group = match groups.iter().find(|v| v.id == id) {
+      Some(v) => v,
+      None => continue
+    };
+    if let Some(target) = group.select_target(groups, &already_selected) {
       already_selected.push(target);
       pairings.insert(id, target);
     }
@@ -42,24 +45,30 @@ fn target_selection_phase(groups: &Vec<Group>) -> HashMap<usize, usize> {
   pairings
 }
 
-fn determine_attack_order(groups: &Vec<Group>) -> Vec<usize> {
-  let mut attack_order = groups.clone();
+fn determine_attack_order(groups: &[Group]) -> Vec<usize> {
+  let mut attack_order = groups.to_vec();
   attack_order.sort_unstable_by(&Group::attack_order);
   attack_order.iter().map(|v| v.id).collect::<Vec<usize>>()
 }
 
-fn attack_phase(groups: &mut Vec<Group>, pairings: &HashMap<usize, usize>) -> u64 {
+fn attack_phase(groups: &mut [Group], pairings: &HashMap<usize, usize>) -> u64 {
   let attack_order = determine_attack_order(groups);
   let mut total_units_lost = 0;
 
   for id in attack_order {
-    let group = groups.iter().filter(|v| v.id == id).next().unwrap();
+    let group = match groups.iter().find(|v| v.id == id) {
+      Some(v) => v,
+      None => continue
+    };
     if group.units == 0 {
       continue;
     }
 
     if let Some(target) = pairings.get(&id) {
-      let (target_index, target_group) = groups.iter().enumerate().filter(|(_, v)| v.id == *target).next().unwrap();
+      let (target_index, target_group) = match groups.iter().enumerate().find(|(_, v)| v.id == *target) {
+        Some(v) => v,
+        None => continue
+      };
       let damage = group.calculate_damage(target_group);
 
       let units_lost = damage / target_group.hitpoints;
@@ -72,27 +81,18 @@ fn attack_phase(groups: &mut Vec<Group>, pairings: &HashMap<usize, usize>) -> u6
 }
 
 fn cleanup(groups: &mut Vec<Group>) {
-  let mut remove = vec![];
-  for (index, group) in groups.iter().enumerate() {
-    if group.units == 0 {
-      remove.push(index);
-    }
-  }
-
-  while let Some(index) = remove.pop() {
-    groups.remove(index);
-  }
+  groups.retain(|group| group.units != 0);
 }
 
-fn battle_is_ongoing(groups: &Vec<Group>) -> bool {
+fn battle_is_ongoing(groups: &[Group]) -> bool {
   let number_of_immunes = groups.iter().filter(|v| v.affiliation == Affiliation::Immune).count();
   let number_of_infections = groups.iter().filter(|v| v.affiliation == Affiliation::Infection).count();
 
   number_of_immunes != 0 && number_of_infections != 0
 }
 
-fn simulate(boost: u64) -> Result<(Affiliation, u64), Error> {
-  let mut groups = load_groups()?;
+fn simulate(groups: &[Group], boost: u64) -> Result<(Affiliation, u64), Error> {
+  let mut groups = groups.to_vec();
 
   for group in &mut groups {
     if group.affiliation == Affiliation::Immune {
@@ -115,46 +115,80 @@ fn simulate(boost: u64) -> Result<(Affiliation, u64), Error> {
   Ok((groups[0].affiliation.clone(), result))
 }
 
-pub fn problem1() -> Result<(), Error> {
-  let (_, result) = simulate(0)?;
-  println!("Result: {}", result);
-  Ok(())
-}
-
-pub fn problem2() -> Result<(), Error> {
+fn smallest_winning_boost(groups: &[Group]) -> Result<u64, Error> {
   let mut boost = 50_000;
   let mut last_boost = 0;
 
   while (last_boost as i64 - boost as i64).abs() > 5 {
-    println!("Boost: {}", boost);
-    let result = simulate(boost)?;
+    let result = simulate(groups, boost)?;
 
     let tmp = last_boost;
     last_boost = boost;
 
     if result.0 == Affiliation::Immune {
       if boost < tmp {
-        boost = boost - (tmp - boost) / 2;
+        boost -= (tmp - boost) / 2;
       } else {
         boost = (tmp + boost) / 2;
       }
+    } else if boost < tmp {
+      boost = (tmp + boost) / 2;
     } else {
-      if boost < tmp {
-        boost = (tmp + boost) / 2;
-      } else {
-        boost = boost + (boost - tmp) / 2;
-      }
+      boost += (boost - tmp) / 2;
     }
   }
 
-  for b in boost-5..boost+5 {
-    let result = simulate(b)?;
-    println!("Boost {} yields {:?}", b, result);
+  for b in boost.saturating_sub(5)..boost+5 {
+    let result = simulate(groups, b)?;
     if result.0 == Affiliation::Immune {
-      println!("Result: {:?}", result);
-      break;
+      return Ok(result.1);
     }
   }
 
-  Ok(())
-}
\ No newline at end of file
+  Err(Error::new("The immune system cannot win"))
+}
+
+pub struct Day24;
+
+impl Solution for Day24 {
+  type Input = Vec<Group>;
+
+  const DAY: u8 = 24;
+  const TITLE: &'static str = "Immune System Simulator 20XX";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Vec<Group>, Error> {
+    load_groups(input)
+  }
+
+  fn part1(groups: &Vec<Group>) -> Result<Answer, Error> {
+    let (_, result) = simulate(groups, 0)?;
+
+    Ok(Answer::from(result))
+  }
+
+  fn part2(groups: &Vec<Group>) -> Result<Answer, Error> {
+    Ok(Answer::from(smallest_winning_boost(groups)?))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::super::utils::describe;
+
+  #[test]
+  fn check_example() {
+    let groups = Day24::parse(include_str!("./data/example.txt")).unwrap();
+    assert_eq!(simulate(&groups, 0).unwrap(), (Affiliation::Infection, 5216));
+    assert_eq!(simulate(&groups, 1570).unwrap(), (Affiliation::Immune, 51));
+  }
+
+  #[test]
+  fn parse_errors_point_to_the_group() {
+    let input = include_str!("./data/example.txt").replace("fire damage", "lava damage");
+    let err = Day24::parse(&input).unwrap_err();
+    assert!(describe(&err).starts_with("line 2: Unknown attack type 'lava' in \"17 units each"));
+  }
+}
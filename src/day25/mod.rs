@@ -1,9 +1,9 @@
-use super::utils::{ParseError, Error};
-use std::num::ParseIntError;
+use super::utils::{parse_lines, parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
 use std::str::FromStr;
 
-#[derive(Debug)]
-struct Point {
+#[derive(Debug, Clone)]
+pub struct Point {
   pub x: i64,
   pub y: i64,
   pub z: i64,
@@ -14,11 +14,11 @@ impl FromStr for Point {
   type Err = ParseError;
 
   fn from_str(s: &str) -> Result<Point, ParseError> {
+    // Coordinates are read as i32, so the distances between them fit an i64.
     let coords = s
       .split(',')
-      .map(|v| v.trim())
-      .map(|v| v.parse::<i64>())
-      .collect::<Result<Vec<i64>, ParseIntError>>()?;
+      .map(|c| parse_number::<i32>(c).map(i64::from))
+      .collect::<Result<Vec<i64>, ParseError>>()?;
 
     if coords.len() != 4 {
       Err(ParseError::new("Insufficient number of coordinates found"))
@@ -37,15 +37,6 @@ impl Point {
   }
 }
 
-fn read_points() -> Result<Vec<Point>, ParseError> {
-  let input = include_str!("./data/input.txt");
-
-  input.split("\n")
-    .filter(|v| *v != "")
-    .map(|v| Point::from_str(v))
-    .collect::<Result<Vec<Point>, ParseError>>()
-}
-
 fn find_next_close_point(points: &mut Vec<Point>, cluster: &mut Vec<Point>) -> bool {
   let mut to_remove = None;
   'outer: for (index, point) in points.iter().enumerate() {
@@ -70,8 +61,7 @@ fn cluster(points: &mut Vec<Point>) -> Vec<Vec<Point>> {
   let mut clusters = vec![];
   let mut cluster = vec![];
 
-  while !points.is_empty() {
-    let last = points.pop().unwrap();
+  while let Some(last) = points.pop() {
     cluster.push(last);
 
     while find_next_close_point(points, &mut cluster) {
@@ -83,19 +73,48 @@ fn cluster(points: &mut Vec<Point>) -> Vec<Vec<Point>> {
   clusters
 }
 
-fn print(points: &Vec<Point>) {
-  for p in points {
-    println!("{:?}", p);
+pub struct Day25;
+
+impl Solution for Day25 {
+  type Input = Vec<Point>;
+
+  const DAY: u8 = 25;
+  const TITLE: &'static str = "Four-Dimensional Adventure";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Vec<Point>, Error> {
+    let points = parse_lines(input, Point::from_str)?;
+
+    Ok(points)
   }
-}
 
-pub fn problem1() -> Result<(), Error> {
-  let mut points = read_points()?;
+  fn part1(points: &Vec<Point>) -> Result<Answer, Error> {
+    let mut points = points.clone();
+    let clusters = cluster(&mut points);
 
-  let clusters = cluster(&mut points);
+    Ok(Answer::from(clusters.len()))
+  }
+
+  fn part2(_points: &Vec<Point>) -> Result<Answer, Error> {
+    Ok(Answer::None)
+  }
+}
 
-  print(&points);
-  println!("Number of clusters: {}", clusters.len());
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-  Ok(())
-}
\ No newline at end of file
+  #[test]
+  fn check_example() {
+    let points = Day25::parse("0,0,0,0\n3,0,0,0\n0,3,0,0\n0,0,3,0\n0,0,0,3\n0,0,0,6\n9,0,0,0\n12,0,0,0\n").unwrap();
+    assert_eq!(Day25::part1(&points).unwrap(), Answer::Number(2));
+  }
+
+  #[test]
+  fn far_apart_points_fit() {
+    let points = Day25::parse("-2147483648,-2147483648,-2147483648,-2147483648\n2147483647,2147483647,2147483647,2147483647\n").unwrap();
+    assert_eq!(Day25::part1(&points).unwrap(), Answer::Number(2));
+    assert!(Day25::parse("0,0,9223372036854775807,0\n").is_err());
+  }
+}
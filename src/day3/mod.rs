@@ -1,12 +1,15 @@
 use std::str::FromStr;
-use std::num::ParseIntError;
 use std::collections::BTreeMap;
-
-
 use regex::Regex;
+use super::utils::{parse_lines, parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
+
+// Claims have to lie on a square of fabric this large, the puzzle inputs
+// only use the first 1000 inches in each direction.
+const FABRIC_SIZE: u32 = 2000;
 
 #[derive(Debug)]
-struct Box {
+pub struct Box {
   pub x: u32,
   pub y: u32,
   pub w: u32,
@@ -15,43 +18,34 @@ struct Box {
 }
 
 impl FromStr for Box {
-  type Err = ParseIntError;
+  type Err = ParseError;
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
     lazy_static!{
       static ref RE: Regex = Regex::new(r"\#(\d+) @ (\d+),(\d+): (\d+)x(\d+)").unwrap();
     }
-    let cap = RE.captures(s).unwrap();
+    let cap = match RE.captures(s) {
+      Some(c) => c,
+      None => Err(ParseError::new("Could not parse claim"))?
+    };
 
-    let parse = |v: &str| v.parse::<u32>().unwrap();
+    let parse = |v: &str| parse_number::<u32>(v);
 
-    let id = parse(&cap[1]);
-    let x = parse(&cap[2]);
-    let y = parse(&cap[3]);
-    let w = parse(&cap[4]);
-    let h = parse(&cap[5]);
+    let id = parse(&cap[1])?;
+    let x = parse(&cap[2])?;
+    let y = parse(&cap[3])?;
+    let w = parse(&cap[4])?;
+    let h = parse(&cap[5])?;
+
+    if x.saturating_add(w) > FABRIC_SIZE || y.saturating_add(h) > FABRIC_SIZE {
+      Err(ParseError::new(&format!("Claim #{} reaches past the {}x{} fabric", id, FABRIC_SIZE, FABRIC_SIZE)))?
+    }
 
     Ok(Box { id, x, y, w, h })
   }
 }
 
-fn parse_boxes() -> Vec<Box> {
-  let input = include_str!("./data/input.txt");
-  let boxes = input
-    .split("\n")
-    .filter(|v| *v != "")
-    .map(|v| Box::from_str(v))
-    .map(|v| match v {
-      Ok(b) => b,
-      _ => panic!("Parse error"),
-    })
-    .collect::<Vec<_>>();
-
-  boxes
-}
-
-fn squares_to_source_map() -> BTreeMap<(u32, u32), Vec<u32>> {
-  let boxes = parse_boxes();
+fn squares_to_source_map(boxes: &[Box]) -> BTreeMap<(u32, u32), Vec<u32>> {
   let mut map = BTreeMap::new();
 
   for b in boxes {
@@ -62,7 +56,7 @@ fn squares_to_source_map() -> BTreeMap<(u32, u32), Vec<u32>> {
 
         map.entry((x, y)).and_modify(|v: & mut Vec<u32>| {
           v.push(b.id);
-        }).or_insert(vec![b.id]);
+        }).or_insert_with(|| vec![b.id]);
       }
     }
   }
@@ -70,46 +64,69 @@ fn squares_to_source_map() -> BTreeMap<(u32, u32), Vec<u32>> {
   map
 }
 
-pub fn problem1() {
-  let map = squares_to_source_map();
+pub struct Day3;
 
-  let mut count = 0;
-  for square in &map {
-    if square.1.len() > 1 {
-      count += 1
-    }
+impl Solution for Day3 {
+  type Input = Vec<Box>;
+
+  const DAY: u8 = 3;
+  const TITLE: &'static str = "No Matter How You Slice It";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Vec<Box>, Error> {
+    let boxes = parse_lines(input, Box::from_str)?;
+
+    Ok(boxes)
   }
 
-  println!("Number of overlapping squares {}", count);
-}
+  fn part1(boxes: &Vec<Box>) -> Result<Answer, Error> {
+    let map = squares_to_source_map(boxes);
+    let count = map.values().filter(|ids| ids.len() > 1).count();
 
-pub fn problem2() {
-  let map = squares_to_source_map();
-  let mut doubles = BTreeMap::new();
+    Ok(Answer::from(count))
+  }
 
-  for d in map {
-    let box_ids = d.1;
+  fn part2(boxes: &Vec<Box>) -> Result<Answer, Error> {
+    let map = squares_to_source_map(boxes);
+    let mut doubles = BTreeMap::new();
 
-    if box_ids.len() == 1 {
-      doubles
-        .entry(box_ids[0])
-        .and_modify(|v| *v = *v && true)
-        .or_insert(true);
-    }
+    for (_, box_ids) in map {
+      if box_ids.len() == 1 {
+        doubles.entry(box_ids[0]).or_insert(true);
+      }
 
-    if box_ids.len() > 1 {
-      for id in box_ids {
-        doubles
-          .entry(id)
-          .and_modify(|v| *v = false)
-          .or_insert(false);
+      if box_ids.len() > 1 {
+        for id in box_ids {
+          doubles
+            .entry(id)
+            .and_modify(|v| *v = false)
+            .or_insert(false);
+        }
       }
     }
-  }
 
-  for d in doubles {
-    if d.1 == true {
-      println!("Box with id {} is non-overlapping!", d.0);
+    match doubles.into_iter().find(|(_, non_overlapping)| *non_overlapping) {
+      Some((id, _)) => Ok(Answer::from(id)),
+      None => Err(Error::new("All boxes overlap")),
     }
   }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn check_example() {
+    let boxes = Day3::parse("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n").unwrap();
+    assert_eq!(Day3::part1(&boxes).unwrap(), Answer::Number(4));
+    assert_eq!(Day3::part2(&boxes).unwrap(), Answer::Number(3));
+  }
+
+  #[test]
+  fn claims_have_to_lie_on_the_fabric() {
+    let err = Day3::parse("#1 @ 1,3: 4x4\n#2 @ 3,1: 4294967295x4\n").err().unwrap();
+    assert_eq!(err.to_string(), "line 2: Claim #2 reaches past the 2000x2000 fabric in \"#2 @ 3,1: 4294967295x4\"");
+  }
 }
\ No newline at end of file
@@ -2,17 +2,18 @@ use std::str::FromStr;
 use regex::Regex;
 use std::collections::BTreeMap;
 
-use super::utils::{preprocess_input, ParseError};
+use super::utils::{parse_lines, parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
-enum Action {
+pub enum Action {
   Starts(u32),
   FallsAsleep,
   WakesUp
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
-struct Timestamp {
+pub struct Timestamp {
   pub year: u32,
   pub month: u32,
   pub day: u32,
@@ -21,7 +22,7 @@ struct Timestamp {
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
-struct Record {
+pub struct Record {
   pub time: Timestamp,
   pub action: Action,
 }
@@ -31,30 +32,30 @@ impl FromStr for Action {
 
   fn from_str(s: &str) -> Result<Self, Self::Err> {
     lazy_static!{
-      static ref REFallsAsleep: Regex = Regex::new(r"falls asleep").unwrap();
-      static ref REWakesUp: Regex = Regex::new(r"wakes up").unwrap();
-      static ref REStarts: Regex = Regex::new(r"Guard \#(\d+) begins shift").unwrap();
+      static ref RE_FALLS_ASLEEP: Regex = Regex::new(r"falls asleep").unwrap();
+      static ref RE_WAKES_UP: Regex = Regex::new(r"wakes up").unwrap();
+      static ref RE_STARTS: Regex = Regex::new(r"Guard \#(\d+) begins shift").unwrap();
     }
 
-    if REFallsAsleep.is_match(s) {
+    if RE_FALLS_ASLEEP.is_match(s) {
       return Ok(Action::FallsAsleep);
     }
 
-    if REWakesUp.is_match(s) {
+    if RE_WAKES_UP.is_match(s) {
       return Ok(Action::WakesUp);
     }
 
-    if REStarts.is_match(s) {
-      let cap = match REStarts.captures(s) {
+    if RE_STARTS.is_match(s) {
+      let cap = match RE_STARTS.captures(s) {
         Some(capture) => capture,
         None => Err(ParseError::new("Could not parse action"))?
       };
-      let guard_id = cap[1].parse::<u32>()?;
+      let guard_id = parse_number::<u32>(&cap[1])?;
 
       return Ok(Action::Starts(guard_id));
     }
 
-    Err(ParseError::new("Could not parse action"))
+    Err(ParseError::new("Unknown action"))
   }
 }
 
@@ -65,9 +66,12 @@ impl FromStr for Timestamp {
     lazy_static!{
       static ref RE: Regex = Regex::new(r"\[(\d+)-(\d+)-(\d+) (\d+):(\d+)\] (.*)").unwrap();
     }
-    let cap = RE.captures(s).unwrap();
+    let cap = match RE.captures(s) {
+      Some(c) => c,
+      None => Err(ParseError::new("Could not find a timestamp"))?
+    };
 
-    let parse = |v: &str| v.parse::<u32>();
+    let parse = |v: &str| parse_number::<u32>(v);
 
     let year = parse(&cap[1])?;
     let month = parse(&cap[2])?;
@@ -90,20 +94,7 @@ impl FromStr for Record {
   }
 }
 
-fn parse_and_sort() -> Result<Vec<Record>, ParseError> {
-  let input = include_str!("./data/input.txt");
-
-  let mut records = preprocess_input(input)
-    .into_iter()
-    .map(|v| Record::from_str(v))
-    .collect::<Result<Vec<Record>, _>>()?;
-
-  records.sort();
-
-  Ok(records)
-}
-
-fn get_sleeping_patterns(records: &Vec<Record>) -> BTreeMap<(u32, u32), u32> {
+fn get_sleeping_patterns(records: &[Record]) -> BTreeMap<(u32, u32), u32> {
   let mut map = BTreeMap::new();
   let mut current_guard: u32 = 0;
   let mut falls_asleep_minute = 0;
@@ -126,77 +117,100 @@ fn get_sleeping_patterns(records: &Vec<Record>) -> BTreeMap<(u32, u32), u32> {
   map
 }
 
-pub fn problem1() -> Result<u32, ParseError> {
-  let records = parse_and_sort()?;
-  let sleeping_patterns = get_sleeping_patterns(&records);
+pub struct Day4;
 
-  let mut total_minutes_per_guard: BTreeMap<u32, u32> = BTreeMap::new();
-  for (key, value) in &sleeping_patterns {
-    total_minutes_per_guard
-      .entry(key.0)
-      .and_modify(|v: &mut u32| *v = *v + *value)
-      .or_insert(*value);
-  }
+impl Solution for Day4 {
+  type Input = Vec<Record>;
 
-  let mut guard_who_slept_most = 0;
-  let mut minutes_that_guard_slept = 0;
+  const DAY: u8 = 4;
+  const TITLE: &'static str = "Repose Record";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  for guard in total_minutes_per_guard {
-    if guard.1 > minutes_that_guard_slept {
-      guard_who_slept_most = guard.0;
-      minutes_that_guard_slept = guard.1;
-    }
+  fn parse(input: &str) -> Result<Vec<Record>, Error> {
+    let mut records = parse_lines(input, Record::from_str)?;
+
+    records.sort();
+
+    Ok(records)
   }
-  println!("Guard {} slept a total of {} minutes", guard_who_slept_most, minutes_that_guard_slept);
-
-  let mut minute_that_guard_slept_most = 0;
-  let mut number_of_times_that_guard_slept_in_that_minute = 0;
-  for (key, value) in &sleeping_patterns {
-    if key.0 == guard_who_slept_most && number_of_times_that_guard_slept_in_that_minute < *value {
-      minute_that_guard_slept_most = key.1;
-      number_of_times_that_guard_slept_in_that_minute = *value;
+
+  fn part1(records: &Vec<Record>) -> Result<Answer, Error> {
+    let sleeping_patterns = get_sleeping_patterns(records);
+
+    let mut total_minutes_per_guard: BTreeMap<u32, u32> = BTreeMap::new();
+    for (key, value) in &sleeping_patterns {
+      total_minutes_per_guard
+        .entry(key.0)
+        .and_modify(|v: &mut u32| *v += *value)
+        .or_insert(*value);
     }
-  }
 
-  println!("And he slept the most in minute {}", minute_that_guard_slept_most);
-  let result = guard_who_slept_most * minute_that_guard_slept_most;
-  println!("Solution: {}", result);
+    let mut guard_who_slept_most = 0;
+    let mut minutes_that_guard_slept = 0;
 
-  Ok(result)
-}
+    for guard in total_minutes_per_guard {
+      if guard.1 > minutes_that_guard_slept {
+        guard_who_slept_most = guard.0;
+        minutes_that_guard_slept = guard.1;
+      }
+    }
 
-pub fn problem2() -> Result<u32, ParseError> {
-  let records = parse_and_sort()?;
-  let sleeping_patterns = get_sleeping_patterns(&records);
-
-  let mut guard = 0;
-  let mut total_minutes = 0;
-  let mut minute = 0;
-  for (key, value) in &sleeping_patterns {
-    if *value > total_minutes {
-      guard = key.0;
-      total_minutes = *value;
-      minute = key.1;
+    let mut minute_that_guard_slept_most = 0;
+    let mut number_of_times_that_guard_slept_in_that_minute = 0;
+    for (key, value) in &sleeping_patterns {
+      if key.0 == guard_who_slept_most && number_of_times_that_guard_slept_in_that_minute < *value {
+        minute_that_guard_slept_most = key.1;
+        number_of_times_that_guard_slept_in_that_minute = *value;
+      }
     }
+
+    let result = guard_who_slept_most * minute_that_guard_slept_most;
+
+    Ok(Answer::from(result))
   }
 
-  let result = guard * minute;
-  println!("Solution: {}", result);
+  fn part2(records: &Vec<Record>) -> Result<Answer, Error> {
+    let sleeping_patterns = get_sleeping_patterns(records);
+
+    let mut guard = 0;
+    let mut total_minutes = 0;
+    let mut minute = 0;
+    for (key, value) in &sleeping_patterns {
+      if *value > total_minutes {
+        guard = key.0;
+        total_minutes = *value;
+        minute = key.1;
+      }
+    }
+
+    let result = guard * minute;
 
-  Ok(result)
+    Ok(Answer::from(result))
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use super::super::utils::describe;
 
   #[test]
   fn check_problem1_result() {
-    assert_eq!(problem1().unwrap(), 103720);
+    let records = Day4::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day4::part1(&records).unwrap(), Answer::Number(103720));
   }
 
   #[test]
   fn check_problem2_result() {
-    assert_eq!(problem2().unwrap(), 110913);
+    let records = Day4::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day4::part2(&records).unwrap(), Answer::Number(110913));
+  }
+
+  #[test]
+  fn parse_errors_point_to_the_line() {
+    let input = "[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:xx] falls asleep\n";
+    let err = Day4::parse(input).unwrap_err();
+    assert_eq!(describe(&err), "line 2: Could not find a timestamp in \"[1518-11-01 00:xx] falls asleep\"");
   }
 }
\ No newline at end of file
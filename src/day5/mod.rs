@@ -1,5 +1,8 @@
+use super::utils::Error;
+use super::solution::{Answer, Solution};
+
 fn same_letter(a: char, b: char) -> bool {
-  a.to_ascii_lowercase() == b.to_ascii_lowercase()
+  a.eq_ignore_ascii_case(&b)
 }
 
 fn different_cases(a: char, b: char) -> bool {
@@ -12,16 +15,18 @@ fn letters_cancel(a: char, b: char) -> bool {
 
 fn reduce(s: String) -> String {
   let chars = s.chars().collect::<Vec<_>>();
+  if chars.len() < 2 {
+    return s;
+  }
 
   let mut result = "".to_string();
   let last = chars.len() - 1;
   let mut last_one_matched = false;
-  for i in 0..last {
+  for (i, &chr) in chars.iter().enumerate().take(last) {
     if last_one_matched {
       last_one_matched = false;
       continue;
     }
-    let chr = chars[i];
     let peek = chars[i + 1];
 
     last_one_matched = letters_cancel(chr, peek);
@@ -52,41 +57,48 @@ pub fn reduce_repeatedly(input: String) -> String {
   polymer
 }
 
-pub fn problem1() -> usize {
-  let mut input = include_str!("./data/input.txt").to_string();
-  input.retain(|v| v.is_alphabetic());
-  let polymer = reduce_repeatedly(input);
+pub struct Day5;
 
-  println!("Result: {}", polymer.len());
+impl Solution for Day5 {
+  type Input = String;
 
-  polymer.len()
-}
+  const DAY: u8 = 5;
+  const TITLE: &'static str = "Alchemical Reduction";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-pub fn problem2() -> usize {
-  let mut input = include_str!("./data/input.txt").to_string();
-  input.retain(|v| v.is_alphabetic());
-  let input = input;
-  let start: u8 = 65;
-  let end: u8 = 91;
-
-  let mut smallest_char = 'a';
-  let mut smallest_len = input.len();
-  for c in start..end {
-    let chr = c as char;
-    let mut filtered = input.clone();
-    filtered.retain(|v| v != chr && v != chr.to_ascii_lowercase());
-    let reduced_polymer = reduce_repeatedly(filtered);
-    let len = reduced_polymer.len();
-
-    if len < smallest_len {
-      smallest_len = len;
-      smallest_char = chr;
-    }
+  fn parse(input: &str) -> Result<String, Error> {
+    let mut polymer = input.to_string();
+    polymer.retain(|v| v.is_alphabetic());
+
+    Ok(polymer)
   }
 
-  println!("Smallest polymer of length {} produced by removing char {}", smallest_len, smallest_char);
+  fn part1(input: &String) -> Result<Answer, Error> {
+    let polymer = reduce_repeatedly(input.clone());
+
+    Ok(Answer::from(polymer.len()))
+  }
 
-  smallest_len
+  fn part2(input: &String) -> Result<Answer, Error> {
+    let start: u8 = 65;
+    let end: u8 = 91;
+
+    let mut smallest_len = input.len();
+    for c in start..end {
+      let chr = c as char;
+      let mut filtered = input.clone();
+      filtered.retain(|v| v != chr && v != chr.to_ascii_lowercase());
+      let reduced_polymer = reduce_repeatedly(filtered);
+      let len = reduced_polymer.len();
+
+      if len < smallest_len {
+        smallest_len = len;
+      }
+    }
+
+    Ok(Answer::from(smallest_len))
+  }
 }
 
 #[cfg(test)]
@@ -111,6 +123,12 @@ mod tests {
     assert_eq!(reduce(input), "aden".to_string())
   }
 
+  #[test]
+  fn reduce_keeps_short_strings() {
+    assert_eq!(reduce("".to_string()), "".to_string());
+    assert_eq!(reduce("a".to_string()), "a".to_string());
+  }
+
   #[test]
   fn reduce_repeatedly_simple_case() {
     let input = "aaaAAcAAab".to_string();
@@ -119,6 +137,7 @@ mod tests {
 
   #[test]
   fn problem1_returns_correct_result() {
-    assert_eq!(problem1(), 9078);
+    let polymer = Day5::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day5::part1(&polymer).unwrap(), Answer::Number(9078));
   }
 }
\ No newline at end of file
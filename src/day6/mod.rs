@@ -1,26 +1,32 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::option::Option;
 
-use super::utils::{preprocess_input, ParseError};
+use super::grid::{Grid, Point};
+use super::utils::{parse_lines, parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
 
-type Coords = (i32, i32);
-type Location = (i32, i32);
+type Coords = Point;
+type Location = Point;
 
 fn parse_coordinate(s: &str) -> Result<Coords, ParseError> {
-  let mut split = s.split(",");
+  let mut split = s.split(',');
 
   let x_str = match split.next() {
     Some(v) => v,
     None => Err(ParseError::new("Could not parse coordinate"))?
   };
 
-  let x = x_str.trim().parse::<i32>()?;
+  let x = parse_number::<i32>(x_str)?;
 
   let y_str = match split.next() {
     Some(v) => v,
     None => Err(ParseError::new("Could not parse coordinate"))?
   };
-  let y = y_str.trim().parse::<i32>()?;
+  let y = parse_number::<i32>(y_str)?;
+
+  if !(0..SIZE as i32).contains(&x) || !(0..SIZE as i32).contains(&y) {
+    Err(ParseError::new(&format!("Coordinate {}, {} is outside of the {}x{} area that is searched", x, y, SIZE, SIZE)))?
+  }
 
   Ok((x, y))
 }
@@ -29,7 +35,7 @@ fn manhattan_distance(p: Location, q: Coords) -> i32 {
   (p.0 - q.0).abs() + (p.1 - q.1).abs()
 }
 
-fn find_closest_point(q: Location, points: &Vec<Coords>) -> (Option<Coords>, i32) {
+fn find_closest_point(q: Location, points: &[Coords]) -> (Option<Coords>, i32) {
   let mut closest_point = Some((0, 0));
   let mut closest_distance = 1000000;
   for p in points {
@@ -46,87 +52,62 @@ fn find_closest_point(q: Location, points: &Vec<Coords>) -> (Option<Coords>, i32
   (closest_point, closest_distance)
 }
 
-fn calculate_closest_point_map(coords: &Vec<Coords>, start: Location, end: Location) -> BTreeMap<Location, (Option<Coords>, i32)> {
-  let mut map = BTreeMap::new();
-  for x in start.0..end.0 {
-    for y in start.1..end.1 {
-      let closest_point_and_distance = find_closest_point((x, y), &coords);
-      map.insert((x, y), closest_point_and_distance);
-    }
-  }
-  map
-}
+// The area that is searched, points on its edge belong to infinite areas.
+const SIZE: usize = 500;
 
-fn is_infinite(p: Location) -> bool {
-  p.0 == 0 || p.1 == 0 || p.0 == 499 || p.1 == 499
+fn calculate_closest_point_map(coords: &[Coords]) -> Grid<(Option<Coords>, i32)> {
+  Grid::from_fn((0, 0), SIZE, SIZE, |p| find_closest_point(p, coords))
 }
 
-pub fn problem1() -> Result<i32, ParseError> {
-  let input = include_str!("./data/input.txt");
+pub struct Day6;
 
-  let coords = preprocess_input(input)
-    .into_iter()
-    .map(|v| parse_coordinate(v))
-    .collect::<Result<Vec<Coords>, _>>()?;
+impl Solution for Day6 {
+  type Input = Vec<Coords>;
 
-  let map = calculate_closest_point_map(&coords, (0, 0), (500, 500));
+  const DAY: u8 = 6;
+  const TITLE: &'static str = "Chronal Coordinates";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  let mut area_map = BTreeMap::new();
-  for (point, (closest_point, _distance)) in &map {
-    match closest_point {
-      Some(p) => {
-        area_map.entry(p).and_modify(|v| *v += 1).or_insert(1);
-        if is_infinite(*point) {
-          area_map.entry(p).and_modify(|v| *v = -50000);
-        }
-      },
-      None => ()
-    }
-  }
+  fn parse(input: &str) -> Result<Vec<Coords>, Error> {
+    let coords = parse_lines(input, parse_coordinate)?;
 
-  let mut largest_area = 0;
-  for (_point, area) in area_map {
-    if largest_area < area {
-      largest_area = area;
-    }
+    Ok(coords)
   }
 
-  println!("largest area: {}", largest_area);
-
-  Ok(largest_area)
-}
-
-
+  fn part1(coords: &Vec<Coords>) -> Result<Answer, Error> {
+    let map = calculate_closest_point_map(coords);
 
-pub fn problem2() -> Result<usize, ParseError> {
-  let input = include_str!("./data/input.txt");
-
-  let coords = preprocess_input(input)
-    .into_iter()
-    .map(|v| parse_coordinate(v))
-    .collect::<Result<Vec<Coords>, _>>()?;
-
-  let mut list_of_good_points = vec![];
-  for x in 0..500 {
-    for y in 0..500 {
-      let mut sum_of_distances = 0;
-      for p in &coords {
-        sum_of_distances += manhattan_distance((x, y), *p);
-      }
-
-      if sum_of_distances < 10000 {
-        list_of_good_points.push((x, y));
+    let mut area_map = BTreeMap::new();
+    let mut infinite = BTreeSet::new();
+    for (point, (closest_point, _distance)) in map.iter() {
+      if let Some(p) = closest_point {
+        area_map.entry(p).and_modify(|v| *v += 1).or_insert(1);
+        if map.on_edge(point) {
+          infinite.insert(p);
+        }
       }
     }
+
+    let largest_area = area_map
+      .iter()
+      .filter(|(p, _)| !infinite.contains(*p))
+      .map(|(_, &area)| area)
+      .fold(0, i32::max);
+
+    Ok(Answer::from(largest_area))
   }
 
-  let result = list_of_good_points.len();
-  println!("Area of good points: {}", result);
+  fn part2(coords: &Vec<Coords>) -> Result<Answer, Error> {
+    let distances = Grid::from_fn((0, 0), SIZE, SIZE, |q| {
+      coords.iter().map(|p| manhattan_distance(q, *p) as i64).sum::<i64>()
+    });
+    let good_points = distances.values().filter(|&&sum| sum < 10000).count();
 
-  Ok(result)
+    Ok(Answer::from(good_points))
+  }
 }
 
-
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -138,13 +119,22 @@ mod tests {
     assert_eq!(manhattan_distance(p, q), 12);
   }
 
+  #[test]
+  fn coordinates_outside_of_the_area_are_rejected() {
+    let err = Day6::parse("1, 1\n2147483647, 3\n").err().unwrap();
+    assert_eq!(err.to_string(), "line 2: Coordinate 2147483647, 3 is outside of the 500x500 area that is searched in \"2147483647, 3\"");
+    assert!(Day6::parse("1, -1\n").is_err());
+  }
+
   #[test]
   fn problem1_result_is_correct() {
-    assert_eq!(problem1().unwrap(), 4398);
+    let coords = Day6::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day6::part1(&coords).unwrap(), Answer::Number(4398));
   }
 
   #[test]
   fn problem2_result_is_correct() {
-    assert_eq!(problem2().unwrap(), 39560);
+    let coords = Day6::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day6::part2(&coords).unwrap(), Answer::Number(39560));
   }
 }
\ No newline at end of file
@@ -1,22 +1,22 @@
 use regex::Regex;
 use std::collections::BTreeSet;
-use super::super::utils::{preprocess_input, ParseError};
+use super::super::utils::{parse_lines, Error, ParseError};
 
 pub type Rule = (char, char);
 
 pub fn get_char(s: &str) -> Result<char, ParseError> {
   match s.chars().next() {
     Some(v) => Ok(v),
-    None => Err(ParseError::new("Could not find vertex"))?
+    None => Err(ParseError::new("Could not find vertex"))
   }
 }
 
 pub fn parse_edge(s: &str) -> Result<Rule, ParseError> {
     lazy_static!{
-      static ref REEdge: Regex = Regex::new(r"Step (.) must be finished before step (.) can begin\.").unwrap();
+      static ref RE_EDGE: Regex = Regex::new(r"Step ([A-Z]) must be finished before step ([A-Z]) can begin\.").unwrap();
     }
 
-    let cap = match REEdge.captures(s) {
+    let cap = match RE_EDGE.captures(s) {
       Some(capture) => capture,
       None => Err(ParseError::new("Could not parse rule"))?
     };
@@ -27,18 +27,11 @@ pub fn parse_edge(s: &str) -> Result<Rule, ParseError> {
     Ok((from, to))
 }
 
-pub fn extract_rules() -> Result<Vec<Rule>, ParseError> {
-  let input = include_str!("./data/input.txt");
-
-  let rules = preprocess_input(input)
-    .into_iter()
-    .map(|v| parse_edge(v))
-    .collect::<Result<Vec<Rule>, _>>();
-
-  rules
+pub fn extract_rules(input: &str) -> Result<Vec<Rule>, ParseError> {
+  parse_lines(input, parse_edge)
 }
 
-pub fn find_root(rules: &Vec<Rule>) -> Vec<char> {
+pub fn find_root(rules: &[Rule]) -> Vec<char> {
   let mut root_letters = BTreeSet::new();
 
   for r in rules {
@@ -52,7 +45,7 @@ pub fn find_root(rules: &Vec<Rule>) -> Vec<char> {
   root_letters.into_iter().collect()
 }
 
-pub fn all_dependencies_done(rules: &Vec<Rule>, task: char, done: &Vec<char>) -> bool {
+pub fn all_dependencies_done(rules: &[Rule], task: char, done: &[char]) -> bool {
   let mut all_dependencies_visited = true;
   for s in rules {
     if task == s.1 && !done.contains(&s.0) {
@@ -61,4 +54,20 @@ pub fn all_dependencies_done(rules: &Vec<Rule>, task: char, done: &Vec<char>) ->
   }
 
   all_dependencies_visited
-}
\ No newline at end of file
+}
+
+// Steps that depend on each other in a circle never become available.
+pub fn check_all_done(rules: &[Rule], done: &[char]) -> Result<(), Error> {
+  let left = rules
+    .iter()
+    .flat_map(|&(from, to)| vec![from, to])
+    .filter(|step| !done.contains(step))
+    .collect::<BTreeSet<char>>();
+
+  if !left.is_empty() {
+    let steps = left.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(", ");
+    Err(Error::new(&format!("Steps {} can never begin, their dependencies form a cycle", steps)))?
+  }
+
+  Ok(())
+}
@@ -1,74 +1,93 @@
-use std::collections::BTreeSet;
-use super::utils::ParseError;
+use super::utils::Error;
+use super::solution::{Answer, Solution};
 
-mod common;
-mod problem1;
-mod problem2;
+pub mod common;
+pub mod problem1;
+pub mod problem2;
 
-pub fn problem1() -> Result<String, ParseError> {
-  let rules = common::extract_rules()?;
-  let mut root_letters = common::find_root(&rules);
+pub struct Day7;
 
-  root_letters.sort();
+impl Solution for Day7 {
+  type Input = Vec<common::Rule>;
 
-  let mut already_visited = vec![];
-  problem1::traverse(&rules, &mut root_letters, &mut already_visited);
+  const DAY: u8 = 7;
+  const TITLE: &'static str = "The Sum of Its Parts";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  let result: String = already_visited.iter().collect();
-  println!("{}", result);
+  fn parse(input: &str) -> Result<Vec<common::Rule>, Error> {
+    Ok(common::extract_rules(input)?)
+  }
 
-  Ok(result)
-}
+  fn part1(rules: &Vec<common::Rule>) -> Result<Answer, Error> {
+    let mut root_letters = common::find_root(rules);
+
+    root_letters.sort();
+
+    let mut already_visited = vec![];
+    problem1::traverse(rules, &mut root_letters, &mut already_visited);
+    common::check_all_done(rules, &already_visited)?;
+
+    let result: String = already_visited.iter().collect();
 
-pub fn problem2() -> Result<i32, ParseError> {
-  let rules = common::extract_rules()?;
-  let root = common::find_root(&rules);
-  let mut letters = BTreeSet::new();
-  for r in root {
-    letters.insert(r);
+    Ok(Answer::from(result))
   }
 
-  let number_of_elves = 5;
-  let mut workers: Vec<Option<problem2::Slot>> = vec![None; number_of_elves];
-  let mut tick = -1;
-  let mut done = vec![];
-  let mut in_work = vec![];
-
-  loop {
-    workers = workers.iter().map(|w| {
-      match w {
-        Some((old_task, 1)) => {
-          done.push(*old_task);
-          problem2::fetch_new_task(&rules, &done, &mut in_work)
-        },
-        None => problem2::fetch_new_task(&rules, &done, &mut in_work),
-        Some((task, ticks_remaining)) => Some((*task, ticks_remaining - 1))
+  fn part2(rules: &Vec<common::Rule>) -> Result<Answer, Error> {
+    let number_of_elves = 5;
+    let mut workers: Vec<Option<problem2::Slot>> = vec![None; number_of_elves];
+    let mut tick = -1;
+    let mut done = vec![];
+    let mut in_work = vec![];
+
+    loop {
+      workers = workers.iter().map(|w| {
+        match w {
+          Some((old_task, 1)) => {
+            done.push(*old_task);
+            problem2::fetch_new_task(rules, &done, &mut in_work)
+          },
+          None => problem2::fetch_new_task(rules, &done, &mut in_work),
+          Some((task, ticks_remaining)) => Some((*task, ticks_remaining - 1))
+        }
+      }).collect();
+
+      if problem2::all_workers_idle(&workers) {
+        break;
       }
-    }).collect();
 
-    if problem2::all_workers_idle(&workers) {
-      break;
+      tick += 1;
     }
+    common::check_all_done(rules, &done)?;
 
-    tick += 1;
+    Ok(Answer::from(tick))
   }
-
-  println!("Parallel work with 5 elves took {} ticks", tick);
-
-  Ok(tick)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn cycles_are_errors() {
+    let input = "Step A must be finished before step B can begin.\n\
+                 Step B must be finished before step C can begin.\n\
+                 Step C must be finished before step B can begin.\n";
+    let rules = Day7::parse(input).unwrap();
+    let message = "Steps B, C can never begin, their dependencies form a cycle";
+    assert_eq!(Day7::part1(&rules).unwrap_err().to_string(), message);
+    assert_eq!(Day7::part2(&rules).unwrap_err().to_string(), message);
+  }
+
   #[test]
   fn check_problem1_works() {
-    assert_eq!(problem1().unwrap(), "ABGKCMVWYDEHFOPQUILSTNZRJX");
+    let rules = Day7::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day7::part1(&rules).unwrap(), Answer::from("ABGKCMVWYDEHFOPQUILSTNZRJX"));
   }
 
   #[test]
   fn check_problem2_works() {
-    assert_eq!(problem2().unwrap(), 898);
+    let rules = Day7::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day7::part2(&rules).unwrap(), Answer::Number(898));
   }
 }
\ No newline at end of file
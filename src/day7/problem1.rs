@@ -1,7 +1,7 @@
 use std::collections::BTreeSet;
 use super::common::{Rule, all_dependencies_done};
 
-fn find_next_steps(rules: &Vec<Rule>, task: char, done: &Vec<char>) -> Vec<char> {
+fn find_next_steps(rules: &[Rule], task: char, done: &[char]) -> Vec<char> {
   let mut next_letters = BTreeSet::new();
 
   for (from, to) in rules {
@@ -13,7 +13,7 @@ fn find_next_steps(rules: &Vec<Rule>, task: char, done: &Vec<char>) -> Vec<char>
   next_letters.into_iter().collect()
 }
 
-fn find_next_letters(rules: &Vec<Rule>, letters: &mut Vec<char>, done: &mut Vec<char>) -> Vec<char> {
+fn find_next_letters(rules: &[Rule], letters: &mut Vec<char>, done: &mut Vec<char>) -> Vec<char> {
   if letters.is_empty() {
     return vec![];
   }
@@ -28,7 +28,7 @@ fn find_next_letters(rules: &Vec<Rule>, letters: &mut Vec<char>, done: &mut Vec<
   next_steps
 }
 
-pub fn traverse(rules: &Vec<Rule>, letters: &mut Vec<char>, already_visited: &mut Vec<char>) {
+pub fn traverse(rules: &[Rule], letters: &mut Vec<char>, already_visited: &mut Vec<char>) {
   let mut next_steps = find_next_letters(rules, letters, already_visited);
   if next_steps.is_empty() {
     return;
@@ -7,7 +7,7 @@ fn get_time(c: char) -> u8 {
   (c as u8) - 4
 }
 
-fn find_open_tasks(rules: &Vec<Rule>, done: &Vec<char>, in_work: &Vec<char>) -> BTreeSet<char> {
+fn find_open_tasks(rules: &[Rule], done: &[char], in_work: &[char]) -> BTreeSet<char> {
   let mut next_letters = BTreeSet::new();
 
   let already_assigned = |c| done.contains(&c) || in_work.contains(&c);
@@ -28,17 +28,13 @@ fn find_open_tasks(rules: &Vec<Rule>, done: &Vec<char>, in_work: &Vec<char>) ->
   next_letters
 }
 
-pub fn all_workers_idle(workers: &Vec<Option<Slot>>) -> bool {
-  workers.iter().all(|w| *w == None)
+pub fn all_workers_idle(workers: &[Option<Slot>]) -> bool {
+  workers.iter().all(|w| w.is_none())
 }
 
-pub fn fetch_new_task(rules: &Vec<Rule>, done: &Vec<char>, in_work: &mut Vec<char>) -> Option<Slot> {
+pub fn fetch_new_task(rules: &[Rule], done: &[char], in_work: &mut Vec<char>) -> Option<Slot> {
   let open_tasks = find_open_tasks(rules, done, in_work);
-  if open_tasks.is_empty() {
-    return None;
-  }
-
-  let next_letter = *(open_tasks.iter().next().unwrap());
+  let next_letter = *open_tasks.iter().next()?;
   let time = get_time(next_letter);
 
   in_work.push(next_letter);
@@ -1,31 +1,36 @@
 use std::iter::Iterator;
 
-use super::utils::ParseError;
+use super::utils::{parse_number, ParseError, Error};
+use super::solution::{Answer, Solution};
 
 #[derive(Debug)]
-struct Node {
+pub struct Node {
   pub children: Vec<Node>,
   pub metadata: Vec<i32>,
 }
 
 fn next_number<'a, I>(it: &mut I) -> Result<i32, ParseError>
 where I: Iterator<Item = &'a str> {
-  let val = it.next();
-  let result = match val {
-    Some(v) => v.parse::<i32>()?,
-    None => Err(ParseError::new("Found no value"))?
-  };
-
-  Ok(result)
+  match it.next() {
+    Some(v) => Ok(parse_number::<i32>(v)?),
+    None => Err(ParseError::new("Found no value"))
+  }
 }
 
-fn parse_tree<'a, I>(input: &mut I) -> Result<Node, ParseError> where I: Iterator<Item = &'a str> {
+// Deeper trees are rejected instead of running out of stack.
+const MAX_DEPTH: usize = 100;
+
+fn parse_tree<'a, I>(input: &mut I, depth: usize) -> Result<Node, ParseError> where I: Iterator<Item = &'a str> {
+  if depth > MAX_DEPTH {
+    Err(ParseError::new(&format!("Tree is nested deeper than {} levels", MAX_DEPTH)))?
+  }
+
   let number_of_children = next_number(input)?;
   let number_of_metadata = next_number(input)?;
 
   let mut children = vec![];
   for _i in 0..number_of_children {
-    let child = parse_tree(input)?;
+    let child = parse_tree(input, depth + 1)?;
     children.push(child);
   }
   let mut metadata = vec![];
@@ -38,53 +43,64 @@ fn parse_tree<'a, I>(input: &mut I) -> Result<Node, ParseError> where I: Iterato
   Ok(root)
 }
 
-fn metadata_sum(root: &Node) -> i32 {
-  let mut sum = root.metadata.iter().sum();
-
-  for c in &root.children {
-    sum += metadata_sum(c);
+fn checked_sum<I>(values: I) -> Result<i32, Error> where I: Iterator<Item = i32> {
+  let mut sum: i32 = 0;
+  for v in values {
+    sum = match sum.checked_add(v) {
+      Some(sum) => sum,
+      None => Err(Error::new("The sum of the metadata is out of range"))?
+    };
   }
 
-  sum
+  Ok(sum)
 }
 
-pub fn problem1() -> Result<i32, ParseError> {
-  let input = include_str!("./data/input.txt");
-  let root = parse_tree(&mut input.split(' '))?;
-  let sum = metadata_sum(&root);
-
-  println!("Metadata sum: {}", sum);
+fn metadata_sum(root: &Node) -> Result<i32, Error> {
+  let children = root.children.iter().map(metadata_sum).collect::<Result<Vec<_>, _>>()?;
 
-  Ok(sum)
+  checked_sum(root.metadata.iter().cloned().chain(children))
 }
 
-fn value_of_node(root: &Node) -> i32 {
-  let mut value = 0;
-
+fn value_of_node(root: &Node) -> Result<i32, Error> {
   if root.children.is_empty() {
-    return root.metadata.iter().sum();
+    return checked_sum(root.metadata.iter().cloned());
   }
 
+  let mut values = vec![];
   for m in &root.metadata {
-    let index = m - 1;
-    value += match root.children.get(index as usize) {
-      Some(v) => value_of_node(v),
+    let index = *m as i64 - 1;
+    values.push(match root.children.get(index as usize) {
+      Some(v) => value_of_node(v)?,
       None => 0
-    };
+    });
   }
 
-  value
+  checked_sum(values.into_iter())
 }
 
-pub fn problem2() -> Result<i32, ParseError> {
-  let input = include_str!("./data/input.txt");
-  let root = parse_tree(&mut input.split(' '))?;
+pub struct Day8;
+
+impl Solution for Day8 {
+  type Input = Node;
 
-  let result = value_of_node(&root);
+  const DAY: u8 = 8;
+  const TITLE: &'static str = "Memory Maneuver";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
 
-  println!("value of root node: {}", result);
+  fn parse(input: &str) -> Result<Node, Error> {
+    let root = parse_tree(&mut input.split_whitespace(), 0)?;
 
-  Ok(result)
+    Ok(root)
+  }
+
+  fn part1(root: &Node) -> Result<Answer, Error> {
+    Ok(Answer::from(metadata_sum(root)?))
+  }
+
+  fn part2(root: &Node) -> Result<Answer, Error> {
+    Ok(Answer::from(value_of_node(root)?))
+  }
 }
 
 #[cfg(test)]
@@ -93,11 +109,35 @@ mod tests {
 
   #[test]
   fn check_problem1_result() {
-    assert_eq!(problem1().unwrap(), 46781);
+    let root = Day8::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day8::part1(&root).unwrap(), Answer::Number(46781));
   }
 
   #[test]
   fn check_problem2_result() {
-    assert_eq!(problem2().unwrap(), 21405);
+    let root = Day8::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day8::part2(&root).unwrap(), Answer::Number(21405));
+  }
+
+  #[test]
+  fn deep_trees_are_rejected() {
+    // Every node but the innermost has a single child and no metadata.
+    let deepest = "1 0 ".repeat(MAX_DEPTH) + "0 0";
+    assert!(Day8::parse(&deepest).is_ok());
+
+    let too_deep = "1 0 ".repeat(MAX_DEPTH + 1) + "0 0";
+    let err = Day8::parse(&too_deep).err().unwrap().to_string();
+    assert!(err.contains(&format!("nested deeper than {} levels", MAX_DEPTH)), "{}", err);
+  }
+
+  #[test]
+  fn large_sums_are_rejected() {
+    let root = Day8::parse("2 0 0 1 2147483647 0 1 1").unwrap();
+    assert_eq!(Day8::part1(&root).err().unwrap().to_string(), "The sum of the metadata is out of range");
+
+    let root = Day8::parse("0 2 2147483647 1").unwrap();
+    assert!(Day8::part2(&root).is_err());
+    let root = Day8::parse("1 1 0 1 5 -2147483648").unwrap();
+    assert_eq!(Day8::part2(&root).unwrap(), Answer::Number(0));
   }
-}
\ No newline at end of file
+}
@@ -1,13 +1,15 @@
 use std::collections::VecDeque;
-use super::utils::Error;
+use regex::Regex;
+use super::utils::{parse_number, Error};
+use super::solution::{Answer, Solution};
 
 trait Rotation {
-  fn rotate_forward(self: &mut Self, number: usize) -> Result<(), Error>;
-  fn rotate_backward(self: &mut Self, number: usize) -> Result<(), Error>;
+  fn rotate_forward(&mut self, number: usize) -> Result<(), Error>;
+  fn rotate_backward(&mut self, number: usize) -> Result<(), Error>;
 }
 
 impl<T> Rotation for VecDeque<T> {
-  fn rotate_forward(self: &mut VecDeque<T>, number: usize) -> Result<(), Error> {
+  fn rotate_forward(&mut self, number: usize) -> Result<(), Error> {
     for _i in 0..number {
       let front = match self.pop_front() {
         Some(v) => v,
@@ -18,7 +20,7 @@ impl<T> Rotation for VecDeque<T> {
     Ok(())
   }
 
-  fn rotate_backward(self: &mut VecDeque<T>, number: usize) -> Result<(), Error> {
+  fn rotate_backward(&mut self, number: usize) -> Result<(), Error> {
     for _i in 0..number {
       let front = match self.pop_back() {
         Some(v) => v,
@@ -36,7 +38,7 @@ fn play(number_of_players: u64, last_marble: u64) -> Result<u64, Error> {
   let mut player = 0;
   let mut scores = vec![0; number_of_players as usize];
 
-  for marble in 1..(last_marble+1) {
+  for marble in 1..=last_marble {
     player = (player % number_of_players) + 1;
 
     if marble % 23 == 0 {
@@ -57,23 +59,62 @@ fn play(number_of_players: u64, last_marble: u64) -> Result<u64, Error> {
   Ok(scores.iter().cloned().fold(0, u64::max))
 }
 
-pub fn problem1() -> Result<u64, Error> {
-  let players = 405;
-  let last_marble = 71700;
-  let highscore = play(players, last_marble)?;
+// Part 2 plays a hundred times as many marbles, so these keep the circle
+// within a few hundred megabytes. The puzzle inputs stay well below them.
+const MAX_PLAYERS: u64 = 1_000;
+const MAX_MARBLE: u64 = 100_000;
 
-  println!("With {} players and last marble {}, highscore is {}", players, last_marble, highscore);
-  Ok(highscore)
+pub struct Game {
+  pub players: u64,
+  pub last_marble: u64,
 }
 
-pub fn problem2() -> Result<u64, Error> {
-  let players = 405;
-  let last_marble = 7170000;
-  let highscore = play(players, last_marble)?;
+pub struct Day9;
 
-  println!("With {} players and last marble {}, highscore is {}", players, last_marble, highscore);
+impl Solution for Day9 {
+  type Input = Game;
 
-  Ok(highscore)
+  const DAY: u8 = 9;
+  const TITLE: &'static str = "Marble Mania";
+  #[cfg(feature = "embedded-inputs")]
+  const INPUT: Option<&'static str> = Some(include_str!("./data/input.txt"));
+
+  fn parse(input: &str) -> Result<Game, Error> {
+    lazy_static!{
+      static ref RE_GAME: Regex = Regex::new(r"(\d+) players; last marble is worth (\d+) points").unwrap();
+    }
+
+    let capture = match RE_GAME.captures(input) {
+      Some(c) => c,
+      None => Err(Error::new("Could not parse game description"))?
+    };
+
+    let players = parse_number::<u64>(&capture[1])?;
+    let last_marble = parse_number::<u64>(&capture[2])?;
+    if players == 0 {
+      Err(Error::new("The game needs at least one player"))?
+    }
+    if players > MAX_PLAYERS {
+      Err(Error::new(&format!("The game can't have more than {} players", MAX_PLAYERS)))?
+    }
+    if last_marble > MAX_MARBLE {
+      Err(Error::new(&format!("The last marble can't be worth more than {} points", MAX_MARBLE)))?
+    }
+
+    Ok(Game { players, last_marble })
+  }
+
+  fn part1(game: &Game) -> Result<Answer, Error> {
+    let highscore = play(game.players, game.last_marble)?;
+
+    Ok(Answer::from(highscore))
+  }
+
+  fn part2(game: &Game) -> Result<Answer, Error> {
+    let highscore = play(game.players, game.last_marble * 100)?;
+
+    Ok(Answer::from(highscore))
+  }
 }
 
 #[cfg(test)]
@@ -110,13 +151,29 @@ mod tests {
     assert_eq!(play(30, 5807).unwrap(), 37305);
   }
 
+  #[test]
+  fn parse_reads_game_description() {
+    let game = Day9::parse("10 players; last marble is worth 1618 points").unwrap();
+    assert_eq!(Day9::part1(&game).unwrap(), Answer::Number(8317));
+  }
+
   #[test]
   fn check_problem1() {
-    assert_eq!(problem1().unwrap(), 428690);
+    let game = Day9::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day9::part1(&game).unwrap(), Answer::Number(428690));
   }
 
   #[test]
   fn check_problem2() {
-    assert_eq!(problem2().unwrap(), 3628143500);
+    let game = Day9::parse(include_str!("./data/input.txt")).unwrap();
+    assert_eq!(Day9::part2(&game).unwrap(), Answer::Number(3628143500));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn large_games_are_rejected() {
+    let err = Day9::parse("2147483647 players; last marble is worth 25 points").err().unwrap();
+    assert_eq!(err.to_string(), "The game can't have more than 1000 players");
+    let err = Day9::parse("9 players; last marble is worth 18446744073709551615 points").err().unwrap();
+    assert_eq!(err.to_string(), "The last marble can't be worth more than 100000 points");
+  }
+}
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::str::FromStr;
+use super::super::utils::{Error, ParseError};
+use super::opcode::Opcode;
+use super::word::Word;
+
+// What the machine does when a sum or a product doesn't fit. Only `addr`,
+// `addi`, `mulr` and `muli` can overflow, so the mode only changes those,
+// and it does so the same way in debug and release builds. A jump past the
+// largest word always stops the program, whatever the mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arithmetic {
+  // Wraps around at the width of the word.
+  Wrapping,
+  // Stops the program with an error naming the instruction.
+  #[default]
+  Checked,
+  // Clamps to the smallest or largest word.
+  Saturating,
+  // Wraps around at the given number of bits, like a device with registers
+  // of that width. Registers and immediates are expected to fit already.
+  // Only widths up to the word size make a difference, there is no
+  // arbitrary precision.
+  WrappingBits(u32),
+}
+
+impl Arithmetic {
+  // None if the sum overflows in checked mode.
+  pub fn add<W: Word>(self, a: W, b: W) -> Option<W> {
+    match self {
+      Arithmetic::Wrapping => Some(a.wrapping_add(b)),
+      Arithmetic::Checked => a.checked_add(b),
+      Arithmetic::Saturating => Some(a.saturating_add(b)),
+      Arithmetic::WrappingBits(bits) => Some(a.wrapping_add(b).truncate(bits)),
+    }
+  }
+
+  pub fn mul<W: Word>(self, a: W, b: W) -> Option<W> {
+    match self {
+      Arithmetic::Wrapping => Some(a.wrapping_mul(b)),
+      Arithmetic::Checked => a.checked_mul(b),
+      Arithmetic::Saturating => Some(a.saturating_mul(b)),
+      Arithmetic::WrappingBits(bits) => Some(a.wrapping_mul(b).truncate(bits)),
+    }
+  }
+
+  // Like `Opcode::apply`, but sums and products follow the mode.
+  pub fn apply<W: Word>(self, opcode: Opcode, a: W, b: W) -> Option<W> {
+    match opcode {
+      Opcode::Addr | Opcode::Addi => self.add(a, b),
+      Opcode::Mulr | Opcode::Muli => self.mul(a, b),
+      _ => Some(opcode.apply(a, b)),
+    }
+  }
+}
+
+// The error of an instruction that overflowed in checked mode.
+#[cold]
+pub fn overflow<W: Word>(opcode: Opcode, a: W, b: W) -> Error {
+  let operator = match opcode {
+    Opcode::Mulr | Opcode::Muli => "*",
+    _ => "+",
+  };
+  Error::new(&format!("{} overflows: {} {} {}", opcode, a, operator, b))
+}
+
+// The error of a jump whose next instruction pointer doesn't fit.
+#[cold]
+pub fn jump_overflow<W: Word>(target: W) -> Error {
+  Error::new(&format!("The instruction pointer overflows: {} + 1", target))
+}
+
+impl fmt::Display for Arithmetic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Arithmetic::Wrapping => write!(f, "wrapping"),
+      Arithmetic::Checked => write!(f, "checked"),
+      Arithmetic::Saturating => write!(f, "saturating"),
+      Arithmetic::WrappingBits(bits) => write!(f, "{} bits", bits),
+    }
+  }
+}
+
+// Either a mode by name or a number of bits, e.g. `checked` or `36`.
+impl FromStr for Arithmetic {
+  type Err = ParseError;
+
+  fn from_str(s: &str) -> Result<Arithmetic, ParseError> {
+    match s {
+      "wrapping" => Ok(Arithmetic::Wrapping),
+      "checked" => Ok(Arithmetic::Checked),
+      "saturating" => Ok(Arithmetic::Saturating),
+      _ => match s.parse::<u32>() {
+        Ok(bits) if bits > 0 => Ok(Arithmetic::WrappingBits(bits)),
+        _ => Err(ParseError::new(&format!("Unknown arithmetic '{}'", s))),
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn wrapping_wraps_at_the_word_width() {
+    assert_eq!(Arithmetic::Wrapping.add(i32::MAX, 1), Some(i32::MIN));
+    assert_eq!(Arithmetic::Wrapping.mul(0x10000, 0x10000), Some(0i32));
+    assert_eq!(Arithmetic::Wrapping.apply(Opcode::Muli, i64::MAX, 2), Some(-2));
+  }
+
+  #[test]
+  fn checked_refuses_overflows() {
+    assert_eq!(Arithmetic::Checked.add(i32::MAX, 1), None);
+    assert_eq!(Arithmetic::Checked.mul(i128::MIN, -1), None);
+    assert_eq!(Arithmetic::Checked.apply(Opcode::Addr, 2, 3), Some(5i64));
+    assert_eq!(overflow(Opcode::Muli, 1 << 20, 1 << 20i32).to_string(), "muli overflows: 1048576 * 1048576");
+    assert_eq!(jump_overflow(i32::MAX).to_string(), "The instruction pointer overflows: 2147483647 + 1");
+  }
+
+  #[test]
+  fn saturating_clamps() {
+    assert_eq!(Arithmetic::Saturating.add(i32::MAX, 1), Some(i32::MAX));
+    assert_eq!(Arithmetic::Saturating.mul(i64::MIN, 2), Some(i64::MIN));
+    assert_eq!(Arithmetic::Saturating.mul(-3, 4), Some(-12i32));
+  }
+
+  #[test]
+  fn wraps_at_any_number_of_bits() {
+    let byte = Arithmetic::WrappingBits(8);
+    assert_eq!(byte.add(127, 1), Some(-128i64));
+    assert_eq!(byte.mul(16, 16), Some(0i128));
+    assert_eq!(byte.add(-1, -1), Some(-2i32));
+    assert_eq!(Arithmetic::WrappingBits(36).mul(1i64 << 35, 2), Some(0));
+    assert_eq!(Arithmetic::WrappingBits(36).mul(1i64 << 34, 2), Some(-(1 << 35)));
+    // As wide as the word or wider is plain wrapping.
+    assert_eq!(Arithmetic::WrappingBits(200).add(i32::MAX, 1), Some(i32::MIN));
+    // Everything but sums and products is left alone.
+    assert_eq!(byte.apply(Opcode::Seti, 1000, 0), Some(1000i32));
+  }
+
+  #[test]
+  fn modes_parse_and_print() {
+    for mode in &["wrapping", "checked", "saturating", "36 bits"] {
+      let parsed = mode.trim_end_matches(" bits").parse::<Arithmetic>().unwrap();
+      assert_eq!(&parsed.to_string(), mode);
+    }
+    assert!("0".parse::<Arithmetic>().is_err());
+    assert!("unchecked".parse::<Arithmetic>().is_err());
+  }
+}
@@ -0,0 +1,360 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use super::arithmetic::Arithmetic;
+use super::opcode::{Opcode, Operand};
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+// Where control goes after an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+  Next,
+  // A jump to a known address, None if it leaves the program.
+  Jump(Option<usize>),
+  // `addr flag ip ip` right after the comparison setting the flag, which
+  // skips the next instruction if the comparison holds.
+  Skip(usize),
+  // Any other write to the bound register, the target is only known at runtime.
+  Computed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+  Address(usize),
+  Exit,
+}
+
+// Instructions from `start` up to (excluding) `end` that always run one
+// after the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+  pub start: usize,
+  pub end: usize,
+  // Block starts or the exit. Empty if the block ends with a computed jump.
+  pub successors: Vec<Target>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+  pub flows: Vec<Flow>,
+  pub blocks: Vec<Block>,
+}
+
+// The registers live before and after every instruction, the bound
+// register excluded as it always holds the address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Liveness {
+  pub live_in: Vec<BTreeSet<usize>>,
+  pub live_out: Vec<BTreeSet<usize>>,
+}
+
+fn is_comparison(opcode: Opcode) -> bool {
+  matches!(opcode, Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr | Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr)
+}
+
+// The registers an instruction reads, leaving out the bound one.
+fn uses<W: Word>(instruction: &Instruction<W>, ip_register: Option<usize>) -> Vec<usize> {
+  let (a, b) = instruction.opcode.operands();
+  vec![(a, instruction.a), (b, instruction.b)]
+    .into_iter()
+    .filter(|&(kind, _)| kind == Operand::Register)
+    .filter_map(|(_, value)| value.to_index())
+    .filter(|&r| Some(r) != ip_register)
+    .collect()
+}
+
+fn target<W: Word>(value: W, len: usize) -> Option<usize> {
+  value.checked_add(W::ONE).and_then(W::to_index).filter(|&t| t < len)
+}
+
+// The value written by an instruction if it doesn't depend on any register
+// but the bound one, and doesn't overflow.
+fn constant<W: Word>(instruction: &Instruction<W>, address: usize, ip_register: Option<usize>) -> Option<W> {
+  let ip = W::from_index(address)?;
+  let value = |kind: Operand, value: W| match kind {
+    Operand::Register if value.to_index() == ip_register => Some(ip),
+    Operand::Register => None,
+    Operand::Immediate => Some(value),
+    Operand::Ignored => Some(W::ZERO),
+  };
+
+  let (a, b) = instruction.opcode.operands();
+  Arithmetic::Checked.apply(instruction.opcode, value(a, instruction.a)?, value(b, instruction.b)?)
+}
+
+impl Flow {
+  fn jumps(self) -> bool {
+    self != Flow::Next
+  }
+
+  fn successors(self, address: usize, len: usize) -> Vec<Target> {
+    let at = |t: usize| if t < len { Target::Address(t) } else { Target::Exit };
+    match self {
+      Flow::Next => vec![at(address + 1)],
+      Flow::Jump(t) => vec![t.map_or(Target::Exit, Target::Address)],
+      Flow::Skip(_) => vec![at(address + 1), at(address + 2)],
+      Flow::Computed => vec![],
+    }
+  }
+}
+
+pub fn flows<W: Word>(program: &Program<W>) -> Vec<Flow> {
+  let ip = match program.ip_register {
+    Some(v) => v,
+    None => return vec![Flow::Next; program.instructions.len()],
+  };
+  let len = program.instructions.len();
+
+  let mut flows = program.instructions
+    .iter()
+    .enumerate()
+    .map(|(address, instruction)| match instruction.c.to_index() {
+      Some(c) if c == ip => match constant(instruction, address, Some(ip)) {
+        Some(v) => Flow::Jump(target(v, len)),
+        None => Flow::Computed,
+      },
+      _ => Flow::Next,
+    })
+    .collect::<Vec<Flow>>();
+
+  // A skip is only known if the comparison right in front of it is the
+  // only way to get there.
+  let targets = flows.iter().filter_map(|flow| match flow {
+    Flow::Jump(Some(t)) => Some(*t),
+    _ => None,
+  }).collect::<BTreeSet<usize>>();
+
+  for address in 1..len {
+    let (instruction, previous) = (&program.instructions[address], &program.instructions[address - 1]);
+    let flag = match uses(instruction, Some(ip)).as_slice() {
+      [flag] if instruction.opcode == Opcode::Addr => *flag,
+      _ => continue,
+    };
+
+    let reads_ip = instruction.a.to_index() == Some(ip) || instruction.b.to_index() == Some(ip);
+    let compared = is_comparison(previous.opcode) && previous.c.to_index() == Some(flag);
+    if flows[address] == Flow::Computed && reads_ip && compared && !flows[address - 1].jumps() && !targets.contains(&address) {
+      flows[address] = Flow::Skip(flag);
+    }
+  }
+
+  flows
+}
+
+pub fn build<W: Word>(program: &Program<W>) -> Cfg {
+  let flows = flows(program);
+  let len = flows.len();
+
+  let mut leaders = BTreeSet::new();
+  leaders.insert(0);
+  for (address, flow) in flows.iter().enumerate() {
+    if flow.jumps() {
+      leaders.insert(address + 1);
+      for successor in flow.successors(address, len) {
+        if let Target::Address(t) = successor {
+          leaders.insert(t);
+        }
+      }
+    }
+  }
+
+  let starts = leaders.into_iter().filter(|&l| l < len).collect::<Vec<usize>>();
+  let blocks = starts
+    .iter()
+    .enumerate()
+    .map(|(i, &start)| {
+      let end = starts.get(i + 1).cloned().unwrap_or(len);
+      Block { start, end, successors: flows[end - 1].successors(end - 1, len) }
+    })
+    .collect();
+
+  Cfg { flows, blocks }
+}
+
+impl Cfg {
+  pub fn computed_jumps(&self) -> Vec<usize> {
+    (0..self.flows.len()).filter(|&a| self.flows[a] == Flow::Computed).collect()
+  }
+
+  pub fn block_at(&self, address: usize) -> Option<&Block> {
+    self.blocks.iter().find(|b| b.start <= address && address < b.end)
+  }
+
+  // Backwards data flow over the instructions. The registers in `at_exit`
+  // are what the program computes, they are live when it halts. A computed
+  // jump may go anywhere, so every register is live in front of it.
+  pub fn liveness<W: Word>(&self, program: &Program<W>, register_count: usize, at_exit: &[usize]) -> Liveness {
+    let len = self.flows.len();
+    let all = (0..register_count).filter(|&r| Some(r) != program.ip_register).collect::<BTreeSet<usize>>();
+    let exit = at_exit.iter().cloned().collect::<BTreeSet<usize>>();
+    let mut live_in = vec![BTreeSet::new(); len];
+    let mut live_out = vec![BTreeSet::new(); len];
+
+    let mut changed = true;
+    while changed {
+      changed = false;
+      for address in (0..len).rev() {
+        let out = match self.flows[address] {
+          Flow::Computed => all.clone(),
+          flow => flow.successors(address, len).into_iter().fold(BTreeSet::new(), |out, t| match t {
+            Target::Address(t) => out.union(&live_in[t]).cloned().collect(),
+            Target::Exit => out.union(&exit).cloned().collect(),
+          }),
+        };
+
+        let instruction = &program.instructions[address];
+        let mut inside = out.clone();
+        if let Some(c) = instruction.c.to_index() {
+          inside.remove(&c);
+        }
+        inside.extend(uses(instruction, program.ip_register));
+
+        if inside != live_in[address] || out != live_out[address] {
+          live_in[address] = inside;
+          live_out[address] = out;
+          changed = true;
+        }
+      }
+    }
+
+    Liveness { live_in, live_out }
+  }
+
+  // Graphviz source with one box per block, listing its instructions and
+  // the registers live when the block starts.
+  pub fn dot<W: Word>(&self, program: &Program<W>, liveness: Option<&Liveness>) -> String {
+    let registers = |set: &BTreeSet<usize>| set.iter().map(|r| format!("r{}", r)).collect::<Vec<String>>().join(" ");
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph elfcode {{");
+    let _ = writeln!(out, "  node [shape=box, fontname=\"monospace\"];");
+
+    for block in &self.blocks {
+      let mut label = String::new();
+      if let Some(liveness) = liveness {
+        let _ = write!(label, "live: {}\\l", registers(&liveness.live_in[block.start]));
+      }
+      for address in block.start..block.end {
+        let _ = write!(label, "{:>3}: {}\\l", address, program.instructions[address]);
+      }
+      let _ = writeln!(out, "  b{} [label=\"{}\"];", block.start, label);
+    }
+
+    let _ = writeln!(out, "  exit [shape=oval];");
+    let computed = self.blocks.iter().any(|b| b.successors.is_empty() && self.flows[b.end - 1] == Flow::Computed);
+    if computed {
+      let _ = writeln!(out, "  computed [shape=oval, label=\"?\"];");
+    }
+
+    for block in &self.blocks {
+      let last = block.end - 1;
+      if let Flow::Skip(flag) = self.flows[last] {
+        let _ = writeln!(out, "  b{} -> {} [label=\"r{} == 0\"];", block.start, node(block.successors[0]), flag);
+        let _ = writeln!(out, "  b{} -> {} [label=\"r{} == 1\"];", block.start, node(block.successors[1]), flag);
+        continue;
+      }
+      for &successor in &block.successors {
+        let _ = writeln!(out, "  b{} -> {};", block.start, node(successor));
+      }
+      if self.flows[last] == Flow::Computed {
+        let _ = writeln!(out, "  b{} -> computed [style=dashed];", block.start);
+      }
+    }
+
+    let _ = writeln!(out, "}}");
+    out
+  }
+}
+
+fn node(target: Target) -> String {
+  match target {
+    Target::Address(t) => format!("b{}", t),
+    Target::Exit => "exit".to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  const COUNTER: &str = "#ip 5\n\
+                         seti 0 0 1\n\
+                         addi 1 1 1\n\
+                         gtri 1 2 3\n\
+                         addr 3 5 5\n\
+                         seti 0 0 5\n\
+                         setr 1 0 0\n";
+
+  #[test]
+  fn resolves_jumps() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let cfg = build(&program);
+
+    assert_eq!(cfg.flows, vec![Flow::Next, Flow::Next, Flow::Next, Flow::Skip(3), Flow::Jump(Some(1)), Flow::Next]);
+    assert_eq!(cfg.blocks, vec![
+      Block { start: 0, end: 1, successors: vec![Target::Address(1)] },
+      Block { start: 1, end: 4, successors: vec![Target::Address(4), Target::Address(5)] },
+      Block { start: 4, end: 5, successors: vec![Target::Address(1)] },
+      Block { start: 5, end: 6, successors: vec![Target::Exit] },
+    ]);
+    assert!(cfg.computed_jumps().is_empty());
+  }
+
+  #[test]
+  fn flags_computed_jumps() {
+    let program = parse::<i64>("#ip 2\naddr 2 0 2\nseti 9 0 2\naddi 2 -1 2\nmulr 2 2 2\nseti 9223372036854775807 0 2\n", 3).unwrap();
+    let cfg = build(&program);
+
+    // Jumping to a negative address or past the end halts.
+    assert_eq!(cfg.flows, vec![Flow::Computed, Flow::Jump(None), Flow::Jump(Some(2)), Flow::Jump(None), Flow::Jump(None)]);
+    assert_eq!(cfg.computed_jumps(), vec![0]);
+    assert_eq!(cfg.block_at(0).unwrap().successors, vec![]);
+  }
+
+  #[test]
+  fn computes_liveness() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let cfg = build(&program);
+    let liveness = cfg.liveness(&program, 6, &[0]);
+
+    let set = |registers: &[usize]| registers.iter().cloned().collect::<BTreeSet<usize>>();
+    assert_eq!(liveness.live_in[0], set(&[]));
+    assert_eq!(liveness.live_in[1], set(&[1]));
+    assert_eq!(liveness.live_in[3], set(&[1, 3]));
+    assert_eq!(liveness.live_out[5], set(&[0]));
+    // Register 3 only feeds the jump.
+    assert_eq!(liveness.live_out[3], set(&[1]));
+  }
+
+  #[test]
+  fn computed_jumps_keep_everything_alive() {
+    let program = parse::<i64>("#ip 3\nseti 1 0 1\naddr 3 0 3\nseti 2 0 2\n", 4).unwrap();
+    let liveness = build(&program).liveness(&program, 4, &[]);
+    assert_eq!(liveness.live_out[1], vec![0, 1, 2].into_iter().collect());
+    assert_eq!(liveness.live_in[0], vec![0, 2].into_iter().collect());
+  }
+
+  #[test]
+  fn exports_dot() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let cfg = build(&program);
+    let dot = cfg.dot(&program, Some(&cfg.liveness(&program, 6, &[0])));
+
+    assert!(dot.starts_with("digraph elfcode {\n"));
+    assert!(dot.contains("  b1 [label=\"live: r1\\l  1: addi 1 1 1\\l  2: gtri 1 2 3\\l  3: addr 3 5 5\\l\"];\n"), "{}", dot);
+    assert!(dot.contains("  b1 -> b4 [label=\"r3 == 0\"];\n  b1 -> b5 [label=\"r3 == 1\"];\n"), "{}", dot);
+    assert!(dot.contains("  b5 -> exit;\n"));
+    assert!(!dot.contains("computed"));
+  }
+
+  #[test]
+  fn analyses_day19() {
+    let program = parse::<i64>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    let cfg = build(&program);
+
+    // `addr 2 0 2` skips the jump back into the loop for part 2.
+    assert_eq!(cfg.computed_jumps(), vec![25]);
+    assert_eq!(cfg.flows[0], Flow::Jump(Some(17)));
+    assert!(cfg.dot(&program, None).contains("b17 -> computed [style=dashed];"));
+  }
+}
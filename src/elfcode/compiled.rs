@@ -0,0 +1,515 @@
+use super::super::utils::Error;
+use super::arithmetic::{self, Arithmetic};
+use super::idiom::{self, Idiom};
+use super::machine::Machine;
+use super::opcode::{Opcode, Operand};
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+// An instruction with its operands decoded: registers are indices that are
+// known to exist, immediates are values. Matching on the kind compiles to a
+// jump table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind<W: Word> {
+  Addr(usize, usize),
+  Addi(usize, W),
+  Mulr(usize, usize),
+  Muli(usize, W),
+  Banr(usize, usize),
+  Bani(usize, W),
+  Borr(usize, usize),
+  Bori(usize, W),
+  Setr(usize),
+  Seti(W),
+  Gtir(W, usize),
+  Gtri(usize, W),
+  Gtrr(usize, usize),
+  Eqri(usize, W),
+  Eqrr(usize, usize),
+  // A sum or product of two constants. The result depends on the arithmetic
+  // mode of the machine, so it is only folded into a `Seti` for checked
+  // arithmetic, and only if it fits.
+  Fold(W, W),
+  // A whole loop replaced by its native equivalent, see `Compiled::optimized`.
+  Idiom(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source<W: Word> {
+  Register(usize),
+  Value(W),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Op<W: Word> {
+  kind: Kind<W>,
+  // The opcode as written, for errors.
+  opcode: Opcode,
+  c: usize,
+  // True if the instruction writes the bound instruction pointer register.
+  jumps: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+  Halted,
+  // The instruction pointer reached the breakpoint.
+  Breakpoint,
+  // The cycle limit was reached.
+  Limit,
+}
+
+impl<W: Word> Op<W> {
+  // The operands of a sum or product.
+  fn operands(&self, registers: &[W]) -> (W, W) {
+    match self.kind {
+      Kind::Addr(a, b) | Kind::Mulr(a, b) => (registers[a], registers[b]),
+      Kind::Addi(a, b) | Kind::Muli(a, b) => (registers[a], b),
+      Kind::Fold(a, b) => (a, b),
+      _ => (W::ZERO, W::ZERO),
+    }
+  }
+
+  // The op as run by machines with checked arithmetic.
+  fn checked(self) -> Op<W> {
+    match self.kind {
+      Kind::Fold(a, b) => match Arithmetic::Checked.apply(self.opcode, a, b) {
+        Some(v) => Op { kind: Kind::Seti(v), ..self },
+        None => self,
+      },
+      _ => self,
+    }
+  }
+}
+
+fn flag<W: Word>(condition: bool) -> W {
+  if condition { W::ONE } else { W::ZERO }
+}
+
+// A program checked and decoded for a machine with a fixed number of
+// registers. Running it gives the same results as `Machine::run`, only a
+// lot faster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Compiled<W: Word> {
+  ops: Vec<Op<W>>,
+  // The replaced loops with the value they leave in the bound register.
+  idioms: Vec<(Idiom<W>, W)>,
+  // The ops without any loops replaced or constants folded, for machines
+  // with other arithmetic modes.
+  plain: Vec<Op<W>>,
+  ip_register: Option<usize>,
+  register_count: usize,
+}
+
+impl<W: Word> Compiled<W> {
+  pub fn new(program: &Program<W>, register_count: usize) -> Result<Compiled<W>, Error> {
+    if let Some(register) = program.ip_register.filter(|&r| r >= register_count) {
+      Err(Error::new(&format!("Register {} does not exist", register)))?
+    }
+
+    let plain = program.instructions
+      .iter()
+      .enumerate()
+      .map(|(address, instruction)| {
+        Compiled::decode(instruction, address, program.ip_register, register_count)
+          .map_err(|err| Error::new(&format!("ip {}: {}", address, err)))
+      })
+      .collect::<Result<Vec<Op<W>>, Error>>()?;
+
+    if W::from_index(plain.len()).is_none() {
+      Err(Error::new("The program is too long for the word size"))?
+    }
+
+    let ops = plain.iter().map(|op| op.checked()).collect();
+    Ok(Compiled { plain, ops, idioms: vec![], ip_register: program.ip_register, register_count })
+  }
+
+  // Like `new`, but loops that compute something well known (see
+  // `idiom::find`) are replaced by a single native step. The results stay
+  // the same, but a replaced loop only counts as one cycle and breakpoints
+  // inside of it are never reached. Loops only get replaced on machines with
+  // checked arithmetic, and only if the result fits into a word.
+  pub fn optimized(program: &Program<W>, register_count: usize) -> Result<Compiled<W>, Error> {
+    let mut compiled = Compiled::new(program, register_count)?;
+
+    for found in idiom::find(program) {
+      // Every idiom ends by jumping, so the instruction pointer is bound.
+      let (register, exit) = match (program.ip_register, W::from_index(found.exit - 1)) {
+        (Some(r), Some(e)) => (r, e),
+        _ => continue,
+      };
+
+      let opcode = compiled.ops[found.start].opcode;
+      compiled.ops[found.start] = Op { kind: Kind::Idiom(compiled.idioms.len()), opcode, c: register, jumps: true };
+      compiled.idioms.push((found.idiom, exit));
+    }
+
+    Ok(compiled)
+  }
+
+  fn decode(instruction: &Instruction<W>, address: usize, ip_register: Option<usize>, register_count: usize) -> Result<Op<W>, Error> {
+    let register = |value: W| match value.to_index() {
+      Some(r) if r < register_count => Ok(r),
+      _ => Err(Error::new(&format!("Register {} does not exist", value))),
+    };
+    let ip = match W::from_index(address) {
+      Some(v) => v,
+      None => Err(Error::new("The program is too long for the word size"))?
+    };
+
+    // The bound register always holds the address of the instruction, so
+    // reading it is the same as using the address as an immediate.
+    let operand = |kind: Operand, value: W| -> Result<Source<W>, Error> {
+      Ok(match kind {
+        Operand::Register if ip_register == value.to_index() => Source::Value(ip),
+        Operand::Register => Source::Register(register(value)?),
+        Operand::Immediate => Source::Value(value),
+        Operand::Ignored => Source::Value(W::ZERO),
+      })
+    };
+
+    let (kind_a, kind_b) = instruction.opcode.operands();
+    let a = operand(kind_a, instruction.a)?;
+    let b = operand(kind_b, instruction.b)?;
+
+    use self::Source::{Register as R, Value as V};
+    let kind = match (instruction.opcode, a, b) {
+      (Opcode::Setr, R(a), _) => Kind::Setr(a),
+      (Opcode::Setr, V(a), _) | (Opcode::Seti, V(a), _) => Kind::Seti(a),
+      (Opcode::Addr, V(a), V(b)) | (Opcode::Addi, V(a), V(b)) | (Opcode::Mulr, V(a), V(b)) | (Opcode::Muli, V(a), V(b)) => {
+        Kind::Fold(a, b)
+      },
+      (opcode, V(a), V(b)) => Kind::Seti(opcode.apply(a, b)),
+      (Opcode::Addr, R(a), R(b)) => Kind::Addr(a, b),
+      (Opcode::Addr, R(r), V(v)) | (Opcode::Addr, V(v), R(r)) | (Opcode::Addi, R(r), V(v)) => Kind::Addi(r, v),
+      (Opcode::Mulr, R(a), R(b)) => Kind::Mulr(a, b),
+      (Opcode::Mulr, R(r), V(v)) | (Opcode::Mulr, V(v), R(r)) | (Opcode::Muli, R(r), V(v)) => Kind::Muli(r, v),
+      (Opcode::Banr, R(a), R(b)) => Kind::Banr(a, b),
+      (Opcode::Banr, R(r), V(v)) | (Opcode::Banr, V(v), R(r)) | (Opcode::Bani, R(r), V(v)) => Kind::Bani(r, v),
+      (Opcode::Borr, R(a), R(b)) => Kind::Borr(a, b),
+      (Opcode::Borr, R(r), V(v)) | (Opcode::Borr, V(v), R(r)) | (Opcode::Bori, R(r), V(v)) => Kind::Bori(r, v),
+      (Opcode::Gtrr, R(a), R(b)) => Kind::Gtrr(a, b),
+      (Opcode::Gtrr, V(a), R(b)) | (Opcode::Gtir, V(a), R(b)) => Kind::Gtir(a, b),
+      (Opcode::Gtrr, R(a), V(b)) | (Opcode::Gtri, R(a), V(b)) => Kind::Gtri(a, b),
+      (Opcode::Eqrr, R(a), R(b)) => Kind::Eqrr(a, b),
+      (Opcode::Eqrr, V(v), R(r)) | (Opcode::Eqrr, R(r), V(v)) | (Opcode::Eqri, R(r), V(v)) | (Opcode::Eqir, V(v), R(r)) => {
+        Kind::Eqri(r, v)
+      },
+      // The operand kinds of an opcode rule out everything else.
+      (opcode, _, _) => Err(Error::new(&format!("Unable to decode {}", opcode)))?,
+    };
+
+    let c = register(instruction.c)?;
+    Ok(Op { kind, opcode: instruction.opcode, c, jumps: ip_register == Some(c) })
+  }
+
+  pub fn len(&self) -> usize {
+    self.ops.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.ops.is_empty()
+  }
+
+  // Runs the machine until the program halts, the instruction pointer
+  // reaches `breakpoint` or `max_cycles` more instructions were executed.
+  // The instruction at the current instruction pointer always runs, so
+  // running again from a breakpoint makes progress.
+  pub fn run_until(&self, machine: &mut Machine<W>, breakpoint: Option<usize>, max_cycles: Option<u64>) -> Result<Exit, Error> {
+    if machine.registers.len() != self.register_count {
+      let what = format!("The program needs {} registers, the machine has {}", self.register_count, machine.registers.len());
+      Err(Error::new(&what))?
+    }
+    if machine.ip_register != self.ip_register {
+      Err(Error::new("The machine binds the instruction pointer differently than the program"))?
+    }
+
+    let mut ip = match machine.ip.to_index() {
+      Some(v) => v,
+      None => return Ok(Exit::Halted),
+    };
+    let breakpoint = breakpoint.unwrap_or(usize::MAX);
+    let limit = max_cycles.map_or(u64::MAX, |max| machine.cycles.saturating_add(max));
+    let mut cycles = machine.cycles;
+    let arithmetic = machine.arithmetic;
+    let ops = if arithmetic == Arithmetic::Checked { &self.ops } else { &self.plain };
+    let registers = &mut machine.registers[..];
+    // A jump to an address that can't even be an index, e.g. a negative one.
+    let mut jumped_out = None;
+    // Whether the last instruction executed was a jump, None before the first.
+    let mut jumped = None;
+    // The error of a sum, product or jump that overflowed.
+    let mut overflowed = None;
+
+    let exit = loop {
+      let mut op = match ops.get(ip) {
+        Some(v) => v,
+        None => break Exit::Halted,
+      };
+      if cycles >= limit {
+        break Exit::Limit;
+      }
+      if let Kind::Idiom(index) = op.kind {
+        // The loop runs as written if the result doesn't fit.
+        if !self.idioms[index].0.apply(registers) {
+          op = &self.plain[ip];
+        }
+      }
+
+      registers[op.c] = match op.kind {
+        Kind::Addr(..) | Kind::Addi(..) | Kind::Mulr(..) | Kind::Muli(..) | Kind::Fold(..) => {
+          let (a, b) = op.operands(registers);
+          match arithmetic.apply(op.opcode, a, b) {
+            Some(v) => v,
+            None => {
+              overflowed = Some(arithmetic::overflow(op.opcode, a, b));
+              break Exit::Halted;
+            },
+          }
+        },
+        Kind::Banr(a, b) => registers[a] & registers[b],
+        Kind::Bani(a, b) => registers[a] & b,
+        Kind::Borr(a, b) => registers[a] | registers[b],
+        Kind::Bori(a, b) => registers[a] | b,
+        Kind::Setr(a) => registers[a],
+        Kind::Seti(a) => a,
+        Kind::Gtir(a, b) => flag(a > registers[b]),
+        Kind::Gtri(a, b) => flag(registers[a] > b),
+        Kind::Gtrr(a, b) => flag(registers[a] > registers[b]),
+        Kind::Eqri(a, b) => flag(registers[a] == b),
+        Kind::Eqrr(a, b) => flag(registers[a] == registers[b]),
+        Kind::Idiom(index) => self.idioms[index].1,
+      };
+
+      if op.jumps {
+        let next = match registers[op.c].checked_add(W::ONE) {
+          Some(v) => v,
+          None => {
+            overflowed = Some(arithmetic::jump_overflow(registers[op.c]));
+            break Exit::Halted;
+          },
+        };
+        cycles += 1;
+        jumped = Some(true);
+        match next.to_index() {
+          Some(v) => ip = v,
+          None => {
+            jumped_out = Some(next);
+            break Exit::Halted;
+          }
+        }
+      } else {
+        cycles += 1;
+        jumped = Some(false);
+        ip += 1;
+      }
+
+      if ip == breakpoint {
+        break Exit::Breakpoint;
+      }
+    };
+
+    // Reads of the bound register were decoded into immediates, so it only
+    // has to be up to date once the run is over. An instruction that
+    // overflowed never finished, so the register holds its own address.
+    let address = match (&overflowed, jumped) {
+      (Some(_), _) => Some(ip),
+      (None, Some(false)) => ip.checked_sub(1),
+      _ => None,
+    };
+    if let (Some(r), Some(address)) = (self.ip_register, address.and_then(W::from_index)) {
+      machine.registers[r] = address;
+    }
+    machine.cycles = cycles;
+    // Addresses up to the length of the program fit into a word, and jumps
+    // further out come from a word to begin with.
+    if let Some(v) = jumped_out.or_else(|| W::from_index(ip)) {
+      machine.ip = v;
+    }
+
+    if let Some(err) = overflowed {
+      Err(Error::new(&format!("ip {}: {}", ip, err)))?
+    }
+    Ok(exit)
+  }
+
+  pub fn run(&self, machine: &mut Machine<W>) -> Result<(), Error> {
+    self.run_until(machine, None, None)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  const COUNTER: &str = "#ip 5\n\
+                         seti 0 0 1\n\
+                         addi 1 1 1\n\
+                         gtri 1 9 3\n\
+                         addr 3 5 5\n\
+                         seti 0 0 5\n\
+                         setr 1 0 0\n";
+
+  fn both(input: &str, registers: &[i64]) -> (Machine<i64>, Machine<i64>) {
+    let program = parse::<i64>(input, 6).unwrap();
+    let mut slow = Machine::for_program(&program, 6).unwrap();
+    slow.registers.copy_from_slice(registers);
+    let mut fast = slow.clone();
+
+    slow.run(&program).unwrap();
+    Compiled::new(&program, 6).unwrap().run(&mut fast).unwrap();
+    (slow, fast)
+  }
+
+  #[test]
+  fn matches_the_machine() {
+    let (slow, fast) = both(COUNTER, &[0; 6]);
+    assert_eq!(fast, slow);
+
+    // Reads of the bound register become immediates.
+    let (slow, fast) = both("#ip 0\ngtrr 0 1 2\ngtir 5 0 3\nsetr 0 0 4\nmulr 0 0 5\neqrr 1 0 1\n", &[0, 1, 0, 0, 0, 0]);
+    assert_eq!(fast, slow);
+    assert_eq!(fast.registers, vec![4, 0, 0, 1, 2, 9]);
+
+    let (slow, fast) = both(include_str!("../day19/data/input.txt"), &[0; 6]);
+    assert_eq!(fast, slow);
+    assert_eq!(fast.registers[0], 1500);
+  }
+
+  #[test]
+  fn runs_every_opcode_like_the_machine() {
+    for opcode in super::super::opcode::ALL.iter() {
+      for &(a, b) in &[(0, 1), (1, 0), (2, 2), (3, 5)] {
+        let input = format!("{} {} {} 4\n", opcode, a, b);
+        let (slow, fast) = both(&input, &[5, 3, 5, 9, 0, 0]);
+        assert_eq!(fast, slow, "{}", input);
+      }
+    }
+  }
+
+  #[test]
+  fn stops_at_breakpoints_and_limits() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let compiled = Compiled::new(&program, 6).unwrap();
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+
+    assert_eq!(compiled.run_until(&mut machine, Some(2), None).unwrap(), Exit::Breakpoint);
+    assert_eq!((machine.ip, machine.cycles, machine.registers[1]), (2, 2, 1));
+    assert_eq!(compiled.run_until(&mut machine, Some(2), None).unwrap(), Exit::Breakpoint);
+    assert_eq!((machine.ip, machine.cycles, machine.registers[1]), (2, 6, 2));
+    assert_eq!(compiled.run_until(&mut machine, None, Some(3)).unwrap(), Exit::Limit);
+    assert_eq!(machine.cycles, 9);
+    assert_eq!(compiled.run_until(&mut machine, Some(2), None).unwrap(), Exit::Breakpoint);
+    assert_eq!(compiled.run_until(&mut machine, None, None).unwrap(), Exit::Halted);
+    assert_eq!(machine.registers[0], 10);
+  }
+
+  #[test]
+  fn optimized_programs_compute_the_same() {
+    let program = parse::<i64>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    let mut slow = Machine::for_program(&program, 6).unwrap();
+    let mut fast = slow.clone();
+
+    slow.run(&program).unwrap();
+    Compiled::optimized(&program, 6).unwrap().run(&mut fast).unwrap();
+    assert_eq!(fast.registers, slow.registers);
+    assert_eq!(fast.ip, slow.ip);
+    assert!(fast.cycles < 100);
+
+    // Other arithmetic modes run the loops as written.
+    let mut wrapping = Machine::for_program(&program, 6).unwrap();
+    wrapping.arithmetic = Arithmetic::Wrapping;
+    Compiled::optimized(&program, 6).unwrap().run(&mut wrapping).unwrap();
+    assert_eq!((wrapping.registers, wrapping.cycles), (slow.registers, slow.cycles));
+  }
+
+  #[test]
+  fn follows_the_arithmetic_mode_of_the_machine() {
+    // Multiplies r0 by 3 forever, 3^40 doesn't fit into 64 bits. The
+    // constant sum at 3 is only folded if it fits.
+    let program = parse::<i64>("#ip 3\nseti 1 0 0\nmuli 0 3 0\nseti 0 0 3\naddi 3 9223372036854775807 1\n", 4).unwrap();
+    let compiled = Compiled::new(&program, 4).unwrap();
+    let modes = [Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Saturating, Arithmetic::WrappingBits(20)];
+
+    for &arithmetic in &modes {
+      let mut slow = Machine::for_program(&program, 4).unwrap();
+      slow.arithmetic = arithmetic;
+      let mut fast = slow.clone();
+
+      let slow_result = (0..100).map(|_| slow.step(&program)).collect::<Result<Vec<bool>, Error>>();
+      let fast_result = compiled.run_until(&mut fast, None, Some(100));
+      assert_eq!(fast, slow, "{}", arithmetic);
+      match arithmetic {
+        Arithmetic::Checked => {
+          assert_eq!(fast_result.unwrap_err().to_string(), slow_result.unwrap_err().to_string());
+          assert_eq!((fast.ip, fast.registers[3], fast.cycles), (1, 1, 79));
+        },
+        _ => assert_eq!(fast_result.unwrap(), Exit::Limit),
+      }
+
+      fast.ip = 3;
+      slow.ip = 3;
+      assert_eq!(compiled.run(&mut fast).is_err(), slow.run(&program).is_err(), "{}", arithmetic);
+      assert_eq!(fast, slow, "{}", arithmetic);
+    }
+  }
+
+  #[test]
+  fn jumps_past_the_largest_word_are_errors() {
+    // The sum itself fits, only the instruction after it doesn't.
+    let program = parse::<i64>("#ip 1\nseti 0 0 0\naddr 0 1 1\n", 2).unwrap();
+    let compiled = Compiled::new(&program, 2).unwrap();
+    let modes = [Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Saturating, Arithmetic::WrappingBits(64)];
+
+    for &arithmetic in &modes {
+      let mut slow = Machine::for_program(&program, 2).unwrap();
+      slow.arithmetic = arithmetic;
+      slow.registers[0] = i64::MAX - 1;
+      slow.ip = 1;
+      let mut fast = slow.clone();
+
+      let slow_err = slow.run(&program).unwrap_err();
+      let fast_err = compiled.run(&mut fast).unwrap_err();
+      assert_eq!(fast_err.to_string(), "ip 1: The instruction pointer overflows: 9223372036854775807 + 1");
+      assert_eq!(fast_err.to_string(), slow_err.to_string());
+      assert_eq!(fast, slow, "{}", arithmetic);
+      assert_eq!((fast.ip, fast.registers[1], fast.cycles), (1, 1, 0));
+    }
+  }
+
+  #[test]
+  fn constants_follow_the_arithmetic_mode_of_the_machine() {
+    // 200 doesn't fit into 8 bits, so the sum wraps around there.
+    let program = parse::<i64>("#ip 1\naddi 1 200 0\nmuli 1 100 2\n", 3).unwrap();
+    let compiled = Compiled::new(&program, 3).unwrap();
+    let modes = [Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Saturating, Arithmetic::WrappingBits(8)];
+
+    for &arithmetic in &modes {
+      let mut slow = Machine::for_program(&program, 3).unwrap();
+      slow.arithmetic = arithmetic;
+      let mut fast = slow.clone();
+
+      slow.run(&program).unwrap();
+      compiled.run(&mut fast).unwrap();
+      assert_eq!(fast, slow, "{}", arithmetic);
+    }
+
+    let mut byte = Machine::for_program(&program, 3).unwrap();
+    byte.arithmetic = Arithmetic::WrappingBits(8);
+    compiled.run(&mut byte).unwrap();
+    assert_eq!(byte.registers, vec![-56, 1, 100]);
+  }
+
+  #[test]
+  fn invalid_programs_are_rejected() {
+    let program = Program::new(None, vec![Instruction::new(Opcode::Addr, 0, 6, 0)]);
+    let err = Compiled::<i64>::new(&program, 6).unwrap_err();
+    assert_eq!(err.to_string(), "ip 0: Register 6 does not exist");
+
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let mut machine = Machine::new(4);
+    assert!(Compiled::new(&program, 6).unwrap().run(&mut machine).is_err());
+  }
+}
@@ -0,0 +1,663 @@
+use std::fmt::Write;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::str::FromStr;
+use super::super::utils::{numbered_lines, parse_number, Error, ParseError};
+use super::machine::Machine;
+use super::program::Program;
+use super::snapshot::{History, Snapshot};
+use super::word::Word;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition<W: Word> {
+  // Stops before the instruction at the address is executed.
+  Address(usize),
+  // Stops after the step that made the register hold the value.
+  Register(usize, W),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint<W: Word> {
+  pub condition: Condition<W>,
+  pub hits: u64,
+  // Register conditions only stop when they start to hold, so continuing
+  // doesn't stop right away again.
+  held: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop<W: Word> {
+  // Index of the breakpoint that was hit.
+  Breakpoint(usize),
+  Watchpoint { register: usize, old: W, new: W },
+  // The address given to run-until was reached.
+  Reached,
+  // All requested steps were executed.
+  Stepped,
+  Halted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command<W: Word> {
+  Step(u64),
+  Continue,
+  Until(usize),
+  Break(Condition<W>),
+  Watch(usize),
+  Delete(usize),
+  Set(usize, W),
+  SetIp(W),
+  Back(u64),
+  Rewind(usize),
+  Save(PathBuf),
+  Load(PathBuf),
+  Registers,
+  Info,
+  Help,
+  Quit,
+}
+
+pub const HELP: &str = "Commands:
+  step [N]            execute N instructions (default 1)
+  continue            run until a breakpoint or watchpoint triggers
+  until <IP>          run until the instruction pointer reaches IP
+  break <IP>          stop before the instruction at IP
+  break rN == <V>     stop once register N holds V
+  watch rN            stop whenever register N changes
+  delete <N>          remove breakpoint N
+  set rN <V>          change register N (or ip) to V
+  back [N]            undo the last N instructions (default 1)
+  rewind <IP>         go back to right before IP last executed
+  save <FILE>         write the registers, ip and cycle count to FILE
+  load <FILE>         continue from a state written by save
+  registers           show the machine state
+  info                list breakpoints, watchpoints and counters
+  quit                stop debugging";
+
+fn parse_register(text: &str) -> Result<usize, ParseError> {
+  match text.strip_prefix('r') {
+    Some(number) => parse_number(number),
+    None => Err(ParseError::new(&format!("Expected a register but found '{}'", text))),
+  }
+}
+
+fn parse_address(text: Option<&str>) -> Result<usize, ParseError> {
+  match text {
+    Some(v) => parse_number(v),
+    None => Err(ParseError::new("Expected an address")),
+  }
+}
+
+impl<W: Word> FromStr for Command<W> {
+  type Err = ParseError;
+
+  fn from_str(s: &str) -> Result<Command<W>, ParseError> {
+    let words = s.split_whitespace().collect::<Vec<&str>>();
+
+    let command = match words.as_slice() {
+      ["step"] | ["s"] => Command::Step(1),
+      ["step", n] | ["s", n] => Command::Step(parse_number(n)?),
+      ["continue"] | ["c"] => Command::Continue,
+      ["until", ip] | ["u", ip] => Command::Until(parse_address(Some(ip))?),
+      ["break", register, "==", value] | ["b", register, "==", value] => {
+        Command::Break(Condition::Register(parse_register(register)?, parse_number(value)?))
+      },
+      ["break", ip] | ["b", ip] => Command::Break(Condition::Address(parse_address(Some(ip))?)),
+      ["watch", register] | ["w", register] => Command::Watch(parse_register(register)?),
+      ["delete", n] | ["d", n] => Command::Delete(parse_number(n)?),
+      ["set", "ip", value] => Command::SetIp(parse_number(value)?),
+      ["set", register, value] => Command::Set(parse_register(register)?, parse_number(value)?),
+      ["back"] => Command::Back(1),
+      ["back", n] => Command::Back(parse_number(n)?),
+      ["rewind", ip] => Command::Rewind(parse_address(Some(ip))?),
+      ["save", path] => Command::Save(PathBuf::from(path)),
+      ["load", path] => Command::Load(PathBuf::from(path)),
+      ["registers"] | ["r"] => Command::Registers,
+      ["info"] | ["i"] => Command::Info,
+      ["help"] | ["h"] => Command::Help,
+      ["quit"] | ["q"] => Command::Quit,
+      [] => Err(ParseError::new("Expected a command"))?,
+      [name, ..] => Err(ParseError::new(&format!("Unknown command '{}' or wrong arguments", name)))?,
+    };
+
+    Ok(command)
+  }
+}
+
+// The history keeps a checkpoint every `CHECKPOINT_INTERVAL` cycles and
+// reaches back `CHECKPOINTS` of them, ten million cycles.
+pub const CHECKPOINTS: usize = 10_000;
+pub const CHECKPOINT_INTERVAL: u64 = 1_000;
+
+// Steps through a program on a machine and stops at breakpoints and
+// watchpoints. Every command returns the text an interactive session
+// prints, so scripts and tests can drive it the same way.
+pub struct Debugger<'a, W: Word> {
+  pub program: &'a Program<W>,
+  pub machine: Machine<W>,
+  pub breakpoints: Vec<Breakpoint<W>>,
+  pub watchpoints: Vec<usize>,
+  // Checkpoints of the last instructions executed, to go back to.
+  pub history: History<W>,
+}
+
+impl<'a, W: Word> Debugger<'a, W> {
+  pub fn new(program: &'a Program<W>, register_count: usize) -> Result<Debugger<'a, W>, Error> {
+    Ok(Debugger {
+      program,
+      machine: Machine::for_program(program, register_count)?,
+      breakpoints: vec![],
+      watchpoints: vec![],
+      history: History::new(CHECKPOINTS, CHECKPOINT_INTERVAL),
+    })
+  }
+
+  fn check_register(&self, register: usize) -> Result<(), Error> {
+    if register >= self.machine.registers.len() {
+      Err(Error::new(&format!("Register {} does not exist", register)))?
+    }
+
+    Ok(())
+  }
+
+  pub fn add_breakpoint(&mut self, condition: Condition<W>) -> Result<usize, Error> {
+    let held = match condition {
+      Condition::Address(_) => false,
+      Condition::Register(register, value) => {
+        self.check_register(register)?;
+        self.machine.registers[register] == value
+      }
+    };
+
+    self.breakpoints.push(Breakpoint { condition, hits: 0, held });
+    Ok(self.breakpoints.len() - 1)
+  }
+
+  pub fn watch(&mut self, register: usize) -> Result<(), Error> {
+    self.check_register(register)?;
+    if !self.watchpoints.contains(&register) {
+      self.watchpoints.push(register);
+    }
+
+    Ok(())
+  }
+
+  pub fn set_register(&mut self, register: usize, value: W) -> Result<(), Error> {
+    self.check_register(register)?;
+    self.machine.registers[register] = value;
+    self.history.clear();
+    self.refresh_breakpoints();
+
+    Ok(())
+  }
+
+  // Register conditions start over from whatever the registers hold now.
+  fn refresh_breakpoints(&mut self) {
+    for breakpoint in &mut self.breakpoints {
+      if let Condition::Register(r, v) = breakpoint.condition {
+        breakpoint.held = self.machine.registers[r] == v;
+      }
+    }
+  }
+
+  // Undoes up to `steps` instructions and returns how many were undone.
+  pub fn back(&mut self, steps: u64) -> Result<u64, Error> {
+    let undone = self.history.step_back(&mut self.machine, self.program, steps)?;
+    self.refresh_breakpoints();
+    Ok(undone)
+  }
+
+  // Goes back to right before the instruction at `address` last executed.
+  pub fn rewind(&mut self, address: usize) -> Result<u64, Error> {
+    let undone = match self.history.back_to(&mut self.machine, self.program, address)? {
+      Some(v) => v,
+      None => {
+        let since = self.history.earliest().unwrap_or(self.machine.cycles);
+        let what = format!("ip {} was not executed since cycle {}", address, since);
+        Err(Error::new(&what))?
+      }
+    };
+    self.refresh_breakpoints();
+    Ok(undone)
+  }
+
+  // Continues from a saved state. The history belongs to the old state, so
+  // it is dropped.
+  pub fn load(&mut self, snapshot: &Snapshot<W>) -> Result<(), Error> {
+    self.machine.restore(snapshot)?;
+    self.history.clear();
+    self.refresh_breakpoints();
+    Ok(())
+  }
+
+  fn address_breakpoint(&self) -> Option<usize> {
+    let ip = self.machine.ip.to_index()?;
+    self.breakpoints.iter().position(|b| b.condition == Condition::Address(ip))
+  }
+
+  fn register_breakpoint(&mut self) -> Option<usize> {
+    let registers = &self.machine.registers;
+    let mut hit = None;
+
+    for (index, breakpoint) in self.breakpoints.iter_mut().enumerate() {
+      if let Condition::Register(register, value) = breakpoint.condition {
+        let holds = registers[register] == value;
+        if holds && !breakpoint.held && hit.is_none() {
+          hit = Some(index);
+        }
+        breakpoint.held = holds;
+      }
+    }
+
+    hit
+  }
+
+  // Runs until something stops the machine. `limit` caps the number of
+  // steps, `until` stops once the instruction pointer gets there. The
+  // instruction the machine currently points at is always executed, so
+  // resuming from a breakpoint makes progress.
+  pub fn resume(&mut self, limit: Option<u64>, until: Option<usize>) -> Result<Stop<W>, Error> {
+    let limit = limit.unwrap_or(u64::MAX);
+    let until = until.and_then(W::from_index);
+    let addresses = self.breakpoints
+      .iter()
+      .filter_map(|b| match b.condition {
+        Condition::Address(ip) => W::from_index(ip),
+        _ => None,
+      })
+      .collect::<Vec<W>>();
+    let registers = self.breakpoints.iter().any(|b| matches!(b.condition, Condition::Register(..)));
+    let mut watched = self.watchpoints
+      .iter()
+      .map(|&r| (r, self.machine.registers[r]))
+      .collect::<Vec<(usize, W)>>();
+    let mut steps = 0;
+
+    loop {
+      if steps >= limit {
+        return Ok(Stop::Stepped);
+      }
+
+      if steps > 0 {
+        let ip = self.machine.ip;
+        if until == Some(ip) {
+          return Ok(Stop::Reached);
+        }
+
+        if addresses.contains(&ip) {
+          if let Some(index) = self.address_breakpoint() {
+            self.breakpoints[index].hits += 1;
+            return Ok(Stop::Breakpoint(index));
+          }
+        }
+      }
+
+      if self.machine.fetch(self.program).is_none() {
+        return Ok(Stop::Halted);
+      }
+      self.history.record(&self.machine);
+      self.machine.step(self.program)?;
+      steps += 1;
+
+      if registers {
+        if let Some(index) = self.register_breakpoint() {
+          self.breakpoints[index].hits += 1;
+          return Ok(Stop::Breakpoint(index));
+        }
+      }
+
+      for (register, old) in watched.iter_mut() {
+        let new = self.machine.registers[*register];
+        if new != *old {
+          return Ok(Stop::Watchpoint { register: *register, old: *old, new });
+        }
+        *old = new;
+      }
+    }
+  }
+
+  fn describe_condition(condition: &Condition<W>) -> String {
+    match condition {
+      Condition::Address(ip) => format!("ip {}", ip),
+      Condition::Register(register, value) => format!("r{} == {}", register, value),
+    }
+  }
+
+  fn describe_stop(&self, stop: &Stop<W>) -> String {
+    match stop {
+      Stop::Breakpoint(index) => {
+        format!("Breakpoint {} ({})", index, Debugger::describe_condition(&self.breakpoints[*index].condition))
+      },
+      Stop::Watchpoint { register, old, new } => format!("Watchpoint r{}: {} -> {}", register, old, new),
+      Stop::Reached => "Reached".to_string(),
+      Stop::Stepped => "Stepped".to_string(),
+      Stop::Halted => "Halted".to_string(),
+    }
+  }
+
+  // The cycle count, the next instruction and the registers.
+  pub fn state(&self) -> String {
+    let registers = self.machine.registers
+      .iter()
+      .map(|r| r.to_string())
+      .collect::<Vec<String>>()
+      .join(", ");
+
+    match self.machine.fetch(self.program) {
+      Some(instruction) => {
+        format!("cycle {}, ip {}: {} [{}]", self.machine.cycles, self.machine.ip, instruction, registers)
+      },
+      None => format!("cycle {}, ip {}: halted [{}]", self.machine.cycles, self.machine.ip, registers),
+    }
+  }
+
+  fn info(&self) -> String {
+    let mut out = String::new();
+    for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+      let condition = Debugger::describe_condition(&breakpoint.condition);
+      let _ = writeln!(out, "Breakpoint {}: {}, hit {} times", index, condition, breakpoint.hits);
+    }
+    for register in &self.watchpoints {
+      let _ = writeln!(out, "Watchpoint: r{}", register);
+    }
+    let _ = write!(out, "{} cycles executed", self.machine.cycles);
+
+    out
+  }
+
+  // Executes a command and returns what it has to say about it.
+  pub fn execute(&mut self, command: &Command<W>) -> Result<String, Error> {
+    let stop = match *command {
+      Command::Step(n) => self.resume(Some(n), None)?,
+      Command::Continue => self.resume(None, None)?,
+      Command::Until(ip) => self.resume(None, Some(ip))?,
+      Command::Break(condition) => {
+        let index = self.add_breakpoint(condition)?;
+        return Ok(format!("Breakpoint {} ({})", index, Debugger::describe_condition(&condition)));
+      },
+      Command::Watch(register) => {
+        self.watch(register)?;
+        return Ok(format!("Watching r{}", register));
+      },
+      Command::Delete(index) => {
+        if index >= self.breakpoints.len() {
+          Err(Error::new(&format!("Breakpoint {} does not exist", index)))?
+        }
+        self.breakpoints.remove(index);
+        return Ok(format!("Deleted breakpoint {}", index));
+      },
+      Command::SetIp(value) => {
+        self.machine.ip = value;
+        self.history.clear();
+        return Ok(self.state());
+      },
+      Command::Set(register, value) => {
+        self.set_register(register, value)?;
+        return Ok(self.state());
+      },
+      Command::Back(n) => {
+        let undone = self.back(n)?;
+        return Ok(format!("Stepped back {}\n{}", undone, self.state()));
+      },
+      Command::Rewind(ip) => {
+        let undone = self.rewind(ip)?;
+        return Ok(format!("Stepped back {}\n{}", undone, self.state()));
+      },
+      Command::Save(ref path) => {
+        self.machine.snapshot().save(path)?;
+        return Ok(format!("Saved to {}", path.display()));
+      },
+      Command::Load(ref path) => {
+        self.load(&Snapshot::load(path)?)?;
+        return Ok(self.state());
+      },
+      Command::Registers => return Ok(self.state()),
+      Command::Info => return Ok(self.info()),
+      Command::Help => return Ok(HELP.to_string()),
+      Command::Quit => return Ok(String::new()),
+    };
+
+    Ok(format!("{}\n{}", self.describe_stop(&stop), self.state()))
+  }
+
+  // Runs the commands of a script, one per line. Lines starting with `;`
+  // are comments. The output echoes every command, so it reads like a
+  // transcript of an interactive session.
+  pub fn run_script(&mut self, script: &str) -> Result<String, Error> {
+    let mut out = String::new();
+
+    for (number, line) in numbered_lines(script) {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with(';') {
+        continue;
+      }
+
+      let command = Command::from_str(line).map_err(|err| err.at_line(number, line))?;
+      let result = self.execute(&command).map_err(|err| Error::new(&format!("line {}: {}", number, err)))?;
+      let _ = writeln!(out, "> {}", line);
+      if !result.is_empty() {
+        let _ = writeln!(out, "{}", result);
+      }
+
+      if command == Command::Quit {
+        break;
+      }
+    }
+
+    Ok(out)
+  }
+
+  // Reads commands from `input` until it ends or the session is quit.
+  // Mistakes are reported and the session goes on.
+  pub fn interact<R: BufRead, O: io::Write>(&mut self, input: R, mut output: O) -> io::Result<()> {
+    write!(output, "{}\n(edb) ", self.state())?;
+    output.flush()?;
+
+    for line in input.lines() {
+      let line = line?;
+      if !line.trim().is_empty() {
+        let result = Command::from_str(&line)
+          .map_err(Error::from)
+          .and_then(|command| match command {
+            Command::Quit => Ok(None),
+            _ => self.execute(&command).map(Some),
+          });
+
+        match result {
+          Ok(None) => return Ok(()),
+          Ok(Some(text)) => writeln!(output, "{}", text)?,
+          Err(err) => writeln!(output, "Error: {}", err)?,
+        }
+      }
+
+      write!(output, "(edb) ")?;
+      output.flush()?;
+    }
+
+    writeln!(output)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  const COUNTER: &str = "#ip 5\n\
+                         seti 0 0 1\n\
+                         addi 1 1 1\n\
+                         gtri 1 9 3\n\
+                         addr 3 5 5\n\
+                         seti 0 0 5\n\
+                         setr 1 0 0\n";
+
+  fn debugger(program: &Program<i64>) -> Debugger<'_, i64> {
+    Debugger::new(program, 6).unwrap()
+  }
+
+  #[test]
+  fn parses_commands() {
+    assert_eq!("step".parse::<Command<i64>>().unwrap(), Command::Step(1));
+    assert_eq!("s 10".parse::<Command<i64>>().unwrap(), Command::Step(10));
+    assert_eq!("break r2 == 28".parse::<Command<i64>>().unwrap(), Command::Break(Condition::Register(2, 28)));
+    assert_eq!("b 7".parse::<Command<i64>>().unwrap(), Command::Break(Condition::Address(7)));
+    assert_eq!("set r0 1".parse::<Command<i64>>().unwrap(), Command::Set(0, 1));
+    assert_eq!("back".parse::<Command<i64>>().unwrap(), Command::Back(1));
+    assert_eq!("rewind 28".parse::<Command<i64>>().unwrap(), Command::Rewind(28));
+    assert_eq!("load state.txt".parse::<Command<i64>>().unwrap(), Command::Load(PathBuf::from("state.txt")));
+    assert!("watch 2".parse::<Command<i64>>().is_err());
+    assert!("jump".parse::<Command<i64>>().is_err());
+  }
+
+  #[test]
+  fn stops_at_instruction_breakpoints() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+    debugger.add_breakpoint(Condition::Address(2)).unwrap();
+
+    assert_eq!(debugger.resume(None, None).unwrap(), Stop::Breakpoint(0));
+    assert_eq!(debugger.machine.registers[1], 1);
+    assert_eq!(debugger.resume(None, None).unwrap(), Stop::Breakpoint(0));
+    assert_eq!(debugger.machine.registers[1], 2);
+    assert_eq!(debugger.breakpoints[0].hits, 2);
+  }
+
+  #[test]
+  fn register_breakpoints_stop_when_the_value_is_reached() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+    debugger.add_breakpoint(Condition::Register(1, 5)).unwrap();
+
+    assert_eq!(debugger.resume(None, None).unwrap(), Stop::Breakpoint(0));
+    assert_eq!(debugger.machine.ip, 2);
+    assert_eq!(debugger.resume(None, None).unwrap(), Stop::Halted);
+    assert_eq!(debugger.machine.registers[0], 10);
+  }
+
+  #[test]
+  fn watchpoints_report_changes() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+    debugger.watch(3).unwrap();
+
+    let stop = debugger.resume(None, None).unwrap();
+    assert_eq!(stop, Stop::Watchpoint { register: 3, old: 0, new: 1 });
+    assert_eq!(debugger.machine.registers[1], 10);
+  }
+
+  #[test]
+  fn steps_and_runs_until_an_address() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+
+    assert_eq!(debugger.resume(Some(3), None).unwrap(), Stop::Stepped);
+    assert_eq!(debugger.machine.cycles, 3);
+    assert_eq!(debugger.resume(None, Some(5)).unwrap(), Stop::Reached);
+    assert_eq!(debugger.machine.registers[1], 10);
+    assert_eq!(debugger.resume(None, Some(5)).unwrap(), Stop::Halted);
+  }
+
+  #[test]
+  fn runs_scripts() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+    let script = "; skip ahead\n\
+                  break 5\n\
+                  set r1 7\n\
+                  step 2\n\
+                  continue\n\
+                  info\n\
+                  quit\n\
+                  continue\n";
+
+    assert_eq!(debugger.run_script(script).unwrap(), "> break 5\n\
+      Breakpoint 0 (ip 5)\n\
+      > set r1 7\n\
+      cycle 0, ip 0: seti 0 0 1 [0, 7, 0, 0, 0, 0]\n\
+      > step 2\n\
+      Stepped\n\
+      cycle 2, ip 2: gtri 1 9 3 [0, 1, 0, 0, 0, 1]\n\
+      > continue\n\
+      Breakpoint 0 (ip 5)\n\
+      cycle 40, ip 5: setr 1 0 0 [0, 10, 0, 1, 0, 4]\n\
+      > info\n\
+      Breakpoint 0: ip 5, hit 1 times\n\
+      40 cycles executed\n\
+      > quit\n");
+  }
+
+  #[test]
+  fn steps_back_in_time() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+    let script = "break 3\n\
+                  continue\n\
+                  continue\n\
+                  back\n\
+                  rewind 3\n\
+                  rewind 5\n";
+
+    let err = debugger.run_script(script).unwrap_err();
+    assert_eq!(err.to_string(), "line 6: ip 5 was not executed since cycle 0");
+    assert_eq!(debugger.state(), "cycle 3, ip 3: addr 3 5 5 [0, 1, 0, 0, 0, 2]");
+
+    debugger.breakpoints.clear();
+    debugger.history = History::new(2, 1);
+    assert_eq!(debugger.resume(Some(5), None).unwrap(), Stop::Stepped);
+    assert_eq!(debugger.back(3).unwrap(), 2);
+    assert_eq!(debugger.machine.cycles, 6);
+  }
+
+  #[test]
+  fn saves_and_loads_the_state() {
+    let program = parse(COUNTER, 6).unwrap();
+    let path = std::env::temp_dir().join(format!("y2018-debugger-{}.txt", std::process::id()));
+    let mut first = debugger(&program);
+    first.execute(&Command::Step(10)).unwrap();
+    let saved = first.execute(&Command::Save(path.clone())).unwrap();
+
+    let mut second = debugger(&program);
+    let loaded = second.execute(&Command::Load(path.clone()));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(saved, format!("Saved to {}", path.display()));
+    assert_eq!(loaded.unwrap(), first.state());
+    assert_eq!(second.machine, first.machine);
+    assert!(second.history.is_empty());
+  }
+
+  #[test]
+  fn interactive_sessions_survive_mistakes() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+    let mut output = vec![];
+    debugger.interact("fly\nstep\nquit\nstep\n".as_bytes(), &mut output).unwrap();
+
+    assert_eq!(String::from_utf8(output).unwrap(), "cycle 0, ip 0: seti 0 0 1 [0, 0, 0, 0, 0, 0]\n\
+      (edb) Error: Unknown command 'fly' or wrong arguments\n\
+      (edb) Stepped\n\
+      cycle 1, ip 1: addi 1 1 1 [0, 0, 0, 0, 0, 0]\n\
+      (edb) ");
+  }
+
+  #[test]
+  fn script_errors_name_the_line() {
+    let program = parse(COUNTER, 6).unwrap();
+    let mut debugger = debugger(&program);
+
+    let err = debugger.run_script("step\nwatch r9\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: Register 9 does not exist");
+    let err = debugger.run_script("step\nfly\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: Unknown command 'fly' or wrong arguments in \"fly\"");
+  }
+
+  #[test]
+  fn finds_the_day21_halting_check() {
+    let program = parse::<i64>(include_str!("../day21/data/input.txt"), 6).unwrap();
+    let mut debugger = debugger(&program);
+    debugger.add_breakpoint(Condition::Address(28)).unwrap();
+
+    assert_eq!(debugger.resume(None, None).unwrap(), Stop::Breakpoint(0));
+    assert_eq!(debugger.machine.registers[4], 12213578);
+  }
+}
@@ -0,0 +1,620 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+use super::arithmetic::Arithmetic;
+use super::opcode::{Opcode, Operand};
+use super::program::Program;
+use super::word::Word;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+  Add,
+  Mul,
+  And,
+  Or,
+  Gt,
+  Le,
+  Eq,
+  Ne,
+}
+
+impl BinOp {
+  fn from_opcode(opcode: Opcode) -> Option<BinOp> {
+    match opcode {
+      Opcode::Addr | Opcode::Addi => Some(BinOp::Add),
+      Opcode::Mulr | Opcode::Muli => Some(BinOp::Mul),
+      Opcode::Banr | Opcode::Bani => Some(BinOp::And),
+      Opcode::Borr | Opcode::Bori => Some(BinOp::Or),
+      Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr => Some(BinOp::Gt),
+      Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr => Some(BinOp::Eq),
+      Opcode::Setr | Opcode::Seti => None,
+    }
+  }
+
+  pub fn symbol(self) -> &'static str {
+    match self {
+      BinOp::Add => "+",
+      BinOp::Mul => "*",
+      BinOp::And => "&",
+      BinOp::Or => "|",
+      BinOp::Gt => ">",
+      BinOp::Le => "<=",
+      BinOp::Eq => "==",
+      BinOp::Ne => "!=",
+    }
+  }
+
+  pub fn is_comparison(self) -> bool {
+    matches!(self, BinOp::Gt | BinOp::Le | BinOp::Eq | BinOp::Ne)
+  }
+
+  fn is_commutative(self) -> bool {
+    !matches!(self, BinOp::Gt | BinOp::Le)
+  }
+
+  fn negated(self) -> Option<BinOp> {
+    match self {
+      BinOp::Gt => Some(BinOp::Le),
+      BinOp::Le => Some(BinOp::Gt),
+      BinOp::Eq => Some(BinOp::Ne),
+      BinOp::Ne => Some(BinOp::Eq),
+      _ => None,
+    }
+  }
+
+  // Same order as in Rust, so the pseudo-code reads the way it looks.
+  fn precedence(self) -> u8 {
+    match self {
+      BinOp::Mul => 5,
+      BinOp::Add => 4,
+      BinOp::And => 3,
+      BinOp::Or => 2,
+      _ => 1,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr<W: Word> {
+  Register(usize),
+  Value(W),
+  Binary(BinOp, Box<Expr<W>>, Box<Expr<W>>),
+}
+
+impl<W: Word> Expr<W> {
+  fn binary(op: BinOp, a: Expr<W>, b: Expr<W>) -> Expr<W> {
+    Expr::Binary(op, Box::new(a), Box::new(b))
+  }
+
+  // Registers are true if they are not zero, like the flags the comparisons set.
+  pub fn negate(self) -> Expr<W> {
+    match self {
+      Expr::Binary(op, a, b) if op.negated().is_some() => Expr::Binary(op.negated().unwrap_or(op), a, b),
+      other => Expr::binary(BinOp::Eq, other, Expr::Value(W::ZERO)),
+    }
+  }
+
+  pub fn reads(&self, register: usize) -> bool {
+    match self {
+      Expr::Register(r) => *r == register,
+      Expr::Value(_) => false,
+      Expr::Binary(_, a, b) => a.reads(register) || b.reads(register),
+    }
+  }
+
+  fn fmt_inner(&self, f: &mut fmt::Formatter, parent: Option<(BinOp, bool)>) -> fmt::Result {
+    match self {
+      Expr::Register(r) => write!(f, "r{}", r),
+      Expr::Value(v) => write!(f, "{}", v),
+      Expr::Binary(op, a, b) => {
+        let parens = match parent {
+          Some((outer, right)) => {
+            op.precedence() < outer.precedence()
+              || (op.precedence() == outer.precedence() && (right || op.is_comparison()))
+          },
+          None => false,
+        };
+
+        if parens {
+          write!(f, "(")?;
+        }
+        a.fmt_inner(f, Some((*op, false)))?;
+        write!(f, " {} ", op.symbol())?;
+        b.fmt_inner(f, Some((*op, true)))?;
+        if parens {
+          write!(f, ")")?;
+        }
+
+        Ok(())
+      }
+    }
+  }
+}
+
+impl<W: Word> fmt::Display for Expr<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.fmt_inner(f, None)
+  }
+}
+
+// One statement per instruction, with all writes to the instruction pointer
+// turned into jumps. Jump targets at or past the end of the program halt it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement<W: Word> {
+  Assign(usize, Expr<W>),
+  Goto(usize),
+  // Jumps to the target if the condition holds and falls through otherwise.
+  Branch(Expr<W>, usize),
+  // Jumps to the address the expression evaluates to.
+  ComputedGoto(Expr<W>),
+  Nop,
+}
+
+fn operand<W: Word>(kind: Operand, value: W, address: usize, ip_register: Option<usize>) -> Expr<W> {
+  match kind {
+    Operand::Register => {
+      let register = value.to_index().unwrap_or(usize::MAX);
+      // The bound register holds the address of the instruction while it executes.
+      match W::from_index(address) {
+        Some(ip) if Some(register) == ip_register => Expr::Value(ip),
+        _ => Expr::Register(register),
+      }
+    },
+    Operand::Immediate => Expr::Value(value),
+    Operand::Ignored => Expr::Value(W::ZERO),
+  }
+}
+
+fn expression<W: Word>(opcode: Opcode, a: Expr<W>, b: Expr<W>) -> Expr<W> {
+  match (BinOp::from_opcode(opcode), a, b) {
+    (None, a, _) => a,
+    // Constants are only folded if they don't overflow.
+    (Some(op), Expr::Value(x), Expr::Value(y)) => match Arithmetic::Checked.apply(opcode, x, y) {
+      Some(v) => Expr::Value(v),
+      None => Expr::binary(op, Expr::Value(x), Expr::Value(y)),
+    },
+    (Some(op), a, b) => Expr::binary(op, a, b),
+  }
+}
+
+fn target<W: Word>(value: W, len: usize) -> usize {
+  match value.checked_add(W::ONE).and_then(W::to_index) {
+    Some(t) if t < len => t,
+    _ => len,
+  }
+}
+
+// The condition if the instruction at `address` adds a comparison result to
+// the instruction pointer, which skips the next instruction if it holds.
+fn branch_condition<W: Word>(program: &Program<W>, address: usize, flag: usize) -> Option<Expr<W>> {
+  let previous = program.instructions.get(address.checked_sub(1)?)?;
+  let op = BinOp::from_opcode(previous.opcode).filter(|op| op.is_comparison())?;
+  if previous.c.to_index() != Some(flag) {
+    return None;
+  }
+
+  let (kind_a, kind_b) = previous.opcode.operands();
+  let a = operand(kind_a, previous.a, address - 1, program.ip_register);
+  let b = operand(kind_b, previous.b, address - 1, program.ip_register);
+
+  // The comparison overwrote one of its inputs, so only the flag is left.
+  if a.reads(flag) || b.reads(flag) {
+    Some(Expr::Register(flag))
+  } else {
+    Some(Expr::binary(op, a, b))
+  }
+}
+
+fn lower_instruction<W: Word>(program: &Program<W>, address: usize, targets: &BTreeSet<usize>) -> Statement<W> {
+  let instruction = &program.instructions[address];
+  let (kind_a, kind_b) = instruction.opcode.operands();
+  let a = operand(kind_a, instruction.a, address, program.ip_register);
+  let b = operand(kind_b, instruction.b, address, program.ip_register);
+  let register = instruction.c.to_index().unwrap_or(usize::MAX);
+  let len = program.instructions.len();
+
+  if Some(register) != program.ip_register {
+    return Statement::Assign(register, expression(instruction.opcode, a, b));
+  }
+
+  let ip = match W::from_index(address) {
+    Some(v) => v,
+    None => return Statement::ComputedGoto(expression(instruction.opcode, a, b)),
+  };
+
+  match expression(instruction.opcode, a, b) {
+    Expr::Value(v) => Statement::Goto(target(v, len)),
+    Expr::Binary(BinOp::Add, x, y) => match (*x, *y) {
+      (Expr::Value(v), Expr::Register(flag)) | (Expr::Register(flag), Expr::Value(v)) if v == ip && !targets.contains(&address) => {
+        match branch_condition(program, address, flag) {
+          Some(condition) => Statement::Branch(condition, address + 2),
+          None => Statement::ComputedGoto(Expr::binary(BinOp::Add, Expr::Value(v + W::ONE), Expr::Register(flag))),
+        }
+      },
+      (Expr::Value(v), other) | (other, Expr::Value(v)) => match v.checked_add(W::ONE) {
+        Some(next) => Statement::ComputedGoto(Expr::binary(BinOp::Add, Expr::Value(next), other)),
+        None => Statement::ComputedGoto(Expr::binary(BinOp::Add, Expr::binary(BinOp::Add, Expr::Value(v), other), Expr::Value(W::ONE))),
+      },
+      (x, y) => Statement::ComputedGoto(Expr::binary(BinOp::Add, Expr::binary(BinOp::Add, x, y), Expr::Value(W::ONE))),
+    },
+    other => Statement::ComputedGoto(Expr::binary(BinOp::Add, other, Expr::Value(W::ONE))),
+  }
+}
+
+// The constant jump target of a statement. Computed jumps count with the
+// address they are relative to.
+fn jump_target<W: Word>(statement: &Statement<W>) -> Option<usize> {
+  match statement {
+    Statement::Goto(t) | Statement::Branch(_, t) => Some(*t),
+    Statement::ComputedGoto(Expr::Binary(BinOp::Add, base, _)) => match **base {
+      Expr::Value(v) => v.to_index(),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn jumps<W: Word>(statements: &[Statement<W>]) -> Vec<(usize, usize)> {
+  statements
+    .iter()
+    .enumerate()
+    .filter_map(|(source, statement)| jump_target(statement).map(|t| (source, t)))
+    .filter(|&(_, target)| target < statements.len())
+    .collect()
+}
+
+// Turns every instruction into a statement. A branch that only skips a jump
+// is folded into one branch to the target of that jump.
+pub fn lower<W: Word>(program: &Program<W>) -> Vec<Statement<W>> {
+  let len = program.instructions.len();
+  let lower_all = |targets: &BTreeSet<usize>| (0..len)
+    .map(|address| lower_instruction(program, address, targets))
+    .collect::<Vec<Statement<W>>>();
+
+  // A branch condition is only known if the comparison right in front of
+  // it is the only way to get there.
+  let targets = jumps(&lower_all(&BTreeSet::new())).into_iter().map(|(_, t)| t).collect();
+  let mut statements = lower_all(&targets);
+  let targets: BTreeSet<usize> = jumps(&statements).into_iter().map(|(_, t)| t).collect();
+
+  for address in 0..len.saturating_sub(1) {
+    let next = address + 1;
+    if targets.contains(&next) {
+      continue;
+    }
+
+    if let (Statement::Branch(condition, skip), Statement::Goto(target)) = (&statements[address], &statements[next]) {
+      if *skip == address + 2 {
+        statements[address] = Statement::Branch(condition.clone().negate(), *target);
+        statements[next] = Statement::Nop;
+      }
+    }
+  }
+
+  statements
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<W: Word> {
+  // Marks the address of the statements that follow.
+  Label(usize),
+  Statement(Statement<W>),
+  If(Expr<W>, Vec<Node<W>>, Vec<Node<W>>),
+  Loop(Vec<Node<W>>),
+  DoWhile(Vec<Node<W>>, Expr<W>),
+  Break,
+  Continue,
+}
+
+#[derive(Clone, Copy)]
+struct LoopContext {
+  head: usize,
+  exit: usize,
+}
+
+struct Structurer<'a, W: Word> {
+  statements: &'a [Statement<W>],
+  jumps: Vec<(usize, usize)>,
+}
+
+impl<'a, W: Word> Structurer<'a, W> {
+  // True if a jump from outside of [from, to] lands in (from, to].
+  fn entered(&self, from: usize, to: usize) -> bool {
+    self.jumps.iter().any(|&(s, t)| (s < from || s > to) && t > from && t <= to)
+  }
+
+  fn back_edge(&self, head: usize, end: usize) -> Option<usize> {
+    self.jumps
+      .iter()
+      .filter(|&&(s, t)| t == head && s >= head && s < end)
+      .map(|&(s, _)| s)
+      .filter(|&s| !self.entered(head, s))
+      .max()
+  }
+
+  fn looped(&self, head: usize, tail: usize) -> Node<W> {
+    let inner = Some(LoopContext { head, exit: tail + 1 });
+    let mut body = self.block(head, tail, inner, true);
+    let other_back_edges = self.jumps.iter().any(|&(s, t)| t == head && s >= head && s < tail);
+
+    match &self.statements[tail] {
+      Statement::Branch(condition, _) if !other_back_edges => Node::DoWhile(body, condition.clone()),
+      Statement::Branch(condition, _) => {
+        body.push(Node::If(condition.clone(), vec![Node::Continue], vec![]));
+        body.push(Node::Break);
+        Node::Loop(body)
+      },
+      Statement::Goto(_) => Node::Loop(body),
+      // Back edges only come from jumps.
+      other => {
+        body.push(Node::Statement(other.clone()));
+        Node::Loop(body)
+      }
+    }
+  }
+
+  fn jump(&self, target: usize, context: Option<LoopContext>) -> Option<Node<W>> {
+    match context {
+      Some(c) if target == c.exit => Some(Node::Break),
+      Some(c) if target == c.head => Some(Node::Continue),
+      _ => None,
+    }
+  }
+
+  fn block(&self, start: usize, end: usize, context: Option<LoopContext>, in_loop_head: bool) -> Vec<Node<W>> {
+    let len = self.statements.len();
+    let mut nodes = vec![];
+    let mut address = start;
+    let mut skip_loop = in_loop_head;
+
+    while address < end {
+      nodes.push(Node::Label(address));
+
+      if !skip_loop {
+        if let Some(tail) = self.back_edge(address, end) {
+          nodes.push(self.looped(address, tail));
+          address = tail + 1;
+          continue;
+        }
+      }
+      skip_loop = false;
+
+      match &self.statements[address] {
+        Statement::Branch(condition, target) => {
+          let target = *target;
+          if let Some(jump) = self.jump(target, context) {
+            nodes.push(Node::If(condition.clone(), vec![jump], vec![]));
+          } else if target > address + 1 && target <= end && target < len && !self.entered(address, target - 1) {
+            let last = target - 1;
+            let otherwise = match &self.statements[last] {
+              Statement::Goto(after) if *after > target && *after <= end && *after < len && !self.entered(target, after - 1) => Some(*after),
+              _ => None,
+            };
+
+            let condition = condition.clone().negate();
+            match otherwise {
+              Some(after) => {
+                let then = self.block(address + 1, last, context, false);
+                let other = self.block(target, after, context, false);
+                nodes.push(Node::If(condition, then, other));
+                address = after;
+              },
+              None => {
+                let then = self.block(address + 1, target, context, false);
+                nodes.push(Node::If(condition, then, vec![]));
+                address = target;
+              }
+            }
+            continue;
+          } else {
+            nodes.push(Node::Statement(self.statements[address].clone()));
+          }
+        },
+        Statement::Goto(target) => match self.jump(*target, context) {
+          Some(jump) => nodes.push(jump),
+          None => nodes.push(Node::Statement(self.statements[address].clone())),
+        },
+        Statement::Nop => {},
+        other => nodes.push(Node::Statement(other.clone())),
+      }
+
+      address += 1;
+    }
+
+    nodes
+  }
+}
+
+// Rebuilds loops and conditionals from the jumps of the program. Jumps that
+// don't fit into a structure are kept as gotos.
+pub fn structure<W: Word>(program: &Program<W>) -> Vec<Node<W>> {
+  let statements = lower(program);
+  let structurer = Structurer { jumps: jumps(&statements), statements: &statements };
+
+  structurer.block(0, statements.len(), None, false)
+}
+
+fn compound<W: Word>(register: usize, expr: &Expr<W>) -> Option<(BinOp, &Expr<W>)> {
+  match expr {
+    Expr::Binary(op, a, b) if !op.is_comparison() => match (&**a, &**b) {
+      (Expr::Register(r), other) if *r == register => Some((*op, other)),
+      (other, Expr::Register(r)) if *r == register && op.is_commutative() => Some((*op, other)),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+struct Printer<'a> {
+  out: String,
+  len: usize,
+  labels: &'a BTreeSet<usize>,
+}
+
+impl<'a> Printer<'a> {
+  fn line(&mut self, depth: usize, text: &str) {
+    let _ = writeln!(self.out, "{}{}", "  ".repeat(depth), text);
+  }
+
+  fn goto(&self, target: usize) -> String {
+    if target >= self.len {
+      "halt".to_string()
+    } else {
+      format!("goto L{}", target)
+    }
+  }
+
+  fn statement<W: Word>(&self, statement: &Statement<W>) -> Option<String> {
+    Some(match statement {
+      Statement::Assign(register, expr) => match compound(*register, expr) {
+        Some((op, other)) => format!("r{} {}= {}", register, op.symbol(), other),
+        None => format!("r{} = {}", register, expr),
+      },
+      Statement::Goto(target) => self.goto(*target),
+      Statement::Branch(condition, target) => format!("if {} {{ {} }}", condition, self.goto(*target)),
+      Statement::ComputedGoto(Expr::Binary(BinOp::Add, base, offset)) if matches!(**base, Expr::Value(_)) => {
+        format!("goto L{} + {}", base, offset)
+      },
+      Statement::ComputedGoto(expr) => format!("goto *({})", expr),
+      Statement::Nop => return None,
+    })
+  }
+
+  fn nodes<W: Word>(&mut self, nodes: &[Node<W>], depth: usize) {
+    for node in nodes {
+      match node {
+        Node::Label(address) if self.labels.contains(address) => self.line(depth, &format!("L{}:", address)),
+        Node::Label(_) => {},
+        Node::Statement(statement) => {
+          if let Some(text) = self.statement(statement) {
+            self.line(depth + 1, &text);
+          }
+        },
+        Node::If(condition, then, otherwise) => {
+          self.line(depth + 1, &format!("if {} {{", condition));
+          self.nodes(then, depth + 1);
+          if !otherwise.is_empty() {
+            self.line(depth + 1, "} else {");
+            self.nodes(otherwise, depth + 1);
+          }
+          self.line(depth + 1, "}");
+        },
+        Node::Loop(body) => {
+          self.line(depth + 1, "loop {");
+          self.nodes(body, depth + 1);
+          self.line(depth + 1, "}");
+        },
+        Node::DoWhile(body, condition) => {
+          self.line(depth + 1, "do {");
+          self.nodes(body, depth + 1);
+          self.line(depth + 1, &format!("}} while {}", condition));
+        },
+        Node::Break => self.line(depth + 1, "break"),
+        Node::Continue => self.line(depth + 1, "continue"),
+      }
+    }
+  }
+}
+
+// Labels that are still needed by gotos after structuring.
+fn used_labels<W: Word>(nodes: &[Node<W>], labels: &mut BTreeSet<usize>) {
+  for node in nodes {
+    match node {
+      Node::Statement(statement) => labels.extend(jump_target(statement)),
+      Node::If(_, then, otherwise) => {
+        used_labels(then, labels);
+        used_labels(otherwise, labels);
+      },
+      Node::Loop(body) | Node::DoWhile(body, _) => used_labels(body, labels),
+      _ => {},
+    }
+  }
+}
+
+// Turns the program into structured pseudo-code. Registers are called r0,
+// r1, ... like in the assembler, the bound instruction pointer disappears
+// into jumps.
+pub fn decompile<W: Word>(program: &Program<W>) -> String {
+  let nodes = structure(program);
+  let mut labels = BTreeSet::new();
+  used_labels(&nodes, &mut labels);
+
+  let mut printer = Printer { out: String::new(), len: program.instructions.len(), labels: &labels };
+  if let Some(register) = program.ip_register {
+    printer.line(0, &format!("// ip is bound to r{}", register));
+  }
+  printer.nodes(&nodes, 0);
+
+  printer.out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  fn decompiled(input: &str) -> String {
+    decompile(&parse::<i64>(input, 6).unwrap())
+  }
+
+  #[test]
+  fn expressions_keep_their_precedence() {
+    let sum: Expr<i64> = Expr::binary(BinOp::Add, Expr::Register(1), Expr::Value(2));
+    let product = Expr::binary(BinOp::Mul, sum.clone(), Expr::Register(3));
+    assert_eq!(product.to_string(), "(r1 + 2) * r3");
+    assert_eq!(Expr::binary(BinOp::Gt, sum, Expr::Register(3)).negate().to_string(), "r1 + 2 <= r3");
+  }
+
+  #[test]
+  fn writes_to_the_instruction_pointer_are_jumps() {
+    let code = decompiled("#ip 5\nseti 2 0 5\naddi 1 1 1\nsetr 1 0 0\nmulr 5 5 5\n");
+    assert_eq!(code, "// ip is bound to r5\n  goto L3\n  r1 += 1\n  r0 = r1\nL3:\n  halt\n");
+  }
+
+  #[test]
+  fn jumps_past_the_largest_word_halt() {
+    let code = decompiled("#ip 5\nseti 9223372036854775807 0 5\naddi 1 9223372036854775807 5\n");
+    assert_eq!(code, "// ip is bound to r5\n  halt\n  goto *(9223372036854775807 + r1 + 1)\n");
+  }
+
+  #[test]
+  fn rebuilds_conditionals() {
+    let code = decompiled("#ip 5\neqrr 1 2 3\naddr 3 5 5\naddi 5 1 5\naddi 0 1 0\nseti 7 0 4\n");
+    assert_eq!(code, "// ip is bound to r5\n  r3 = r1 == r2\n  if r1 == r2 {\n    r0 += 1\n  }\n  r4 = 7\n");
+  }
+
+  #[test]
+  fn rebuilds_if_else() {
+    let input = "#ip 5\n\
+                 gtri 1 9 3\n\
+                 addr 3 5 5\n\
+                 seti 4 0 5\n\
+                 seti 1 0 0\n\
+                 seti 5 0 5\n\
+                 seti 2 0 0\n\
+                 seti 3 0 2\n";
+    let code = decompiled(input);
+    assert_eq!(code, "// ip is bound to r5\n  r3 = r1 > 9\n  if r1 > 9 {\n    r0 = 1\n  } else {\n    r0 = 2\n  }\n  r2 = 3\n");
+  }
+
+  #[test]
+  fn rebuilds_loops() {
+    let input = "#ip 5\n\
+                 seti 0 0 1\n\
+                 addi 1 1 1\n\
+                 gtri 1 9 3\n\
+                 addr 3 5 5\n\
+                 seti 0 0 5\n\
+                 setr 1 0 0\n";
+    let code = decompiled(input);
+    assert_eq!(code, "// ip is bound to r5\n  r1 = 0\n  do {\n    r1 += 1\n    r3 = r1 > 9\n  } while r1 <= 9\n  r0 = r1\n");
+  }
+
+  #[test]
+  fn decompiles_day19() {
+    let code = decompiled(include_str!("../day19/data/input.txt"));
+    let expected = "  do {\n    r2 = 1\n    do {\n      r3 = r4 * r2\n      r3 = r3 == r1\n      if r3 != 0 {\n        r0 += r4\n      }\n      r2 += 1\n      r3 = r2 > r1\n    } while r2 <= r1\n";
+    assert!(code.contains(expected), "{}", code);
+    assert!(code.contains("goto L26 + r0"), "{}", code);
+    assert!(code.contains("\nL26:\n"), "{}", code);
+  }
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeSet;
+use super::super::utils::Error;
+use super::compiled::{Compiled, Exit};
+use super::machine::Machine;
+use super::opcode::{Opcode, Operand};
+use super::program::Program;
+use super::word::Word;
+
+// Programs like the one of day 21 only read register 0 to compare it with
+// a value they generate, and halt once both are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HaltingCheck {
+  // The address of the `eqrr` comparing `register` with register 0.
+  pub address: usize,
+  pub register: usize,
+}
+
+// The sequence x0, f(x0), f(f(x0)), ... repeats from `start` on, every
+// `length` elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+  pub start: usize,
+  pub length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HaltingValues<W: Word> {
+  // The value halting the program the soonest.
+  pub first: W,
+  // The value halting the program the latest, after all others were compared.
+  pub last: W,
+  pub distinct: usize,
+  // None if the program halts on its own, even with register 0 never
+  // matching the values.
+  pub cycle: Option<Cycle>,
+}
+
+fn reads<W: Word>(operand: Operand, value: W, register: usize) -> bool {
+  operand == Operand::Register && value.to_index() == Some(register)
+}
+
+impl HaltingCheck {
+  pub fn find<W: Word>(program: &Program<W>) -> Result<HaltingCheck, Error> {
+    let ip = match program.ip_register {
+      Some(v) => v,
+      None => Err(Error::new("The program doesn't bind the instruction pointer"))?
+    };
+
+    let mut uses = vec![];
+    for (address, instruction) in program.instructions.iter().enumerate() {
+      let (a, b) = instruction.opcode.operands();
+      if reads(a, instruction.a, 0) || reads(b, instruction.b, 0) || instruction.c.to_index() == Some(0) {
+        uses.push(address);
+      }
+    }
+
+    let address = match uses.as_slice() {
+      [address] => *address,
+      [] => Err(Error::new("The program never uses register 0"))?,
+      _ => Err(Error::new(&format!("The program uses register 0 at more than one address: {:?}", uses)))?,
+    };
+
+    let compare = &program.instructions[address];
+    let (a, b, flag) = match (compare.a.to_index(), compare.b.to_index(), compare.c.to_index()) {
+      (Some(a), Some(b), Some(c)) if compare.opcode == Opcode::Eqrr => (a, b, c),
+      _ => Err(Error::new(&format!("ip {}: Register 0 is used by something other than eqrr", address)))?
+    };
+    let register = if a == 0 { b } else { a };
+
+    // The flag has to skip over the jump back into the program.
+    let skip = program.instructions.get(address + 1).filter(|i| {
+      let sources = (i.a.to_index(), i.b.to_index());
+      i.opcode == Opcode::Addr && i.c.to_index() == Some(ip) && (sources == (Some(flag), Some(ip)) || sources == (Some(ip), Some(flag)))
+    });
+    if register == 0 || register == ip || flag == ip || skip.is_none() || address + 3 < program.instructions.len() {
+      Err(Error::new(&format!("ip {}: The comparison with register 0 doesn't halt the program", address)))?
+    }
+
+    Ok(HaltingCheck { address, register })
+  }
+}
+
+// Brent's cycle detection. `f` returns None once the sequence ends.
+fn find_cycle<T, F>(x0: &T, mut f: F) -> Result<Option<Cycle>, Error>
+where T: Clone + PartialEq, F: FnMut(&T) -> Result<Option<T>, Error> {
+  let mut power = 1;
+  let mut length = 1;
+  let mut tortoise = x0.clone();
+  let mut hare = match f(x0)? {
+    Some(v) => v,
+    None => return Ok(None),
+  };
+
+  while tortoise != hare {
+    if power == length {
+      tortoise = hare.clone();
+      power *= 2;
+      length = 0;
+    }
+    hare = match f(&hare)? {
+      Some(v) => v,
+      None => return Ok(None),
+    };
+    length += 1;
+  }
+
+  tortoise = x0.clone();
+  hare = x0.clone();
+  for _ in 0..length {
+    hare = f(&hare)?.unwrap();
+  }
+  let mut start = 0;
+  while tortoise != hare {
+    tortoise = f(&tortoise)?.unwrap();
+    hare = f(&hare)?.unwrap();
+    start += 1;
+  }
+
+  Ok(Some(Cycle { start, length }))
+}
+
+// The machine is None if the program halts before the check.
+type Start<W> = (HaltingCheck, Compiled<W>, Option<Machine<W>>);
+
+// Runs the program optimized until it first reaches the halting check.
+// Register 0 stays 0, so the program only halts if it generates a 0.
+fn fast_forward<W: Word>(program: &Program<W>, register_count: usize) -> Result<Start<W>, Error> {
+  let check = HaltingCheck::find(program)?;
+  let compiled = Compiled::optimized(program, register_count)?;
+  let mut machine = Machine::for_program(program, register_count)?;
+
+  let reached = match compiled.run_until(&mut machine, Some(check.address), None)? {
+    Exit::Breakpoint => Some(machine),
+    _ => None,
+  };
+
+  Ok((check, compiled, reached))
+}
+
+// The value register 0 needs to halt the program as soon as possible.
+pub fn first_value<W: Word>(program: &Program<W>, register_count: usize) -> Result<W, Error> {
+  match fast_forward(program, register_count)? {
+    (check, _, Some(machine)) => Ok(machine.registers[check.register]),
+    _ => Err(Error::new("The program never reaches the halting check")),
+  }
+}
+
+// Finds the values the program compares register 0 with. The registers at
+// the halting check decide how the program goes on, so once they repeat the
+// values do as well.
+pub fn values<W: Word>(program: &Program<W>, register_count: usize) -> Result<HaltingValues<W>, Error> {
+  let (check, compiled, start) = fast_forward(program, register_count)?;
+  let start = match start {
+    Some(v) => v,
+    None => Err(Error::new("The program never reaches the halting check"))?
+  };
+
+  let next = |registers: &Vec<W>| -> Result<Option<Vec<W>>, Error> {
+    let mut machine = start.clone();
+    machine.registers.copy_from_slice(registers);
+    match compiled.run_until(&mut machine, Some(check.address), None)? {
+      Exit::Breakpoint => Ok(Some(machine.registers)),
+      _ => Ok(None),
+    }
+  };
+
+  let cycle = find_cycle(&start.registers, next)?;
+
+  // Every state up to the end of the first cycle is new, but different
+  // states may still compare the same value.
+  let mut seen = BTreeSet::new();
+  let mut last = start.registers[check.register];
+  let mut registers = Some(start.registers.clone());
+  let mut count = 0;
+  while let Some(current) = registers {
+    if cycle.is_some_and(|c| count == c.start + c.length) {
+      break;
+    }
+    if seen.insert(current[check.register]) {
+      last = current[check.register];
+    }
+    registers = next(&current)?;
+    count += 1;
+  }
+
+  Ok(HaltingValues { first: start.registers[check.register], last, distinct: seen.len(), cycle })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  #[test]
+  fn finds_cycles() {
+    // 0 1 2 3 4 5 3 4 5 ...
+    let cycle = find_cycle(&0, |&x| Ok(Some(if x == 5 { 3 } else { x + 1 }))).unwrap();
+    assert_eq!(cycle, Some(Cycle { start: 3, length: 3 }));
+
+    let cycle = find_cycle(&7, |&x| Ok(Some(x))).unwrap();
+    assert_eq!(cycle, Some(Cycle { start: 0, length: 1 }));
+
+    let ends = find_cycle(&0, |&x| Ok(if x < 10 { Some(x + 1) } else { None })).unwrap();
+    assert_eq!(ends, None);
+  }
+
+  #[test]
+  fn finds_the_day21_check() {
+    let program = parse::<i64>(include_str!("../day21/data/input.txt"), 6).unwrap();
+    assert_eq!(HaltingCheck::find(&program).unwrap(), HaltingCheck { address: 28, register: 4 });
+  }
+
+  #[test]
+  fn analyses_a_small_generator() {
+    // r2 = (r1 * 5 + 3) & 7 | 8, which runs through 8 11 10 13 12 15 14 9
+    // before it repeats. The check uses other registers than day 21.
+    let input = "#ip 5\n\
+                 seti 1 0 1\n\
+                 muli 1 5 1\n\
+                 addi 1 3 1\n\
+                 bani 1 7 1\n\
+                 bori 1 8 2\n\
+                 eqrr 2 0 3\n\
+                 addr 3 5 5\n\
+                 seti 0 0 5\n";
+    let program = parse::<i64>(input, 6).unwrap();
+    assert_eq!(HaltingCheck::find(&program).unwrap(), HaltingCheck { address: 5, register: 2 });
+    assert_eq!(first_value(&program, 6).unwrap(), 8);
+
+    let values = values(&program, 6).unwrap();
+    assert_eq!(values, HaltingValues { first: 8, last: 9, distinct: 8, cycle: Some(Cycle { start: 0, length: 8 }) });
+  }
+
+  #[test]
+  fn programs_without_a_check_are_rejected() {
+    let program = parse::<i64>("#ip 1\naddi 0 1 0\nseti 0 0 1\n", 2).unwrap();
+    assert!(HaltingCheck::find(&program).is_err());
+    let program = parse::<i64>("#ip 1\nseti 0 0 2\nseti 0 0 1\n", 3).unwrap();
+    assert_eq!(HaltingCheck::find(&program).err().unwrap().to_string(), "The program never uses register 0");
+  }
+}
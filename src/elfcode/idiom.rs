@@ -0,0 +1,358 @@
+use super::opcode::Opcode;
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+// Loops that are known to compute something simple, with the registers
+// they work on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idiom<W: Word> {
+  // Adds the sum of all divisors of `n` to `sum` by trying every pair of
+  // `i` and `j` up to `n` and checking if `i * j == n`.
+  DivisorSum { n: usize, sum: usize, i: usize, j: usize, flag: usize },
+  // Divides `dividend` by `divisor` by counting `quotient` up until
+  // `(quotient + 1) * divisor` is larger than the dividend.
+  Quotient { dividend: usize, quotient: usize, flag: usize, divisor: W },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<W: Word> {
+  // The first instruction of the loop, it has to be entered from here.
+  pub start: usize,
+  // The instruction that runs after the loop is done.
+  pub exit: usize,
+  pub idiom: Idiom<W>,
+}
+
+// None if the sum doesn't fit into a word. A square that doesn't fit is
+// larger than `n` anyway.
+fn divisor_sum<W: Word>(n: W) -> Option<W> {
+  let mut sum = W::ZERO;
+  let mut i = W::ONE;
+  while let Some(square) = i.checked_mul(i).filter(|&square| square <= n) {
+    if n % i == W::ZERO {
+      sum = sum.checked_add(i)?;
+      if square != n {
+        sum = sum.checked_add(n / i)?;
+      }
+    }
+    i = i + W::ONE;
+  }
+
+  Some(sum)
+}
+
+impl<W: Word> Idiom<W> {
+  // Leaves the registers the way the loop would have left them. Returns
+  // false and leaves them alone if the sum or the counters don't fit into
+  // a word, so the loop runs as written.
+  pub fn apply(&self, registers: &mut [W]) -> bool {
+    match *self {
+      Idiom::DivisorSum { n, sum, i, j, flag } => {
+        let value = registers[n];
+        // Both loops run at least once.
+        let end = if value > W::ONE { value } else { W::ONE }.checked_add(W::ONE);
+        let total = divisor_sum(value).and_then(|v| registers[sum].checked_add(v));
+        let (end, total) = match (end, total) {
+          (Some(end), Some(total)) => (end, total),
+          _ => return false,
+        };
+        registers[sum] = total;
+        registers[i] = end;
+        registers[j] = end;
+        registers[flag] = W::ONE;
+      },
+      Idiom::Quotient { dividend, quotient, flag, divisor } => {
+        let value = registers[dividend];
+        let q = if value < W::ZERO { W::ZERO } else { value / divisor };
+        // The loop ends by checking `(q + 1) * divisor`.
+        if q.checked_add(W::ONE).and_then(|next| next.checked_mul(divisor)).is_none() {
+          return false;
+        }
+        registers[quotient] = q;
+        registers[flag] = W::ONE;
+      },
+    }
+
+    true
+  }
+}
+
+// A window into the program that knows the bound register and the address
+// the window starts at, so jumps can be checked.
+struct Code<'a, W: Word> {
+  instructions: &'a [Instruction<W>],
+  start: usize,
+  ip: usize,
+}
+
+impl<'a, W: Word> Code<'a, W> {
+  fn instruction(&self, offset: usize, opcode: Opcode) -> Option<&Instruction<W>> {
+    self.instructions.get(self.start + offset).filter(|i| i.opcode == opcode)
+  }
+
+  // The registers A, B and C of an instruction that reads two registers.
+  fn registers(&self, offset: usize, opcode: Opcode) -> Option<(usize, usize, usize)> {
+    let i = self.instruction(offset, opcode)?;
+    Some((i.a.to_index()?, i.b.to_index()?, i.c.to_index()?))
+  }
+
+  // Register A, value B and register C of an instruction with an immediate.
+  fn immediate(&self, offset: usize, opcode: Opcode) -> Option<(usize, W, usize)> {
+    let i = self.instruction(offset, opcode)?;
+    Some((i.a.to_index()?, i.b, i.c.to_index()?))
+  }
+
+  fn set(&self, offset: usize) -> Option<(W, usize)> {
+    let i = self.instruction(offset, Opcode::Seti)?;
+    Some((i.a, i.c.to_index()?))
+  }
+
+  fn increment(&self, offset: usize, register: usize) -> Option<()> {
+    Some(()).filter(|_| self.immediate(offset, Opcode::Addi) == Some((register, W::ONE, register)))
+  }
+
+  // `addr flag ip ip` in either order, skips the next instruction if the flag is set.
+  fn skip_if(&self, offset: usize, flag: usize) -> Option<()> {
+    let (a, b, c) = self.registers(offset, Opcode::Addr)?;
+    Some(()).filter(|_| c == self.ip && either(a, b, self.ip) == Some(flag))
+  }
+
+  // `seti target-1 _ ip`
+  fn goto(&self, offset: usize, target: usize) -> Option<()> {
+    let (value, register) = self.set(offset)?;
+    Some(()).filter(|_| register == self.ip && value.checked_add(W::ONE).and_then(W::to_index) == Some(target))
+  }
+}
+
+// The other register if one of `a` and `b` is `known`.
+fn either(a: usize, b: usize, known: usize) -> Option<usize> {
+  if a == known {
+    Some(b)
+  } else if b == known {
+    Some(a)
+  } else {
+    None
+  }
+}
+
+fn distinct(registers: &[usize]) -> Option<()> {
+  let unique = registers.iter().enumerate().all(|(k, r)| !registers[..k].contains(r));
+  Some(()).filter(|_| unique)
+}
+
+//  0  seti 1 _ i
+//  1  seti 1 _ j
+//  2  mulr i j flag
+//  3  eqrr flag n flag
+//  4  addr flag ip ip
+//  5  addi ip 1 ip
+//  6  addr i sum sum
+//  7  addi j 1 j
+//  8  gtrr j n flag
+//  9  addr ip flag ip
+// 10  seti start+1 _ ip
+// 11  addi i 1 i
+// 12  gtrr i n flag
+// 13  addr flag ip ip
+// 14  seti start _ ip
+fn find_divisor_sum<W: Word>(code: &Code<W>) -> Option<Match<W>> {
+  let (one, i) = code.set(0)?;
+  let (other, j) = code.set(1)?;
+  if one != W::ONE || other != W::ONE {
+    return None;
+  }
+
+  let (a, b, flag) = code.registers(2, Opcode::Mulr)?;
+  if either(a, b, i) != Some(j) {
+    return None;
+  }
+  let (a, b, c) = code.registers(3, Opcode::Eqrr)?;
+  let n = either(a, b, flag).filter(|_| c == flag)?;
+  code.skip_if(4, flag)?;
+  code.increment(5, code.ip)?;
+  let (a, b, sum) = code.registers(6, Opcode::Addr)?;
+  if either(a, b, i) != Some(sum) {
+    return None;
+  }
+  code.increment(7, j)?;
+  if code.registers(8, Opcode::Gtrr)? != (j, n, flag) {
+    return None;
+  }
+  code.skip_if(9, flag)?;
+  code.goto(10, code.start + 2)?;
+  code.increment(11, i)?;
+  if code.registers(12, Opcode::Gtrr)? != (i, n, flag) {
+    return None;
+  }
+  code.skip_if(13, flag)?;
+  code.goto(14, code.start + 1)?;
+  distinct(&[n, sum, i, j, flag, code.ip])?;
+
+  Some(Match { start: code.start, exit: code.start + 15, idiom: Idiom::DivisorSum { n, sum, i, j, flag } })
+}
+
+//  0  seti 0 _ quotient
+//  1  addi quotient 1 flag
+//  2  muli flag divisor flag
+//  3  gtrr flag dividend flag
+//  4  addr flag ip ip
+//  5  addi ip 1 ip
+//  6  seti start+8 _ ip
+//  7  addi quotient 1 quotient
+//  8  seti start _ ip
+fn find_quotient<W: Word>(code: &Code<W>) -> Option<Match<W>> {
+  let (zero, quotient) = code.set(0)?;
+  if zero != W::ZERO {
+    return None;
+  }
+
+  let (q, one, flag) = code.immediate(1, Opcode::Addi)?;
+  if q != quotient || one != W::ONE {
+    return None;
+  }
+  let (f, divisor, c) = code.immediate(2, Opcode::Muli)?;
+  if f != flag || c != flag || divisor <= W::ZERO {
+    return None;
+  }
+  let (f, dividend, c) = code.registers(3, Opcode::Gtrr)?;
+  if f != flag || c != flag {
+    return None;
+  }
+  code.skip_if(4, flag)?;
+  code.increment(5, code.ip)?;
+  code.goto(6, code.start + 9)?;
+  code.increment(7, quotient)?;
+  code.goto(8, code.start + 1)?;
+  distinct(&[dividend, quotient, flag, code.ip])?;
+
+  Some(Match { start: code.start, exit: code.start + 9, idiom: Idiom::Quotient { dividend, quotient, flag, divisor } })
+}
+
+// Finds the loops in the program that can be replaced by a native
+// computation. Matches don't overlap.
+pub fn find<W: Word>(program: &Program<W>) -> Vec<Match<W>> {
+  let ip = match program.ip_register {
+    Some(v) => v,
+    None => return vec![],
+  };
+
+  let mut matches = vec![];
+  let mut start = 0;
+  while start < program.instructions.len() {
+    let code = Code { instructions: &program.instructions, start, ip };
+    match find_divisor_sum(&code).or_else(|| find_quotient(&code)) {
+      Some(found) => {
+        start = found.exit;
+        matches.push(found);
+      },
+      None => start += 1,
+    }
+  }
+
+  matches
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+  use super::super::machine::Machine;
+
+  // Runs the loop at `start` on the machine and compares the result with the idiom.
+  fn check(program: &Program<i64>, found: &Match<i64>, registers: &[i64]) {
+    let mut machine = Machine::for_program(program, 6).unwrap();
+    machine.registers.copy_from_slice(registers);
+    machine.ip = found.start as i64;
+    let mut native = machine.registers.clone();
+
+    while machine.ip != found.exit as i64 {
+      machine.step(program).unwrap();
+    }
+    assert!(found.idiom.apply(&mut native));
+    native[program.ip_register.unwrap()] = found.exit as i64 - 1;
+
+    assert_eq!(native, machine.registers, "{:?}", registers);
+  }
+
+  #[test]
+  fn finds_the_day19_divisor_sum() {
+    let program = parse::<i64>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    let found = find(&program);
+
+    assert_eq!(found, vec![Match { start: 1, exit: 16, idiom: Idiom::DivisorSum { n: 1, sum: 0, i: 4, j: 2, flag: 3 } }]);
+    for &n in &[-3, 0, 1, 2, 12, 36, 97] {
+      check(&program, &found[0], &[5, n, 0, 0, 0, 0]);
+    }
+
+    // The loop would overflow, so the registers are left to it.
+    let mut registers = [i64::MAX, 12, 0, 0, 0, 0];
+    assert!(!found[0].idiom.apply(&mut registers));
+    assert_eq!(registers, [i64::MAX, 12, 0, 0, 0, 0]);
+    let mut registers = [0, i64::MAX, 0, 0, 0, 0];
+    assert!(!found[0].idiom.apply(&mut registers));
+    assert_eq!(registers, [0, i64::MAX, 0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn divisor_sums_never_overflow() {
+    assert_eq!(divisor_sum(36), Some(91));
+    // A prime just below the largest i32, the square after its root doesn't fit.
+    assert_eq!(divisor_sum(2147483629i32), Some(2147483630));
+    assert_eq!(divisor_sum(i32::MAX - 1), None);
+    assert_eq!(divisor_sum(i128::MAX), None);
+  }
+
+  #[test]
+  fn finds_the_day21_quotient() {
+    let program = parse::<i64>(include_str!("../day21/data/input.txt"), 6).unwrap();
+    let found = find(&program);
+
+    assert_eq!(found, vec![Match { start: 17, exit: 26, idiom: Idiom::Quotient { dividend: 5, quotient: 1, flag: 3, divisor: 256 } }]);
+    for &x in &[-5, 0, 255, 256, 65535, 65536, 1_000_000] {
+      check(&program, &found[0], &[0, 0, 0, 0, 0, x]);
+    }
+
+    // The last product of the loop would overflow.
+    let mut registers = [0, 0, 0, 0, 0, i64::MAX];
+    assert!(!found[0].idiom.apply(&mut registers));
+    assert_eq!(registers, [0, 0, 0, 0, 0, i64::MAX]);
+  }
+
+  #[test]
+  fn register_names_and_operand_order_may_differ() {
+    let input = "#ip 2\n\
+                 seti 0 0 0\n\
+                 seti 0 0 4\n\
+                 addi 4 1 1\n\
+                 muli 1 10 1\n\
+                 gtrr 1 3 1\n\
+                 addr 2 1 2\n\
+                 addi 2 1 2\n\
+                 seti 9 0 2\n\
+                 addi 4 1 4\n\
+                 seti 1 0 2\n\
+                 setr 4 0 5\n";
+    let program = parse::<i64>(input, 6).unwrap();
+    let found = find(&program);
+
+    assert_eq!(found, vec![Match { start: 1, exit: 10, idiom: Idiom::Quotient { dividend: 3, quotient: 4, flag: 1, divisor: 10 } }]);
+    check(&program, &found[0], &[0, 0, 0, 123, 0, 0]);
+  }
+
+  #[test]
+  fn loops_that_differ_are_left_alone() {
+    // The same divide loop, but it counts up by two.
+    let input = "#ip 2\n\
+                 seti 0 0 4\n\
+                 addi 4 1 1\n\
+                 muli 1 10 1\n\
+                 gtrr 1 3 1\n\
+                 addr 1 2 2\n\
+                 addi 2 1 2\n\
+                 seti 8 0 2\n\
+                 addi 4 2 4\n\
+                 seti 0 0 2\n";
+    let program = parse::<i64>(input, 6).unwrap();
+    assert!(find(&program).is_empty());
+  }
+}
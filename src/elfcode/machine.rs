@@ -0,0 +1,227 @@
+use super::super::utils::Error;
+use super::arithmetic::{self, Arithmetic};
+use super::opcode::Operand;
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Machine<W: Word> {
+  pub registers: Vec<W>,
+  pub ip: W,
+  pub ip_register: Option<usize>,
+  // Number of instructions executed so far.
+  pub cycles: u64,
+  // Checked unless set otherwise, so overflows never go unnoticed.
+  pub arithmetic: Arithmetic,
+}
+
+impl<W: Word> Machine<W> {
+  pub fn new(register_count: usize) -> Machine<W> {
+    Machine::with_registers(vec![W::ZERO; register_count])
+  }
+
+  pub fn with_registers(registers: Vec<W>) -> Machine<W> {
+    Machine { registers, ip: W::ZERO, ip_register: None, cycles: 0, arithmetic: Arithmetic::default() }
+  }
+
+  // A machine with the instruction pointer bound as the program declares it.
+  pub fn for_program(program: &Program<W>, register_count: usize) -> Result<Machine<W>, Error> {
+    let mut machine = Machine::new(register_count);
+    if let Some(register) = program.ip_register {
+      machine.bind_ip(register)?;
+    }
+
+    Ok(machine)
+  }
+
+  pub fn bind_ip(&mut self, register: usize) -> Result<(), Error> {
+    if register >= self.registers.len() {
+      Err(Error::new(&format!("Register {} does not exist", register)))?
+    }
+
+    self.ip_register = Some(register);
+    Ok(())
+  }
+
+  fn register(&self, index: W) -> Result<usize, Error> {
+    match index.to_index() {
+      Some(i) if i < self.registers.len() => Ok(i),
+      _ => Err(Error::new(&format!("Register {} does not exist", index))),
+    }
+  }
+
+  fn operand(&self, kind: Operand, value: W) -> Result<W, Error> {
+    Ok(match kind {
+      Operand::Register => self.registers[self.register(value)?],
+      Operand::Immediate => value,
+      Operand::Ignored => W::ZERO,
+    })
+  }
+
+  // Executes a single instruction, leaving the instruction pointer alone.
+  #[inline]
+  pub fn execute(&mut self, instruction: &Instruction<W>) -> Result<(), Error> {
+    let (kind_a, kind_b) = instruction.opcode.operands();
+    let a = self.operand(kind_a, instruction.a)?;
+    let b = self.operand(kind_b, instruction.b)?;
+    let c = self.register(instruction.c)?;
+
+    self.registers[c] = match self.arithmetic.apply(instruction.opcode, a, b) {
+      Some(v) => v,
+      None => Err(arithmetic::overflow(instruction.opcode, a, b))?,
+    };
+    Ok(())
+  }
+
+  // The instruction at the instruction pointer, None once the program halted.
+  pub fn fetch<'a>(&self, program: &'a Program<W>) -> Option<&'a Instruction<W>> {
+    self.ip.to_index().and_then(|ip| program.instructions.get(ip))
+  }
+
+  // Executes the next instruction. Returns false if the program has halted.
+  pub fn step(&mut self, program: &Program<W>) -> Result<bool, Error> {
+    let instruction = match self.fetch(program) {
+      Some(v) => *v,
+      None => return Ok(false),
+    };
+
+    if let Some(register) = self.ip_register {
+      self.registers[register] = self.ip;
+    }
+
+    let ip = self.ip;
+    self.execute(&instruction).map_err(|err| Error::new(&format!("ip {}: {}", ip, err)))?;
+
+    let target = match self.ip_register {
+      Some(register) => self.registers[register],
+      None => self.ip,
+    };
+    self.ip = match target.checked_add(W::ONE) {
+      Some(v) => v,
+      None => {
+        // Like an overflowing sum, the jump never happened.
+        if let Some(register) = self.ip_register {
+          self.registers[register] = ip;
+        }
+        Err(Error::new(&format!("ip {}: {}", ip, arithmetic::jump_overflow(target))))?
+      },
+    };
+    self.cycles += 1;
+
+    Ok(true)
+  }
+
+  pub fn run(&mut self, program: &Program<W>) -> Result<(), Error> {
+    while self.step(program)? {}
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::opcode::Opcode;
+
+  fn instruction(opcode: Opcode, a: i64, b: i64, c: i64) -> Instruction<i64> {
+    Instruction::new(opcode, a, b, c)
+  }
+
+  #[test]
+  fn executes_the_day16_example() {
+    let mut machine = Machine::with_registers(vec![3, 2, 1, 1]);
+    machine.execute(&instruction(Opcode::Mulr, 2, 1, 2)).unwrap();
+    assert_eq!(machine.registers, vec![3, 2, 2, 1]);
+  }
+
+  #[test]
+  fn runs_the_day19_example() {
+    let program = Program::new(Some(0), vec![
+      instruction(Opcode::Seti, 5, 0, 1),
+      instruction(Opcode::Seti, 6, 0, 2),
+      instruction(Opcode::Addi, 0, 1, 0),
+      instruction(Opcode::Addr, 1, 2, 3),
+      instruction(Opcode::Setr, 1, 0, 0),
+      instruction(Opcode::Seti, 8, 0, 4),
+      instruction(Opcode::Seti, 9, 0, 5),
+    ]);
+
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    machine.run(&program).unwrap();
+    assert_eq!(machine.registers, vec![6, 5, 6, 0, 0, 9]);
+    assert_eq!(machine.cycles, 5);
+  }
+
+  #[test]
+  fn invalid_registers_are_errors() {
+    let mut machine: Machine<i32> = Machine::new(4);
+    let err = machine.execute(&Instruction::new(Opcode::Addr, 0, 4, 0)).unwrap_err();
+    assert_eq!(err.to_string(), "Register 4 does not exist");
+    assert!(machine.bind_ip(4).is_err());
+  }
+
+  #[test]
+  fn errors_name_the_instruction_pointer() {
+    let program = Program::new(None, vec![
+      instruction(Opcode::Seti, 1, 0, 0),
+      instruction(Opcode::Setr, 7, 0, 0),
+    ]);
+
+    let mut machine = Machine::new(6);
+    let err = machine.run(&program).unwrap_err();
+    assert_eq!(err.to_string(), "ip 1: Register 7 does not exist");
+  }
+
+  #[test]
+  fn overflows_follow_the_arithmetic_mode() {
+    let program = Program::new(Some(3), vec![
+      instruction(Opcode::Seti, 1, 0, 0),
+      instruction(Opcode::Mulr, 0, 1, 0),
+      instruction(Opcode::Seti, 0, 0, 3),
+    ]);
+    let mut machine = Machine::for_program(&program, 4).unwrap();
+    machine.registers[1] = 3;
+    let run = |arithmetic: Arithmetic, cycles: u64| -> Result<Machine<i64>, Error> {
+      let mut machine = Machine { arithmetic, ..machine.clone() };
+      for _ in 0..cycles {
+        machine.step(&program)?;
+      }
+      Ok(machine)
+    };
+
+    // 3^40 doesn't fit into 64 bits.
+    let wrapped = run(Arithmetic::Wrapping, 80).unwrap();
+    assert_eq!(wrapped.registers[0], 3i64.wrapping_pow(40));
+    let saturated = run(Arithmetic::Saturating, 80).unwrap();
+    assert_eq!(saturated.registers[0], i64::MAX);
+    let narrow = run(Arithmetic::WrappingBits(16), 80).unwrap();
+    assert_eq!(narrow.registers[0], 3i64.wrapping_pow(40) as i16 as i64);
+
+    let err = run(Arithmetic::Checked, 80).unwrap_err();
+    assert_eq!(err.to_string(), "ip 1: mulr overflows: 4052555153018976267 * 3");
+    // The machine stops at the instruction that overflowed.
+    machine.run(&program).unwrap_err();
+    assert_eq!((machine.ip, machine.registers[0], machine.registers[3]), (1, 3i64.pow(39), 1));
+  }
+
+  #[test]
+  fn jumps_past_the_largest_word_are_errors() {
+    let program = Program::new(Some(0), vec![Instruction::new(Opcode::Seti, i128::MAX, 0, 0)]);
+    for &arithmetic in &[Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Saturating, Arithmetic::WrappingBits(128)] {
+      let mut machine = Machine::for_program(&program, 1).unwrap();
+      machine.arithmetic = arithmetic;
+      let err = machine.run(&program).unwrap_err();
+      assert_eq!(err.to_string(), format!("ip 0: The instruction pointer overflows: {} + 1", i128::MAX));
+      // The machine stops at the jump, as if it never happened.
+      assert_eq!((machine.ip, machine.registers[0], machine.cycles), (0, 0, 0), "{}", arithmetic);
+    }
+  }
+
+  #[test]
+  fn immediates_are_not_registers() {
+    let mut machine: Machine<i32> = Machine::new(4);
+    machine.execute(&Instruction::new(Opcode::Seti, 42, 99, 3)).unwrap();
+    machine.execute(&Instruction::new(Opcode::Gtir, 50, 3, 0)).unwrap();
+    assert_eq!(machine.registers, vec![1, 0, 0, 42]);
+  }
+}
@@ -0,0 +1,30 @@
+// The virtual machine behind the ElfCode programs of days 16, 19 and 21.
+// Register count and word size are up to the caller, so the four 32 bit
+// registers of day 16 and the six registers of days 19 and 21 share the
+// same instruction semantics.
+pub mod opcode;
+pub mod word;
+pub mod arithmetic;
+pub mod program;
+pub mod machine;
+pub mod snapshot;
+pub mod parser;
+pub mod decompiler;
+pub mod debugger;
+pub mod trace;
+pub mod compiled;
+pub mod idiom;
+pub mod halting;
+pub mod transpiler;
+pub mod cfg;
+
+pub use self::opcode::{Opcode, Operand};
+pub use self::word::Word;
+pub use self::arithmetic::Arithmetic;
+pub use self::program::{Instruction, Program};
+pub use self::machine::Machine;
+pub use self::snapshot::{History, Snapshot};
+pub use self::parser::parse;
+pub use self::decompiler::decompile;
+pub use self::debugger::Debugger;
+pub use self::compiled::Compiled;
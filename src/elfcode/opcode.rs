@@ -0,0 +1,148 @@
+use std::fmt;
+use std::str::FromStr;
+use super::super::utils::ParseError;
+use super::word::Word;
+
+// How an instruction interprets its A and B inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+  Register,
+  Immediate,
+  Ignored,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Opcode {
+  // addr (add register) stores into register C the result of adding register A and register B.
+  Addr,
+  // addi (add immediate) stores into register C the result of adding register A and value B.
+  Addi,
+  // mulr (multiply register) stores into register C the result of multiplying register A and register B.
+  Mulr,
+  // muli (multiply immediate) stores into register C the result of multiplying register A and value B.
+  Muli,
+  // banr (bitwise AND register) stores into register C the result of the bitwise AND of register A and register B.
+  Banr,
+  // bani (bitwise AND immediate) stores into register C the result of the bitwise AND of register A and value B.
+  Bani,
+  // borr (bitwise OR register) stores into register C the result of the bitwise OR of register A and register B.
+  Borr,
+  // bori (bitwise OR immediate) stores into register C the result of the bitwise OR of register A and value B.
+  Bori,
+  // setr (set register) copies the contents of register A into register C. (Input B is ignored.)
+  Setr,
+  // seti (set immediate) stores value A into register C. (Input B is ignored.)
+  Seti,
+  // gtir (greater-than immediate/register) sets register C to 1 if value A is greater than register B. Otherwise, register C is set to 0.
+  Gtir,
+  // gtri (greater-than register/immediate) sets register C to 1 if register A is greater than value B. Otherwise, register C is set to 0.
+  Gtri,
+  // gtrr (greater-than register/register) sets register C to 1 if register A is greater than register B. Otherwise, register C is set to 0.
+  Gtrr,
+  // eqir (equal immediate/register) sets register C to 1 if value A is equal to register B. Otherwise, register C is set to 0.
+  Eqir,
+  // eqri (equal register/immediate) sets register C to 1 if register A is equal to value B. Otherwise, register C is set to 0.
+  Eqri,
+  // eqrr (equal register/register) sets register C to 1 if register A is equal to register B. Otherwise, register C is set to 0.
+  Eqrr,
+}
+
+pub const ALL: [Opcode; 16] = [
+  Opcode::Addr, Opcode::Addi, Opcode::Mulr, Opcode::Muli,
+  Opcode::Banr, Opcode::Bani, Opcode::Borr, Opcode::Bori,
+  Opcode::Setr, Opcode::Seti, Opcode::Gtir, Opcode::Gtri,
+  Opcode::Gtrr, Opcode::Eqir, Opcode::Eqri, Opcode::Eqrr,
+];
+
+impl Opcode {
+  pub fn mnemonic(self) -> &'static str {
+    match self {
+      Opcode::Addr => "addr",
+      Opcode::Addi => "addi",
+      Opcode::Mulr => "mulr",
+      Opcode::Muli => "muli",
+      Opcode::Banr => "banr",
+      Opcode::Bani => "bani",
+      Opcode::Borr => "borr",
+      Opcode::Bori => "bori",
+      Opcode::Setr => "setr",
+      Opcode::Seti => "seti",
+      Opcode::Gtir => "gtir",
+      Opcode::Gtri => "gtri",
+      Opcode::Gtrr => "gtrr",
+      Opcode::Eqir => "eqir",
+      Opcode::Eqri => "eqri",
+      Opcode::Eqrr => "eqrr",
+    }
+  }
+
+  pub fn operands(self) -> (Operand, Operand) {
+    use self::Operand::*;
+
+    match self {
+      Opcode::Addr | Opcode::Mulr | Opcode::Banr | Opcode::Borr => (Register, Register),
+      Opcode::Gtrr | Opcode::Eqrr => (Register, Register),
+      Opcode::Addi | Opcode::Muli | Opcode::Bani | Opcode::Bori => (Register, Immediate),
+      Opcode::Gtri | Opcode::Eqri => (Register, Immediate),
+      Opcode::Gtir | Opcode::Eqir => (Immediate, Register),
+      Opcode::Setr => (Register, Ignored),
+      Opcode::Seti => (Immediate, Ignored),
+    }
+  }
+
+  // Computes the value stored into register C from the resolved inputs.
+  pub fn apply<W: Word>(self, a: W, b: W) -> W {
+    let flag = |v: bool| if v { W::ONE } else { W::ZERO };
+
+    match self {
+      Opcode::Addr | Opcode::Addi => a + b,
+      Opcode::Mulr | Opcode::Muli => a * b,
+      Opcode::Banr | Opcode::Bani => a & b,
+      Opcode::Borr | Opcode::Bori => a | b,
+      Opcode::Setr | Opcode::Seti => a,
+      Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr => flag(a > b),
+      Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr => flag(a == b),
+    }
+  }
+}
+
+impl fmt::Display for Opcode {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.mnemonic())
+  }
+}
+
+impl FromStr for Opcode {
+  type Err = ParseError;
+
+  fn from_str(s: &str) -> Result<Opcode, ParseError> {
+    match ALL.iter().find(|op| op.mnemonic() == s) {
+      Some(op) => Ok(*op),
+      None => Err(ParseError::new(&format!("Unknown opcode '{}'", s))),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mnemonics_round_trip() {
+    for op in ALL.iter() {
+      assert_eq!(Opcode::from_str(op.mnemonic()).unwrap(), *op);
+    }
+  }
+
+  #[test]
+  fn unknown_mnemonics_are_rejected() {
+    assert_eq!(Opcode::from_str("addx").unwrap_err().to_string(), "Unknown opcode 'addx'");
+  }
+
+  #[test]
+  fn comparisons_yield_one_or_zero() {
+    assert_eq!(Opcode::Gtrr.apply(3i32, 2), 1);
+    assert_eq!(Opcode::Gtrr.apply(2i32, 3), 0);
+    assert_eq!(Opcode::Eqri.apply(4i64, 4), 1);
+  }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use regex::Regex;
+use super::super::utils::{numbered_lines, parse_number, ParseError};
+use super::opcode::{Opcode, Operand};
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+// Everything from a semicolon to the end of the line is a comment.
+const COMMENT: char = ';';
+
+lazy_static!{
+  static ref RE_REGISTER: Regex = Regex::new(r"^r(\d+)$").unwrap();
+  static ref RE_LABEL: Regex = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*):$").unwrap();
+  static ref RE_LABEL_REF: Regex = Regex::new(r"^([A-Za-z_][A-Za-z0-9_]*)([+-]\d+)?$").unwrap();
+}
+
+#[derive(Debug)]
+struct Token<'a> {
+  // 1 based column of the first character.
+  column: usize,
+  text: &'a str,
+}
+
+impl<'a> Token<'a> {
+  fn end(&self) -> usize {
+    self.column + self.text.chars().count()
+  }
+}
+
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+  let code = match line.find(COMMENT) {
+    Some(index) => &line[..index],
+    None => line,
+  };
+
+  let mut tokens = vec![];
+  let mut start = None;
+  for (index, chr) in code.char_indices().chain(Some((code.len(), ' '))) {
+    if chr.is_whitespace() {
+      if let Some(s) = start.take() {
+        let column = code[..s].chars().count() + 1;
+        tokens.push(Token { column, text: &code[s..index] });
+      }
+    } else if start.is_none() {
+      start = Some(index);
+    }
+  }
+
+  tokens
+}
+
+fn parse_register(token: &Token, register_count: usize) -> Result<usize, ParseError> {
+  let text = match RE_REGISTER.captures(token.text) {
+    Some(c) => c.get(1).map_or("", |m| m.as_str()),
+    None => token.text,
+  };
+
+  let register = match parse_number::<usize>(text) {
+    Ok(v) => v,
+    Err(_) => Err(ParseError::new(&format!("Expected a register but found '{}'", token.text)).at_column(token.column))?
+  };
+
+  if register >= register_count {
+    Err(ParseError::new(&format!("Register {} does not exist", register)).at_column(token.column))?
+  }
+
+  Ok(register)
+}
+
+fn resolve_label<W: Word>(token: &Token, labels: &HashMap<&str, usize>) -> Result<W, ParseError> {
+  let capture = match RE_LABEL_REF.captures(token.text) {
+    Some(c) => c,
+    None => Err(ParseError::new(&format!("Expected a value but found '{}'", token.text)))?
+  };
+
+  let address = match labels.get(&capture[1]) {
+    Some(v) => *v as i64,
+    None => Err(ParseError::new(&format!("Unknown label '{}'", &capture[1])))?
+  };
+
+  let offset = match capture.get(2) {
+    Some(m) => parse_number::<i64>(m.as_str())?,
+    None => 0,
+  };
+
+  let target = match address.checked_add(offset) {
+    Some(v) if v >= 0 => W::from_index(v as usize),
+    _ => None,
+  };
+
+  match target {
+    Some(v) => Ok(v),
+    None => Err(ParseError::new(&format!("Label '{}' points outside of the program", token.text))),
+  }
+}
+
+fn parse_value<W: Word>(token: &Token, labels: &HashMap<&str, usize>) -> Result<W, ParseError> {
+  let result = if RE_REGISTER.is_match(token.text) {
+    Err(ParseError::new(&format!("Expected a value but found register '{}'", token.text)))
+  } else if token.text.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+    parse_number::<W>(token.text)
+  } else {
+    resolve_label(token, labels)
+  };
+
+  result.map_err(|err| err.at_column(token.column))
+}
+
+fn parse_operand<W: Word>(token: &Token, kind: Operand, labels: &HashMap<&str, usize>, register_count: usize) -> Result<W, ParseError> {
+  let register = |token: &Token| {
+    let index = parse_register(token, register_count)?;
+    // Register indices always fit, the register count is a usize itself.
+    W::from_index(index).ok_or_else(|| ParseError::new("Register index is too large").at_column(token.column))
+  };
+
+  match kind {
+    Operand::Register => register(token),
+    Operand::Immediate => parse_value(token, labels),
+    // Ignored inputs may be anything, but they still have to make sense.
+    Operand::Ignored if RE_REGISTER.is_match(token.text) => register(token),
+    Operand::Ignored => parse_value(token, labels),
+  }
+}
+
+fn parse_instruction<W: Word>(tokens: &[Token], labels: &HashMap<&str, usize>, register_count: usize) -> Result<Instruction<W>, ParseError> {
+  let (name, operands) = match tokens.split_first() {
+    Some(v) => v,
+    None => Err(ParseError::new("Expected an instruction"))?
+  };
+
+  let opcode = Opcode::from_str(name.text).map_err(|err| err.at_column(name.column))?;
+
+  if operands.len() > 3 {
+    Err(ParseError::new(&format!("Unexpected '{}'", operands[3].text)).at_column(operands[3].column))?
+  }
+
+  if operands.len() < 3 {
+    let column = operands.last().unwrap_or(name).end();
+    let what = format!("{} expects 3 operands but found {}", opcode, operands.len());
+    Err(ParseError::new(&what).at_column(column))?
+  }
+
+  let (kind_a, kind_b) = opcode.operands();
+  let a = parse_operand(&operands[0], kind_a, labels, register_count)?;
+  let b = parse_operand(&operands[1], kind_b, labels, register_count)?;
+  let c = parse_operand(&operands[2], Operand::Register, labels, register_count)?;
+
+  Ok(Instruction::new(opcode, a, b, c))
+}
+
+fn parse_directive(tokens: &[Token], register_count: usize) -> Result<usize, ParseError> {
+  match tokens {
+    [directive] => {
+      let what = format!("Expected a register after {}", directive.text);
+      Err(ParseError::new(&what).at_column(directive.end()))
+    },
+    [_, register] => parse_register(register, register_count),
+    _ => Err(ParseError::new(&format!("Unexpected '{}'", tokens[2].text)).at_column(tokens[2].column)),
+  }
+}
+
+// Parses an ElfCode program for a machine with the given number of registers.
+//
+// Besides the plain puzzle syntax this understands
+//  - comments starting with `;`,
+//  - symbolic register names `r0`, `r1`, ... (plain numbers work as well),
+//  - labels like `loop:` in front of an instruction or on a line of their
+//    own. Labels can be used as values and evaluate to the index of the
+//    instruction that follows, optionally with an offset (`loop-1`).
+pub fn parse<W: Word>(input: &str, register_count: usize) -> Result<Program<W>, ParseError> {
+  let mut ip_register = None;
+  let mut labels = HashMap::new();
+  let mut lines = vec![];
+
+  for (number, line) in numbered_lines(input) {
+    let mut tokens = tokenize(line);
+
+    let directive = tokens.first().filter(|t| t.text.starts_with('#')).map(|t| t.text);
+    if let Some(directive) = directive {
+      let register = match directive {
+        "#ip" if ip_register.is_some() => Err(ParseError::new("Instruction pointer is bound twice").at_column(tokens[0].column)),
+        "#ip" => parse_directive(&tokens, register_count),
+        _ => Err(ParseError::new(&format!("Unknown directive '{}'", directive)).at_column(tokens[0].column)),
+      }.map_err(|err| err.at_line(number, line))?;
+
+      ip_register = Some(register);
+      continue;
+    }
+
+    let label = tokens.first().and_then(|t| RE_LABEL.captures(t.text).map(|c| (t.column, c)));
+    if let Some((column, capture)) = label {
+      let name = capture.get(1).map_or("", |m| m.as_str());
+      if RE_REGISTER.is_match(name) {
+        Err(ParseError::new(&format!("Label '{}' looks like a register", name)).at_column(column).at_line(number, line))?
+      }
+      if labels.insert(name, lines.len()).is_some() {
+        Err(ParseError::new(&format!("Label '{}' is defined twice", name)).at_column(column).at_line(number, line))?
+      }
+      tokens.remove(0);
+    }
+
+    if !tokens.is_empty() {
+      lines.push((number, line, tokens));
+    }
+  }
+
+  let instructions = lines
+    .iter()
+    .map(|(number, line, tokens)| {
+      parse_instruction(tokens, &labels, register_count).map_err(|err| err.at_line(*number, line))
+    })
+    .collect::<Result<Vec<Instruction<W>>, ParseError>>()?;
+
+  Ok(Program::new(ip_register, instructions))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_error(input: &str) -> String {
+    parse::<i64>(input, 6).unwrap_err().to_string()
+  }
+
+  #[test]
+  fn parses_the_puzzle_syntax() {
+    let program = parse::<i64>("#ip 0\nseti 5 0 1\naddr 1 2 3\n", 6).unwrap();
+    assert_eq!(program.ip_register, Some(0));
+    assert_eq!(program.instructions, vec![
+      Instruction::new(Opcode::Seti, 5, 0, 1),
+      Instruction::new(Opcode::Addr, 1, 2, 3),
+    ]);
+  }
+
+  #[test]
+  fn programs_round_trip() {
+    let input = include_str!("../day19/data/input.txt");
+    let program = parse::<i128>(input, 6).unwrap();
+    assert_eq!(parse::<i128>(&program.to_string(), 6).unwrap(), program);
+  }
+
+  #[test]
+  fn understands_comments_labels_and_register_names() {
+    let input = "#ip r5 ; bind the ip\n\
+                 start:\n  seti 0 0 r1\n\
+                 loop: addi r1 1 r1 ; count\n\
+                 gtri r1 9 r2\n\
+                 addr r2 r5 r5\n\
+                 seti loop-1 0 r5\n\
+                 seti start 0 r0\n";
+    let program = parse::<i64>(input, 6).unwrap();
+
+    assert_eq!(program.ip_register, Some(5));
+    assert_eq!(program.instructions[1], Instruction::new(Opcode::Addi, 1, 1, 1));
+    assert_eq!(program.instructions[4], Instruction::new(Opcode::Seti, 0, 0, 5));
+    assert_eq!(program.instructions[5], Instruction::new(Opcode::Seti, 0, 0, 0));
+  }
+
+  #[test]
+  fn errors_point_to_line_and_column() {
+    assert_eq!(parse_error("#ip 0\nseti 1 2 3\naddx 1 2 3"), "line 3, column 1: Unknown opcode 'addx' in \"addx 1 2 3\"");
+    assert_eq!(parse_error("seti 1 2 r7"), "line 1, column 10: Register 7 does not exist in \"seti 1 2 r7\"");
+    assert_eq!(parse_error("addi r1 r2 r3"), "line 1, column 9: Expected a value but found register 'r2' in \"addi r1 r2 r3\"");
+    assert_eq!(parse_error("addr 1 x 3"), "line 1, column 8: Expected a register but found 'x' in \"addr 1 x 3\"");
+    assert_eq!(parse_error("seti nowhere 0 1"), "line 1, column 6: Unknown label 'nowhere' in \"seti nowhere 0 1\"");
+  }
+
+  #[test]
+  fn operand_counts_are_checked() {
+    assert_eq!(parse_error("seti 1 2"), "line 1, column 9: seti expects 3 operands but found 2 in \"seti 1 2\"");
+    assert_eq!(parse_error("seti 1 2 3 4"), "line 1, column 12: Unexpected '4' in \"seti 1 2 3 4\"");
+  }
+
+  #[test]
+  fn directives_are_checked() {
+    assert_eq!(parse_error("#ip 6"), "line 1, column 5: Register 6 does not exist in \"#ip 6\"");
+    assert_eq!(parse_error("#ip"), "line 1, column 4: Expected a register after #ip in \"#ip\"");
+    assert_eq!(parse_error("#ip 1\n#ip 2"), "line 2, column 1: Instruction pointer is bound twice in \"#ip 2\"");
+    assert_eq!(parse_error("#jp 1"), "line 1, column 1: Unknown directive '#jp' in \"#jp 1\"");
+  }
+
+  #[test]
+  fn labels_are_checked() {
+    assert_eq!(parse_error("a: seti 0 0 0\na: seti 0 0 0"), "line 2, column 1: Label 'a' is defined twice in \"a: seti 0 0 0\"");
+    assert_eq!(parse_error("r1: seti 0 0 0"), "line 1, column 1: Label 'r1' looks like a register in \"r1: seti 0 0 0\"");
+  }
+}
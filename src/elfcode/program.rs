@@ -0,0 +1,50 @@
+use std::fmt;
+use super::opcode::Opcode;
+use super::word::Word;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction<W: Word> {
+  pub opcode: Opcode,
+  pub a: W,
+  pub b: W,
+  pub c: W,
+}
+
+impl<W: Word> Instruction<W> {
+  pub fn new(opcode: Opcode, a: W, b: W, c: W) -> Instruction<W> {
+    Instruction { opcode, a, b, c }
+  }
+}
+
+impl<W: Word> fmt::Display for Instruction<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {} {} {}", self.opcode, self.a, self.b, self.c)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program<W: Word> {
+  // The register the instruction pointer is bound to by an `#ip` directive.
+  pub ip_register: Option<usize>,
+  pub instructions: Vec<Instruction<W>>,
+}
+
+impl<W: Word> Program<W> {
+  pub fn new(ip_register: Option<usize>, instructions: Vec<Instruction<W>>) -> Program<W> {
+    Program { ip_register, instructions }
+  }
+}
+
+impl<W: Word> fmt::Display for Program<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if let Some(register) = self.ip_register {
+      writeln!(f, "#ip {}", register)?;
+    }
+
+    for instruction in &self.instructions {
+      writeln!(f, "{}", instruction)?;
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,325 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use super::super::utils::{numbered_lines, parse_number, read_file, Error, ParseError};
+use super::machine::Machine;
+use super::program::Program;
+use super::word::Word;
+
+// Everything that changes while a machine runs a program. The bound
+// register and the arithmetic mode come with the machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot<W: Word> {
+  pub registers: Vec<W>,
+  pub ip: W,
+  pub cycles: u64,
+}
+
+impl<W: Word> Machine<W> {
+  pub fn snapshot(&self) -> Snapshot<W> {
+    Snapshot { registers: self.registers.clone(), ip: self.ip, cycles: self.cycles }
+  }
+
+  pub fn restore(&mut self, snapshot: &Snapshot<W>) -> Result<(), Error> {
+    if snapshot.registers.len() != self.registers.len() {
+      let what = format!("The snapshot has {} registers, the machine has {}", snapshot.registers.len(), self.registers.len());
+      Err(Error::new(&what))?
+    }
+
+    self.registers.copy_from_slice(&snapshot.registers);
+    self.ip = snapshot.ip;
+    self.cycles = snapshot.cycles;
+    Ok(())
+  }
+}
+
+impl<W: Word> Snapshot<W> {
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    match std::fs::write(path.as_ref(), self.to_string()) {
+      Ok(_) => Ok(()),
+      Err(source) => Err(Error::Io { path: path.as_ref().to_path_buf(), source }),
+    }
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Snapshot<W>, Error> {
+    Ok(read_file(path)?.parse()?)
+  }
+}
+
+// One field per line:
+//
+//   ip 28
+//   cycles 1845
+//   registers 0 12213578 1 0 12213578 0
+impl<W: Word> fmt::Display for Snapshot<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let registers = self.registers.iter().map(|r| r.to_string()).collect::<Vec<String>>();
+    writeln!(f, "ip {}", self.ip)?;
+    writeln!(f, "cycles {}", self.cycles)?;
+    writeln!(f, "registers {}", registers.join(" "))
+  }
+}
+
+impl<W: Word> FromStr for Snapshot<W> {
+  type Err = ParseError;
+
+  fn from_str(s: &str) -> Result<Snapshot<W>, ParseError> {
+    let (mut ip, mut cycles, mut registers) = (None, None, None);
+
+    for (number, line) in numbered_lines(s) {
+      let mut words = line.split_whitespace();
+      let parsed = match (words.next(), words.next(), words.next()) {
+        (Some("ip"), Some(value), None) if ip.is_none() => parse_number(value).map(|v| ip = Some(v)),
+        (Some("cycles"), Some(value), None) if cycles.is_none() => parse_number(value).map(|v| cycles = Some(v)),
+        (Some("registers"), _, _) if registers.is_none() => {
+          line.split_whitespace().skip(1).map(parse_number).collect::<Result<Vec<W>, ParseError>>().map(|v| registers = Some(v))
+        },
+        _ => Err(ParseError::new("Expected one of ip, cycles or registers, each once")),
+      };
+      parsed.map_err(|err| err.at_line(number, line))?;
+    }
+
+    match (ip, cycles, registers) {
+      (Some(ip), Some(cycles), Some(registers)) => Ok(Snapshot { registers, ip, cycles }),
+      _ => Err(ParseError::new("A snapshot needs the ip, cycles and registers")),
+    }
+  }
+}
+
+// Recent states of a machine, to step backwards. Keeping every state
+// would take a lot of memory for long runs, so only every `interval`
+// cycles a checkpoint is kept, and going back restores the checkpoint
+// before the target and runs the program up to it again. Once `capacity`
+// checkpoints are kept, the oldest one makes room and its registers are
+// reused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History<W: Word> {
+  checkpoints: VecDeque<Snapshot<W>>,
+  capacity: usize,
+  interval: u64,
+}
+
+impl<W: Word> History<W> {
+  pub fn new(capacity: usize, interval: u64) -> History<W> {
+    History { checkpoints: VecDeque::with_capacity(capacity), capacity, interval: interval.max(1) }
+  }
+
+  pub fn len(&self) -> usize {
+    self.checkpoints.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.checkpoints.is_empty()
+  }
+
+  // The cycle count of the oldest state that can be gone back to.
+  pub fn earliest(&self) -> Option<u64> {
+    self.checkpoints.front().map(|c| c.cycles)
+  }
+
+  // Changing the machine by hand makes the checkpoints useless, they
+  // would no longer run into the current state.
+  pub fn clear(&mut self) {
+    self.checkpoints.clear();
+  }
+
+  // Called before every step, keeps a checkpoint now and then.
+  pub fn record(&mut self, machine: &Machine<W>) {
+    if self.checkpoints.back().is_some_and(|c| machine.cycles < c.cycles + self.interval) {
+      return;
+    }
+    if self.checkpoints.len() < self.capacity {
+      self.checkpoints.push_back(machine.snapshot());
+      return;
+    }
+
+    if let Some(mut oldest) = self.checkpoints.pop_front() {
+      oldest.registers.clone_from(&machine.registers);
+      oldest.ip = machine.ip;
+      oldest.cycles = machine.cycles;
+      self.checkpoints.push_back(oldest);
+    }
+  }
+
+  // Brings the machine to the state it had after `cycles` cycles. Returns
+  // false and leaves the machine alone if that is before the history.
+  pub fn travel(&mut self, machine: &mut Machine<W>, program: &Program<W>, cycles: u64) -> Result<bool, Error> {
+    let index = match self.checkpoints.iter().rposition(|c| c.cycles <= cycles) {
+      Some(v) => v,
+      None => return Ok(false),
+    };
+
+    machine.restore(&self.checkpoints[index])?;
+    self.checkpoints.truncate(index + 1);
+    while machine.cycles < cycles {
+      if !machine.step(program)? {
+        Err(Error::new(&format!("The program halted before cycle {}", cycles)))?
+      }
+    }
+
+    Ok(true)
+  }
+
+  // Goes back `steps` cycles, or as far as the history reaches. Returns
+  // how many steps were undone.
+  pub fn step_back(&mut self, machine: &mut Machine<W>, program: &Program<W>, steps: u64) -> Result<u64, Error> {
+    let now = machine.cycles;
+    let target = match self.earliest() {
+      Some(earliest) => now.saturating_sub(steps).max(earliest).min(now),
+      None => return Ok(0),
+    };
+
+    self.travel(machine, program, target)?;
+    Ok(now - machine.cycles)
+  }
+
+  // Goes back to right before the instruction at `address` last executed.
+  // Returns how many steps were undone, None if that was before the
+  // history and the machine was left alone.
+  pub fn back_to(&mut self, machine: &mut Machine<W>, program: &Program<W>, address: usize) -> Result<Option<u64>, Error> {
+    let now = machine.cycles;
+    let mut end = now;
+
+    // Replays the stretch after each checkpoint, latest first.
+    for index in (0..self.checkpoints.len()).rev() {
+      if self.checkpoints[index].cycles >= end {
+        continue;
+      }
+
+      let mut probe = machine.clone();
+      probe.restore(&self.checkpoints[index])?;
+      let mut found = None;
+      while probe.cycles < end {
+        if probe.ip.to_index() == Some(address) {
+          found = Some(probe.cycles);
+        }
+        if !probe.step(program)? {
+          break;
+        }
+      }
+
+      if let Some(cycles) = found {
+        self.travel(machine, program, cycles)?;
+        return Ok(Some(now - cycles));
+      }
+      end = self.checkpoints[index].cycles;
+    }
+
+    Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  const COUNTER: &str = "#ip 5\n\
+                         seti 0 0 1\n\
+                         addi 1 1 1\n\
+                         gtri 1 9 3\n\
+                         addr 3 5 5\n\
+                         seti 0 0 5\n\
+                         setr 1 0 0\n";
+
+  #[test]
+  fn snapshots_restore_the_machine() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    for _ in 0..7 {
+      machine.step(&program).unwrap();
+    }
+    let snapshot = machine.snapshot();
+    let expected = machine.clone();
+
+    machine.run(&program).unwrap();
+    machine.restore(&snapshot).unwrap();
+    assert_eq!(machine, expected);
+    assert!(Machine::<i64>::new(4).restore(&snapshot).is_err());
+  }
+
+  #[test]
+  fn snapshots_round_trip_through_text() {
+    let snapshot = Snapshot { registers: vec![0, -12213578, 1 << 80, 0], ip: 28, cycles: 1845 };
+    let text = snapshot.to_string();
+
+    assert_eq!(text, "ip 28\ncycles 1845\nregisters 0 -12213578 1208925819614629174706176 0\n");
+    assert_eq!(text.parse::<Snapshot<i128>>().unwrap(), snapshot);
+  }
+
+  #[test]
+  fn broken_snapshots_are_rejected() {
+    let err = "ip 28\ncycles -1\nregisters 0\n".parse::<Snapshot<i64>>().unwrap_err();
+    assert_eq!(err.to_string(), "line 2: Unable to parse number '-1' in \"cycles -1\"");
+    let err = "ip 28\nip 29\n".parse::<Snapshot<i64>>().unwrap_err();
+    assert_eq!(err.to_string(), "line 2: Expected one of ip, cycles or registers, each once in \"ip 29\"");
+    assert!("ip 28\ncycles 3\n".parse::<Snapshot<i64>>().is_err());
+  }
+
+  #[test]
+  fn snapshots_are_saved_to_disk() {
+    let path = std::env::temp_dir().join(format!("y2018-snapshot-{}.txt", std::process::id()));
+    let snapshot = Snapshot { registers: vec![3, 1, 4, 1, 5, 9], ip: 2, cycles: 6 };
+    snapshot.save(&path).unwrap();
+    let loaded = Snapshot::<i64>::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.unwrap(), snapshot);
+    assert!(Snapshot::<i64>::load("does/not/exist.txt").is_err());
+  }
+
+  // Runs the counter, recording the state before every step.
+  fn recorded(history: &mut History<i64>, program: &Program<i64>, cycles: u64) -> (Machine<i64>, Vec<Snapshot<i64>>) {
+    let mut machine = Machine::for_program(program, 6).unwrap();
+    let mut states = vec![];
+    while machine.cycles < cycles {
+      states.push(machine.snapshot());
+      history.record(&machine);
+      machine.step(program).unwrap();
+    }
+    states.push(machine.snapshot());
+    (machine, states)
+  }
+
+  #[test]
+  fn the_history_keeps_checkpoints() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let mut history = History::new(3, 4);
+    let (mut machine, states) = recorded(&mut history, &program, 30);
+
+    // Checkpoints at 0, 4, ..., 28, only the last three are kept.
+    assert_eq!((history.len(), history.earliest()), (3, Some(20)));
+
+    assert_eq!(history.step_back(&mut machine, &program, 1).unwrap(), 1);
+    assert_eq!(machine.snapshot(), states[29]);
+    assert_eq!(history.step_back(&mut machine, &program, 7).unwrap(), 7);
+    assert_eq!(machine.snapshot(), states[22]);
+    assert_eq!(history.step_back(&mut machine, &program, 10).unwrap(), 2);
+    assert_eq!(machine.snapshot(), states[20]);
+    assert_eq!(history.step_back(&mut machine, &program, 1).unwrap(), 0);
+
+    assert!(history.travel(&mut machine, &program, 25).unwrap());
+    assert_eq!(machine.snapshot(), states[25]);
+    assert!(!history.travel(&mut machine, &program, 3).unwrap());
+    assert_eq!(machine.snapshot(), states[25]);
+  }
+
+  #[test]
+  fn goes_back_to_the_last_time_an_instruction_ran() {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let mut history = History::new(100, 3);
+    let (mut machine, _) = recorded(&mut history, &program, 39);
+    assert_eq!(machine.ip, 3);
+
+    // The counter loops through 1 to 4, and reaches 3 for the last time.
+    assert_eq!(history.back_to(&mut machine, &program, 2).unwrap(), Some(1));
+    assert_eq!((machine.ip, machine.registers[1], machine.cycles), (2, 10, 38));
+    assert_eq!(history.back_to(&mut machine, &program, 2).unwrap(), Some(4));
+    assert_eq!((machine.ip, machine.registers[1], machine.cycles), (2, 9, 34));
+    assert_eq!(history.back_to(&mut machine, &program, 0).unwrap(), Some(34));
+    assert_eq!(machine.cycles, 0);
+    assert_eq!(history.back_to(&mut machine, &program, 5).unwrap(), None);
+    assert_eq!(machine.cycles, 0);
+  }
+}
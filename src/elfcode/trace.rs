@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as FmtWrite;
+use std::io;
+use super::super::utils::Error;
+use super::machine::Machine;
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+// Stops the trace (but not the program) once one of the limits is reached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TraceLimits {
+  pub cycles: Option<u64>,
+  pub bytes: Option<u64>,
+}
+
+// Writes one line per executed instruction:
+//
+//     <cycle> <ip> <instruction> <registers before> <registers after>
+//
+// with the registers separated by commas, e.g.
+// `3 2 addi 1 1 1 0,0,0,0,0,2 0,1,0,0,0,2`.
+pub struct Trace<O: io::Write> {
+  out: O,
+  limits: TraceLimits,
+  written: u64,
+  pub truncated: bool,
+}
+
+fn join<W: Word>(registers: &[W]) -> String {
+  registers.iter().map(|r| r.to_string()).collect::<Vec<String>>().join(",")
+}
+
+impl<O: io::Write> Trace<O> {
+  pub fn new(out: O, limits: TraceLimits) -> Trace<O> {
+    Trace { out, limits, written: 0, truncated: false }
+  }
+
+  pub fn is_active(&self) -> bool {
+    !self.truncated
+  }
+
+  fn record<W: Word>(&mut self, cycle: u64, ip: W, instruction: &Instruction<W>, before: &[W], after: &[W]) -> io::Result<()> {
+    if self.truncated {
+      return Ok(());
+    }
+
+    let line = format!("{} {} {} {} {}\n", cycle, ip, instruction, join(before), join(after));
+    let over_cycles = self.limits.cycles.is_some_and(|max| cycle > max);
+    let over_bytes = self.limits.bytes.is_some_and(|max| self.written + line.len() as u64 > max);
+    if over_cycles || over_bytes {
+      self.truncated = true;
+      return writeln!(self.out, "# truncated after {} cycles", cycle - 1);
+    }
+
+    self.written += line.len() as u64;
+    self.out.write_all(line.as_bytes())
+  }
+
+  pub fn finish(mut self) -> io::Result<O> {
+    self.out.flush()?;
+    Ok(self.out)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotLoop {
+  // The loop covers the instructions from `start` to `end` (inclusive),
+  // its back edge jumps from `end` to `start`.
+  pub start: usize,
+  pub end: usize,
+  pub iterations: u64,
+  // Cycles spent on instructions inside the loop, nested loops included.
+  pub cycles: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+  // How often every instruction was executed.
+  pub counts: Vec<u64>,
+  // How often control went from an instruction back to an earlier one (or
+  // the same one).
+  pub back_edges: BTreeMap<(usize, usize), u64>,
+  pub cycles: u64,
+  pub halted: bool,
+}
+
+impl Profile {
+  pub fn new(len: usize) -> Profile {
+    Profile { counts: vec![0; len], back_edges: BTreeMap::new(), cycles: 0, halted: false }
+  }
+
+  // The loops formed by the back edges, the most iterations first.
+  pub fn hot_loops(&self) -> Vec<HotLoop> {
+    let mut loops = self.back_edges
+      .iter()
+      .map(|(&(end, start), &iterations)| HotLoop {
+        start,
+        end,
+        iterations,
+        cycles: self.counts[start..=end].iter().sum(),
+      })
+      .collect::<Vec<HotLoop>>();
+
+    loops.sort_by(|a, b| b.iterations.cmp(&a.iterations).then(b.cycles.cmp(&a.cycles)));
+    loops
+  }
+
+  fn share(&self, cycles: u64) -> f64 {
+    if self.cycles == 0 {
+      0.0
+    } else {
+      cycles as f64 * 100.0 / self.cycles as f64
+    }
+  }
+
+  pub fn report<W: Word>(&self, program: &Program<W>) -> String {
+    let mut out = String::new();
+    let end = if self.halted { "halted" } else { "stopped" };
+    let _ = writeln!(out, "Executed {} cycles, the program {}", self.cycles, end);
+
+    let _ = writeln!(out, "\nHot loops:");
+    for hot in self.hot_loops() {
+      let _ = writeln!(out, "  ip {:>3} to {:>3}  {:>12} iterations  {:>14} cycles  {:>5.1}%",
+        hot.start, hot.end, hot.iterations, hot.cycles, self.share(hot.cycles));
+    }
+
+    let _ = writeln!(out, "\n   ip  {:>14}  {:>6}  instruction", "count", "share");
+    for (ip, (count, instruction)) in self.counts.iter().zip(&program.instructions).enumerate() {
+      let _ = writeln!(out, "  {:>3}  {:>14}  {:>5.1}%  {}", ip, count, self.share(*count), instruction);
+    }
+
+    out
+  }
+}
+
+// Runs the program to its end or for at most `max_cycles` and counts how
+// often every instruction runs. Every step is also written to the trace, if
+// there is one.
+pub fn profile<W, O>(machine: &mut Machine<W>, program: &Program<W>, mut trace: Option<&mut Trace<O>>, max_cycles: Option<u64>) -> Result<Profile, Error>
+where W: Word, O: io::Write {
+  let mut profile = Profile::new(program.instructions.len());
+  let max_cycles = max_cycles.unwrap_or(u64::MAX);
+
+  while profile.cycles < max_cycles {
+    let ip = match machine.ip.to_index().filter(|&ip| ip < program.instructions.len()) {
+      Some(v) => v,
+      None => {
+        profile.halted = true;
+        break;
+      }
+    };
+
+    let before = match &trace {
+      Some(t) if t.is_active() => Some((machine.ip, machine.registers.clone())),
+      _ => None,
+    };
+
+    machine.step(program)?;
+    profile.counts[ip] += 1;
+    profile.cycles += 1;
+
+    if let Some(next) = machine.ip.to_index() {
+      if next <= ip {
+        *profile.back_edges.entry((ip, next)).or_insert(0) += 1;
+      }
+    }
+
+    if let (Some(trace), Some((old_ip, registers))) = (trace.as_mut(), before) {
+      let instruction = &program.instructions[ip];
+      trace.record(machine.cycles, old_ip, instruction, &registers, &machine.registers)
+        .map_err(|err| Error::new(&format!("Unable to write the trace: {}", err)))?;
+    }
+  }
+
+  Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::parser::parse;
+
+  const COUNTER: &str = "#ip 5\n\
+                         seti 0 0 1\n\
+                         addi 1 1 1\n\
+                         gtri 1 2 3\n\
+                         addr 3 5 5\n\
+                         seti 0 0 5\n\
+                         setr 1 0 0\n";
+
+  fn run(limits: TraceLimits, max_cycles: Option<u64>) -> (Profile, String) {
+    let program = parse::<i64>(COUNTER, 6).unwrap();
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    let mut trace = Trace::new(vec![], limits);
+    let profile = profile(&mut machine, &program, Some(&mut trace), max_cycles).unwrap();
+
+    (profile, String::from_utf8(trace.finish().unwrap()).unwrap())
+  }
+
+  #[test]
+  fn counts_instructions_and_loops() {
+    let (profile, _) = run(TraceLimits::default(), None);
+
+    assert_eq!(profile.counts, vec![1, 3, 3, 3, 2, 1]);
+    assert_eq!(profile.cycles, 13);
+    assert!(profile.halted);
+    assert_eq!(profile.hot_loops(), vec![HotLoop { start: 1, end: 4, iterations: 2, cycles: 11 }]);
+  }
+
+  #[test]
+  fn traces_every_step() {
+    let (_, trace) = run(TraceLimits::default(), None);
+    let lines = trace.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines.len(), 13);
+    assert_eq!(lines[0], "1 0 seti 0 0 1 0,0,0,0,0,0 0,0,0,0,0,0");
+    assert_eq!(lines[1], "2 1 addi 1 1 1 0,0,0,0,0,0 0,1,0,0,0,1");
+    assert_eq!(lines[12], "13 5 setr 1 0 0 0,3,0,1,0,4 3,3,0,1,0,5");
+  }
+
+  #[test]
+  fn traces_are_capped() {
+    let (profile, trace) = run(TraceLimits { cycles: Some(3), bytes: None }, None);
+    assert_eq!(trace.lines().count(), 4);
+    assert!(trace.ends_with("# truncated after 3 cycles\n"));
+    assert_eq!(profile.cycles, 13);
+
+    let (_, trace) = run(TraceLimits { cycles: None, bytes: Some(100) }, None);
+    assert!(trace.len() <= 100 + "# truncated after 99 cycles\n".len());
+    assert!(trace.contains("# truncated"));
+  }
+
+  #[test]
+  fn runs_can_be_stopped() {
+    let (profile, trace) = run(TraceLimits::default(), Some(5));
+    assert_eq!(profile.cycles, 5);
+    assert!(!profile.halted);
+    assert_eq!(trace.lines().count(), 5);
+  }
+
+  #[test]
+  fn reports_the_day19_inner_loop() {
+    let program = parse::<i64>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    let profile = profile::<_, Vec<u8>>(&mut machine, &program, None, Some(100_000)).unwrap();
+
+    let hottest = &profile.hot_loops()[0];
+    assert_eq!((hottest.start, hottest.end), (3, 11));
+    assert!(profile.report(&program).contains("Hot loops:\n  ip   3 to  11"));
+  }
+}
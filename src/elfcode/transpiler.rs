@@ -0,0 +1,418 @@
+use std::fmt::Write;
+use super::super::utils::Error;
+use super::opcode::{Opcode, Operand};
+use super::program::{Instruction, Program};
+use super::word::Word;
+
+// What an operand turns into in the generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value<W: Word> {
+  Register(usize),
+  Literal(W),
+}
+
+impl<W: Word> Value<W> {
+  fn code(self) -> String {
+    match self {
+      Value::Register(r) => format!("r{}", r),
+      Value::Literal(v) if v < W::ZERO => format!("({})", v),
+      Value::Literal(v) => v.to_string(),
+    }
+  }
+}
+
+// The name of the word type in the generated code, e.g. `i128`.
+fn type_name<W: Word>() -> &'static str {
+  let name = std::any::type_name::<W>();
+  name.rsplit("::").next().unwrap_or(name)
+}
+
+// Sums and products go through the `apply!` macro of the generated code
+// instead, since they depend on the arithmetic mode of the machine.
+fn operator(opcode: Opcode) -> Option<&'static str> {
+  match opcode {
+    Opcode::Banr | Opcode::Bani => Some("&"),
+    Opcode::Borr | Opcode::Bori => Some("|"),
+    _ => None,
+  }
+}
+
+struct Transpiler<'a, W: Word> {
+  program: &'a Program<W>,
+  register_count: usize,
+}
+
+impl<'a, W: Word> Transpiler<'a, W> {
+  fn register(&self, value: W) -> Result<usize, Error> {
+    match value.to_index() {
+      Some(r) if r < self.register_count => Ok(r),
+      _ => Err(Error::new(&format!("Register {} does not exist", value))),
+    }
+  }
+
+  // Reading the bound register gives the address of the instruction.
+  fn operand(&self, kind: Operand, value: W, address: W) -> Result<Value<W>, Error> {
+    Ok(match kind {
+      Operand::Register if self.program.ip_register == value.to_index() => Value::Literal(address),
+      Operand::Register => Value::Register(self.register(value)?),
+      Operand::Immediate => Value::Literal(value),
+      Operand::Ignored => Value::Literal(W::ZERO),
+    })
+  }
+
+  // The expression computing register C, without the neutral and absorbing
+  // elements so the generated code reads (and lints) like handwritten code.
+  // Sums and products are left to `apply!` even for constants, since a width
+  // truncates results that don't fit. Only a product with zero is the same
+  // in every arithmetic mode.
+  fn expression(&self, opcode: Opcode, a: Value<W>, b: Value<W>) -> String {
+    use self::Value::Literal as L;
+
+    let word = type_name::<W>();
+    match (opcode, a, b) {
+      (Opcode::Setr, _, _) | (Opcode::Seti, _, _) => a.code(),
+      (Opcode::Mulr, _, L(z)) | (Opcode::Muli, _, L(z)) | (Opcode::Mulr, L(z), _) if z == W::ZERO => "0".to_string(),
+      (Opcode::Addr, _, _) | (Opcode::Addi, _, _) | (Opcode::Mulr, _, _) | (Opcode::Muli, _, _) => {
+        format!("apply!({:?}, {}, {})", opcode, a.code(), b.code())
+      },
+      (opcode, L(a), L(b)) => L(opcode.apply(a, b)).code(),
+      (Opcode::Banr, _, L(z)) | (Opcode::Bani, _, L(z)) | (Opcode::Banr, L(z), _) if z == W::ZERO => "0".to_string(),
+      (Opcode::Borr, v, L(z)) | (Opcode::Bori, v, L(z)) | (Opcode::Borr, L(z), v) if z == W::ZERO => v.code(),
+      (Opcode::Gtir, _, _) | (Opcode::Gtri, _, _) | (Opcode::Gtrr, _, _) => format!("{}::from({} > {})", word, a.code(), b.code()),
+      (Opcode::Eqir, _, _) | (Opcode::Eqri, _, _) | (Opcode::Eqrr, _, _) => format!("{}::from({} == {})", word, a.code(), b.code()),
+      (opcode, _, _) => format!("{} {} {}", a.code(), operator(opcode).unwrap_or("?"), b.code()),
+    }
+  }
+
+  fn assignment(&self, target: Value<W>, opcode: Opcode, a: Value<W>, b: Value<W>) -> String {
+    let expression = self.expression(opcode, a, b);
+
+    // `r1 = r1 | r2` is written as `r1 |= r2`.
+    if let Some(op) = operator(opcode) {
+      if expression == format!("{} {} {}", a.code(), op, b.code()) && (a == target || b == target) {
+        let other = if a == target { b } else { a };
+        return format!("{} {}= {};", target.code(), op, other.code());
+      }
+    }
+
+    if expression == target.code() {
+      String::new()
+    } else {
+      format!("{} = {};", target.code(), expression)
+    }
+  }
+
+  fn arm(&self, out: &mut String, address: usize, instruction: &Instruction<W>) -> Result<(), Error> {
+    let here = match W::from_index(address) {
+      Some(v) => v,
+      None => Err(Error::new("The program is too long for the word size"))?
+    };
+
+    let (kind_a, kind_b) = instruction.opcode.operands();
+    let a = self.operand(kind_a, instruction.a, here)?;
+    let b = self.operand(kind_b, instruction.b, here)?;
+    let c = self.register(instruction.c)?;
+
+    let _ = writeln!(out, "      {} => {{", address);
+    let _ = writeln!(out, "        // {}", instruction);
+    if self.program.ip_register == Some(c) {
+      // A jump, the bound register is written back when the program stops.
+      let target = self.expression(instruction.opcode, a, b);
+      let next = match target.parse::<W>().ok().and_then(|v| v.checked_add(W::ONE)) {
+        Some(v) => Value::Literal(v).code(),
+        None => format!("next!({})", target),
+      };
+      let _ = writeln!(out, "        ip = {};", next);
+    } else {
+      let assignment = self.assignment(Value::Register(c), instruction.opcode, a, b);
+      if !assignment.is_empty() {
+        let _ = writeln!(out, "        {}", assignment);
+      }
+      let _ = writeln!(out, "        ip = {};", address + 1);
+    }
+    let _ = writeln!(out, "      }},");
+
+    Ok(())
+  }
+
+  fn transpile(&self, source: &str) -> Result<String, Error> {
+    if let Some(register) = self.program.ip_register.filter(|&r| r >= self.register_count) {
+      Err(Error::new(&format!("Register {} does not exist", register)))?
+    }
+
+    let mut arms = String::new();
+    for (address, instruction) in self.program.instructions.iter().enumerate() {
+      self.arm(&mut arms, address, instruction)
+        .map_err(|err| Error::new(&format!("ip {}: {}", address, err)))?;
+    }
+
+    // Registers that are never written don't have to be mutable.
+    let written = |r: usize| {
+      self.program.ip_register == Some(r) || self.program.instructions.iter().any(|i| i.c.to_index() == Some(r))
+    };
+    let word = type_name::<W>();
+    let registers = (0..self.register_count).map(|r| format!("r{}", r)).collect::<Vec<String>>();
+
+    // Jumps to computed addresses need the `next!` macro.
+    let jumps = arms.contains("next!(");
+
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated from {} by `elfcode::transpiler`, don't edit.", source);
+    if jumps {
+      let _ = writeln!(out, "use super::super::elfcode::arithmetic::{{jump_overflow, overflow}};");
+    } else {
+      let _ = writeln!(out, "use super::super::elfcode::arithmetic::overflow;");
+    }
+    let _ = writeln!(out, "use super::super::elfcode::compiled::Exit;");
+    let _ = writeln!(out, "use super::super::elfcode::{{Machine, Opcode}};");
+    let _ = writeln!(out, "use super::super::utils::Error;");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "// Behaves like `Compiled::run_until` without a breakpoint.");
+    let _ = writeln!(out, "pub fn run(machine: &mut Machine<{}>, max_cycles: Option<u64>) -> Result<Exit, Error> {{", word);
+    let _ = writeln!(out, "  if machine.registers.len() != {} || machine.ip_register != {:?} {{", self.register_count, self.program.ip_register);
+    let _ = writeln!(out, "    Err(Error::new(\"The machine doesn't fit the program\"))?");
+    let _ = writeln!(out, "  }}");
+    let _ = writeln!(out);
+    for r in 0..self.register_count {
+      let _ = writeln!(out, "  let {}r{} = machine.registers[{}];", if written(r) { "mut " } else { "" }, r, r);
+    }
+    let _ = writeln!(out, "  let mut ip = machine.ip;");
+    let _ = writeln!(out, "  let limit = max_cycles.map_or(u64::MAX, |max| machine.cycles.saturating_add(max));");
+    let _ = writeln!(out, "  let mut cycles = machine.cycles;");
+    let _ = writeln!(out, "  let arithmetic = machine.arithmetic;");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "  // Sums and products follow the arithmetic mode of the machine, an");
+    let _ = writeln!(out, "  // overflow stops the program at the instruction.");
+    let _ = writeln!(out, "  macro_rules! apply {{");
+    let _ = writeln!(out, "    ($opcode:ident, $a:expr, $b:expr) => {{");
+    let _ = writeln!(out, "      match arithmetic.apply(Opcode::$opcode, $a, $b) {{");
+    let _ = writeln!(out, "        Some(v) => v,");
+    let _ = writeln!(out, "        None => break Err(Error::new(&format!(\"ip {{}}: {{}}\", ip, overflow(Opcode::$opcode, $a, $b)))),");
+    let _ = writeln!(out, "      }}");
+    let _ = writeln!(out, "    }};");
+    let _ = writeln!(out, "  }}");
+    let _ = writeln!(out);
+    if jumps {
+      let _ = writeln!(out, "  // The instruction after a jump, a jump past the largest word stops the");
+      let _ = writeln!(out, "  // program at the instruction whatever the arithmetic mode.");
+      let _ = writeln!(out, "  macro_rules! next {{");
+      let _ = writeln!(out, "    ($target:expr) => {{{{");
+      let _ = writeln!(out, "      let target: {} = $target;", word);
+      let _ = writeln!(out, "      match target.checked_add(1) {{");
+      let _ = writeln!(out, "        Some(v) => v,");
+      let _ = writeln!(out, "        None => break Err(Error::new(&format!(\"ip {{}}: {{}}\", ip, jump_overflow(target)))),");
+      let _ = writeln!(out, "      }}");
+      let _ = writeln!(out, "    }}}};");
+      let _ = writeln!(out, "  }}");
+      let _ = writeln!(out);
+    }
+    let _ = writeln!(out, "  let exit = loop {{");
+    let _ = writeln!(out, "    if cycles >= limit {{");
+    let _ = writeln!(out, "      break Ok(Exit::Limit);");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "    match ip {{");
+    out.push_str(&arms);
+    let _ = writeln!(out, "      _ => break Ok(Exit::Halted),");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "    cycles += 1;");
+    let _ = writeln!(out, "  }};");
+    let _ = writeln!(out);
+    if let Some(r) = self.program.ip_register {
+      // An instruction that overflowed never finished.
+      let _ = writeln!(out, "  if exit.is_err() {{");
+      let _ = writeln!(out, "    r{} = ip;", r);
+      let _ = writeln!(out, "  }} else if cycles != machine.cycles {{");
+      let _ = writeln!(out, "    r{} = ip - 1;", r);
+      let _ = writeln!(out, "  }}");
+    }
+    let _ = writeln!(out, "  machine.registers.copy_from_slice(&[{}]);", registers.join(", "));
+    let _ = writeln!(out, "  machine.ip = ip;");
+    let _ = writeln!(out, "  machine.cycles = cycles;");
+    let _ = writeln!(out, "  exit");
+    let _ = writeln!(out, "}}");
+
+    Ok(out)
+  }
+}
+
+// Turns the program into the source of a Rust module with a `run` function
+// that works like `Compiled::run_until`: a loop matching on the instruction
+// pointer with one arm per instruction and the registers in local
+// variables. Reads of the bound register are replaced by the address. The
+// module is meant to live in a day module of this crate, `source` says where
+// the program comes from.
+pub fn transpile<W: Word>(program: &Program<W>, register_count: usize, source: &str) -> Result<String, Error> {
+  Transpiler { program, register_count }.transpile(source)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::arithmetic::Arithmetic;
+  use super::super::compiled::Exit;
+  use super::super::machine::Machine;
+  use super::super::parser::parse;
+  use super::super::super::{day19, day21};
+
+  #[test]
+  fn transpiled_code_is_fresh() {
+    let day19 = parse::<i128>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    assert_eq!(transpile(&day19, 6, "src/day19/data/input.txt").unwrap(), include_str!("../day19/transpiled.rs"),
+      "Run `cargo run -- transpile --input src/day19/data/input.txt > src/day19/transpiled.rs`");
+
+    let day21 = parse::<i128>(include_str!("../day21/data/input.txt"), 6).unwrap();
+    assert_eq!(transpile(&day21, 6, "src/day21/data/input.txt").unwrap(), include_str!("../day21/transpiled.rs"),
+      "Run `cargo run -- transpile --input src/day21/data/input.txt > src/day21/transpiled.rs`");
+  }
+
+  #[test]
+  fn instructions_become_statements() {
+    let program = parse::<i64>("#ip 1\n\
+                                addi 0 5 0\n\
+                                mulr 2 1 3\n\
+                                gtir -3 3 2\n\
+                                eqrr 0 2 0\n\
+                                setr 3 0 3\n\
+                                addr 0 1 1\n\
+                                seti 3 0 1\n", 4).unwrap();
+    let code = transpile(&program, 4, "a test").unwrap();
+
+    assert!(code.starts_with("// Generated from a test by `elfcode::transpiler`, don't edit.\n"));
+    assert!(code.contains("      0 => {\n        // addi 0 5 0\n        r0 = apply!(Addi, r0, 5);\n        ip = 1;\n      },\n"), "{}", code);
+    assert!(code.contains("        r3 = apply!(Mulr, r2, 1);\n        ip = 2;\n"), "{}", code);
+    assert!(code.contains("        r2 = i64::from((-3) > r3);\n"), "{}", code);
+    assert!(code.contains("        r0 = i64::from(r0 == r2);\n"), "{}", code);
+    assert!(code.contains("        // setr 3 0 3\n        ip = 5;\n"), "{}", code);
+    assert!(code.contains("        ip = next!(apply!(Addr, r0, 5));\n"), "{}", code);
+    assert!(code.contains("        ip = 4;\n      },\n      _ => break Ok(Exit::Halted),"), "{}", code);
+  }
+
+  #[test]
+  fn invalid_programs_are_rejected() {
+    let program = parse::<i64>("#ip 1\nseti 0 0 7\n", 8).unwrap();
+    assert_eq!(transpile(&program, 6, "").err().unwrap().to_string(), "ip 0: Register 7 does not exist");
+  }
+
+  // Runs the transpiled program and the machine side by side, both
+  // `cycles` instructions at a time.
+  fn compare<F>(program: &Program<i128>, run: F, registers: Vec<i128>, cycles: u64, rounds: usize)
+  where F: Fn(&mut Machine<i128>, Option<u64>) -> Result<Exit, Error> {
+    let mut expected = Machine::for_program(program, 6).unwrap();
+    expected.registers = registers;
+    let mut actual = expected.clone();
+
+    for _ in 0..rounds {
+      let mut halted = false;
+      for _ in 0..cycles {
+        if !expected.step(program).unwrap() {
+          halted = true;
+          break;
+        }
+      }
+
+      let exit = run(&mut actual, Some(cycles)).unwrap();
+      assert_eq!(actual, expected);
+      assert_eq!(exit == Exit::Halted, halted);
+      if halted {
+        break;
+      }
+    }
+  }
+
+  #[test]
+  fn day19_behaves_like_the_machine() {
+    let program = parse::<i128>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    compare(&program, day19::transpiled::run, vec![0; 6], 1, 100);
+    compare(&program, day19::transpiled::run, vec![0; 6], 997, 20);
+    compare(&program, day19::transpiled::run, vec![1, 0, 0, 0, 0, 0], 10_000, 5);
+    compare(&program, day19::transpiled::run, vec![0, 12, -4, 0, 3, 0], 1_000_000, 10);
+  }
+
+  #[test]
+  fn day21_behaves_like_the_machine() {
+    let program = parse::<i128>(include_str!("../day21/data/input.txt"), 6).unwrap();
+    compare(&program, day21::transpiled::run, vec![0; 6], 1, 100);
+    compare(&program, day21::transpiled::run, vec![12213578, 0, 0, 0, 0, 0], 1_000, 10);
+    compare(&program, day21::transpiled::run, vec![5, 9, 1 << 40, 0, -7, 0], 100_000, 5);
+  }
+
+  #[test]
+  fn overflows_follow_the_arithmetic_mode() {
+    // Starts in the inner loop of day 19 with a product that doesn't fit.
+    let program = parse::<i128>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    for &arithmetic in &[Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Saturating, Arithmetic::WrappingBits(64)] {
+      let mut expected = Machine::for_program(&program, 6).unwrap();
+      expected.arithmetic = arithmetic;
+      expected.ip = 3;
+      expected.registers = vec![0, 1 << 100, 1 << 70, 0, 1 << 70, 0];
+      let mut actual = expected.clone();
+
+      let expected_result = (0..1000).try_for_each(|_| expected.step(&program).map(|_| ()));
+      let actual_result = day19::transpiled::run(&mut actual, Some(1000));
+      assert_eq!(actual, expected, "{}", arithmetic);
+      assert_eq!(actual_result.err().map(|e| e.to_string()), expected_result.err().map(|e| e.to_string()));
+    }
+
+    let mut machine = Machine::for_program(&program, 6).unwrap();
+    machine.ip = 3;
+    machine.registers = vec![0, 1 << 100, 1 << 70, 0, 1 << 70, 0];
+    let err = day19::transpiled::run(&mut machine, None).unwrap_err();
+    assert!(err.to_string().starts_with("ip 3: mulr overflows: "), "{}", err);
+    assert_eq!((machine.ip, machine.cycles, machine.registers[5]), (3, 0, 3));
+  }
+
+  #[test]
+  fn jumps_past_the_largest_word_are_errors() {
+    // `addr 3 5 5` at 5 in day 19 jumps to the largest word.
+    let program = parse::<i128>(include_str!("../day19/data/input.txt"), 6).unwrap();
+    for &arithmetic in &[Arithmetic::Wrapping, Arithmetic::Checked, Arithmetic::Saturating, Arithmetic::WrappingBits(128)] {
+      let mut expected = Machine::for_program(&program, 6).unwrap();
+      expected.arithmetic = arithmetic;
+      expected.ip = 5;
+      expected.registers = vec![0, 0, 0, i128::MAX - 5, 0, 0];
+      let mut actual = expected.clone();
+
+      let expected_err = expected.run(&program).unwrap_err();
+      let actual_err = day19::transpiled::run(&mut actual, None).unwrap_err();
+      assert_eq!(actual_err.to_string(), format!("ip 5: The instruction pointer overflows: {} + 1", i128::MAX));
+      assert_eq!(actual_err.to_string(), expected_err.to_string());
+      assert_eq!(actual, expected, "{}", arithmetic);
+    }
+
+    let program = parse::<i64>("#ip 0\nseti 9223372036854775807 0 0\n", 1).unwrap();
+    let code = transpile(&program, 1, "a test").unwrap();
+    assert!(code.contains("        ip = next!(9223372036854775807);\n"), "{}", code);
+  }
+
+  #[test]
+  fn constants_follow_the_arithmetic_mode() {
+    // 0 + 200 doesn't fit into 8 bits, so the sum can't be folded.
+    let program = parse::<i64>("#ip 1\naddi 1 200 0\n", 2).unwrap();
+    let code = transpile(&program, 2, "a test").unwrap();
+    assert!(code.contains("        r0 = apply!(Addi, 0, 200);\n"), "{}", code);
+
+    type Run = fn(&mut Machine<i128>, Option<u64>) -> Result<Exit, Error>;
+    let days: [(&str, Run); 2] = [
+      (include_str!("../day19/data/input.txt"), day19::transpiled::run),
+      (include_str!("../day21/data/input.txt"), day21::transpiled::run),
+    ];
+    for (input, run) in days {
+      let program = parse::<i128>(input, 6).unwrap();
+      let mut expected = Machine::for_program(&program, 6).unwrap();
+      expected.arithmetic = Arithmetic::WrappingBits(8);
+      let mut actual = expected.clone();
+
+      let expected_result = (0..10_000).try_for_each(|_| expected.step(&program).map(|_| ()));
+      let actual_result = run(&mut actual, Some(10_000));
+      assert_eq!(actual, expected);
+      assert_eq!(actual_result.is_err(), expected_result.is_err());
+    }
+  }
+
+  #[test]
+  fn machines_have_to_fit() {
+    let mut machine = Machine::new(4);
+    assert!(day19::transpiled::run(&mut machine, None).is_err());
+  }
+}
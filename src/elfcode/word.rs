@@ -0,0 +1,80 @@
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::num::ParseIntError;
+use std::ops::{Add, BitAnd, BitOr, Div, Mul, Rem, Sub};
+use std::str::FromStr;
+
+// The integer type a machine computes with. Registers, immediates and the
+// instruction pointer are all words.
+pub trait Word: Copy + Eq + Ord + Hash + Debug + Display + Send + Sync + 'static
+  + FromStr<Err = ParseIntError>
+  + Add<Output = Self> + Mul<Output = Self> + BitAnd<Output = Self> + BitOr<Output = Self>
+  + Sub<Output = Self> + Div<Output = Self> + Rem<Output = Self> {
+  const ZERO: Self;
+  const ONE: Self;
+
+  // None if the word can't be used to index a register or an instruction.
+  fn to_index(self) -> Option<usize>;
+  fn from_index(index: usize) -> Option<Self>;
+
+  fn checked_add(self, other: Self) -> Option<Self>;
+  fn checked_mul(self, other: Self) -> Option<Self>;
+  fn wrapping_add(self, other: Self) -> Self;
+  fn wrapping_mul(self, other: Self) -> Self;
+  fn saturating_add(self, other: Self) -> Self;
+  fn saturating_mul(self, other: Self) -> Self;
+  // Keeps the lowest `bits` bits (at least one, at most all of them) and
+  // sign extends them.
+  fn truncate(self, bits: u32) -> Self;
+}
+
+macro_rules! word {
+  ($($t:ty),*) => {
+    $(
+      impl Word for $t {
+        const ZERO: $t = 0;
+        const ONE: $t = 1;
+
+        fn to_index(self) -> Option<usize> {
+          usize::try_from(self).ok()
+        }
+
+        fn from_index(index: usize) -> Option<$t> {
+          <$t>::try_from(index).ok()
+        }
+
+        fn checked_add(self, other: $t) -> Option<$t> {
+          <$t>::checked_add(self, other)
+        }
+
+        fn checked_mul(self, other: $t) -> Option<$t> {
+          <$t>::checked_mul(self, other)
+        }
+
+        fn wrapping_add(self, other: $t) -> $t {
+          <$t>::wrapping_add(self, other)
+        }
+
+        fn wrapping_mul(self, other: $t) -> $t {
+          <$t>::wrapping_mul(self, other)
+        }
+
+        fn saturating_add(self, other: $t) -> $t {
+          <$t>::saturating_add(self, other)
+        }
+
+        fn saturating_mul(self, other: $t) -> $t {
+          <$t>::saturating_mul(self, other)
+        }
+
+        fn truncate(self, bits: u32) -> $t {
+          let shift = <$t>::BITS - bits.clamp(1, <$t>::BITS);
+          (self << shift) >> shift
+        }
+      }
+    )*
+  }
+}
+
+word!(i32, i64, i128);
@@ -0,0 +1,331 @@
+use std::ops::{Index, IndexMut};
+use super::utils::{numbered_lines, ParseError};
+
+// A position on a grid, x grows to the right and y downwards.
+pub type Point = (i32, i32);
+
+// The four and eight neighbours of a cell, in reading order.
+pub const NEIGHBOURS4: [Point; 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+pub const NEIGHBOURS8: [Point; 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+// A rectangle of cells stored row by row. The top left cell is at `origin`,
+// so maps that start somewhere else or reach into negative coordinates are
+// addressed with their own coordinates. `get` returns None for points
+// outside the grid, indexing them panics.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+  origin: Point,
+  width: usize,
+  height: usize,
+  cells: Vec<T>,
+}
+
+// The points of a grid in reading order. It doesn't borrow the grid, so the
+// cells can be changed while walking over them.
+#[derive(Debug, Clone)]
+pub struct Points {
+  left: i32,
+  end: Point,
+  next: Point,
+}
+
+impl Points {
+  fn new(origin: Point, width: usize, height: usize) -> Points {
+    let end = (origin.0 + width as i32, origin.1 + height as i32);
+    Points { left: origin.0, end, next: origin }
+  }
+}
+
+impl Iterator for Points {
+  type Item = Point;
+
+  fn next(&mut self) -> Option<Point> {
+    if self.next.1 >= self.end.1 || self.left >= self.end.0 {
+      return None;
+    }
+
+    let point = self.next;
+    self.next.0 += 1;
+    if self.next.0 == self.end.0 {
+      self.next = (self.left, self.next.1 + 1);
+    }
+    Some(point)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let width = (self.end.0 - self.left).max(0) as usize;
+    let rows = (self.end.1 - self.next.1).max(0) as usize;
+    let left = (rows * width).saturating_sub((self.next.0 - self.left) as usize);
+    (left, Some(left))
+  }
+}
+
+fn neighbour(p: Point, delta: &Point) -> Option<Point> {
+  Some((p.0.checked_add(delta.0)?, p.1.checked_add(delta.1)?))
+}
+
+impl<T> Grid<T> {
+  pub fn from_fn<F>(origin: Point, width: usize, height: usize, f: F) -> Grid<T>
+  where F: FnMut(Point) -> T {
+    let cells = Points::new(origin, width, height).map(f).collect();
+    Grid { origin, width, height, cells }
+  }
+
+  // One cell per character, every row has to be as long as the first one.
+  pub fn parse<F>(s: &str, mut f: F) -> Result<Grid<T>, ParseError>
+  where F: FnMut(char) -> Result<T, ParseError> {
+    let mut cells = vec![];
+    let mut width = None;
+    let mut height = 0;
+
+    for (number, line) in numbered_lines(s) {
+      let before = cells.len();
+      for (column, c) in line.chars().enumerate() {
+        let cell = f(c).map_err(|err| err.at_column(column + 1).at_line(number, line))?;
+        cells.push(cell);
+      }
+
+      let length = cells.len() - before;
+      if *width.get_or_insert(length) != length {
+        Err(ParseError::new("All rows of the map must have the same length").at_line(number, line))?
+      }
+      height += 1;
+    }
+
+    match width {
+      Some(width) => Ok(Grid { origin: (0, 0), width, height, cells }),
+      None => Err(ParseError::new("The map is empty")),
+    }
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn origin(&self) -> Point {
+    self.origin
+  }
+
+  // The point right after the bottom right cell in both directions.
+  pub fn end(&self) -> Point {
+    (self.origin.0 + self.width as i32, self.origin.1 + self.height as i32)
+  }
+
+  pub fn contains(&self, p: Point) -> bool {
+    self.index_of(p).is_some()
+  }
+
+  // Whether the point is in the outermost rows or columns.
+  pub fn on_edge(&self, p: Point) -> bool {
+    let end = self.end();
+    self.contains(p) && (p.0 == self.origin.0 || p.1 == self.origin.1 || p.0 == end.0 - 1 || p.1 == end.1 - 1)
+  }
+
+  // Points left of or above the origin wrap around to large offsets, so
+  // one comparison per axis is enough.
+  #[inline]
+  fn index_of(&self, p: Point) -> Option<usize> {
+    let x = (p.0 as i64 - self.origin.0 as i64) as u64;
+    let y = (p.1 as i64 - self.origin.1 as i64) as u64;
+    if x < self.width as u64 && y < self.height as u64 {
+      Some(y as usize * self.width + x as usize)
+    } else {
+      None
+    }
+  }
+
+  pub fn get(&self, p: Point) -> Option<&T> {
+    self.index_of(p).map(|i| &self.cells[i])
+  }
+
+  pub fn get_mut(&mut self, p: Point) -> Option<&mut T> {
+    self.index_of(p).map(move |i| &mut self.cells[i])
+  }
+
+  pub fn points(&self) -> Points {
+    Points::new(self.origin, self.width, self.height)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+    self.points().zip(self.cells.iter())
+  }
+
+  pub fn values(&self) -> std::slice::Iter<'_, T> {
+    self.cells.iter()
+  }
+
+  pub fn row(&self, y: i32) -> Option<&[T]> {
+    let start = self.index_of((self.origin.0, y))?;
+    Some(&self.cells[start..start + self.width])
+  }
+
+  // The neighbours above, left, right and below that are on the grid.
+  pub fn neighbours4(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+    NEIGHBOURS4.iter().filter_map(move |d| neighbour(p, d)).filter(move |&q| self.contains(q))
+  }
+
+  // Like `neighbours4`, with the diagonals.
+  pub fn neighbours8(&self, p: Point) -> impl Iterator<Item = Point> + '_ {
+    NEIGHBOURS8.iter().filter_map(move |d| neighbour(p, d)).filter(move |&q| self.contains(q))
+  }
+
+  pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+    Grid { origin: self.origin, width: self.width, height: self.height, cells: self.cells.iter().map(f).collect() }
+  }
+
+  // One line per row, without a line break at the end.
+  pub fn render<F: FnMut(&T) -> char>(&self, mut f: F) -> String {
+    let mut out = String::with_capacity((self.width + 1) * self.height);
+    for (index, cell) in self.cells.iter().enumerate() {
+      if index > 0 && index % self.width == 0 {
+        out.push('\n');
+      }
+      out.push(f(cell));
+    }
+
+    out
+  }
+}
+
+impl<T: Clone> Grid<T> {
+  pub fn new(width: usize, height: usize, fill: T) -> Grid<T> {
+    Grid::with_origin((0, 0), width, height, fill)
+  }
+
+  pub fn with_origin(origin: Point, width: usize, height: usize, fill: T) -> Grid<T> {
+    Grid { origin, width, height, cells: vec![fill; width * height] }
+  }
+
+  // Grows the grid until it contains `p`, new cells are `fill`. It grows by
+  // at least its own size in each direction, so maps that are explored one
+  // step at a time are only copied a few times.
+  pub fn fit(&mut self, p: Point, fill: T) {
+    if self.contains(p) {
+      return;
+    }
+    if self.cells.is_empty() {
+      *self = Grid::with_origin(p, 1, 1, fill);
+      return;
+    }
+
+    let (start, end) = (self.origin, self.end());
+    let (width, height) = (self.width as i32, self.height as i32);
+    let left = if p.0 < start.0 { p.0.min(start.0 - width) } else { start.0 };
+    let top = if p.1 < start.1 { p.1.min(start.1 - height) } else { start.1 };
+    let right = if p.0 >= end.0 { (p.0 + 1).max(end.0 + width) } else { end.0 };
+    let bottom = if p.1 >= end.1 { (p.1 + 1).max(end.1 + height) } else { end.1 };
+
+    let mut grown = Grid::with_origin((left, top), (right - left) as usize, (bottom - top) as usize, fill);
+    let points = self.points();
+    for (point, cell) in points.zip(self.cells.drain(..)) {
+      grown[point] = cell;
+    }
+
+    *self = grown;
+  }
+}
+
+impl<T> Index<Point> for Grid<T> {
+  type Output = T;
+
+  fn index(&self, p: Point) -> &T {
+    match self.index_of(p) {
+      Some(i) => &self.cells[i],
+      None => panic!("{:?} is outside of the grid", p),
+    }
+  }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+  fn index_mut(&mut self, p: Point) -> &mut T {
+    match self.index_of(p) {
+      Some(i) => &mut self.cells[i],
+      None => panic!("{:?} is outside of the grid", p),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn chars(s: &str) -> Result<Grid<char>, ParseError> {
+    Grid::parse(s, Ok)
+  }
+
+  #[test]
+  fn parses_and_renders_text() {
+    let grid = chars("#..\n.#.\n\n..#\n").unwrap();
+    assert_eq!((grid.width(), grid.height()), (3, 3));
+    assert_eq!(grid[(1, 1)], '#');
+    assert_eq!(grid.row(2), Some(&['.', '.', '#'][..]));
+    assert_eq!(grid.render(|&c| c), "#..\n.#.\n..#");
+    assert_eq!(grid.values().filter(|&&c| c == '#').count(), 3);
+  }
+
+  #[test]
+  fn parse_errors_name_the_position() {
+    let err = Grid::<bool>::parse("..\n.x\n", |c| match c {
+      '.' => Ok(false),
+      _ => Err(ParseError::new(&format!("Unknown cell '{}'", c))),
+    }).unwrap_err();
+    assert_eq!(err.to_string(), "line 2, column 2: Unknown cell 'x' in \".x\"");
+
+    let err = chars("...\n..\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: All rows of the map must have the same length in \"..\"");
+    assert_eq!(chars("\n\n").unwrap_err().to_string(), "The map is empty");
+  }
+
+  #[test]
+  fn points_are_offset_by_the_origin() {
+    let mut grid = Grid::with_origin((-2, 5), 3, 2, 0);
+    grid[(-2, 5)] = 1;
+    grid[(0, 6)] = 2;
+
+    assert_eq!(grid.end(), (1, 7));
+    assert_eq!(grid.get((0, 0)), None);
+    assert_eq!(grid.get((1, 6)), None);
+    assert_eq!(grid.get_mut((-3, 6)), None);
+    assert!(grid.on_edge((-1, 5)) && !grid.on_edge((-1, 4)));
+    assert_eq!(grid.iter().filter(|(_, &v)| v > 0).collect::<Vec<_>>(), vec![((-2, 5), &1), ((0, 6), &2)]);
+    assert_eq!(grid.points().collect::<Vec<Point>>(), vec![(-2, 5), (-1, 5), (0, 5), (-2, 6), (-1, 6), (0, 6)]);
+  }
+
+  #[test]
+  fn neighbours_stay_on_the_grid() {
+    let grid = Grid::new(3, 3, ());
+    assert_eq!(grid.neighbours4((1, 1)).collect::<Vec<Point>>(), vec![(1, 0), (0, 1), (2, 1), (1, 2)]);
+    assert_eq!(grid.neighbours4((0, 0)).collect::<Vec<Point>>(), vec![(1, 0), (0, 1)]);
+    assert_eq!(grid.neighbours8((1, 1)).count(), 8);
+    assert_eq!(grid.neighbours8((2, 0)).collect::<Vec<Point>>(), vec![(1, 0), (1, 1), (2, 1)]);
+    assert_eq!(Grid::with_origin((i32::MAX, 0), 1, 1, ()).neighbours8((i32::MAX, 0)).count(), 0);
+  }
+
+  #[test]
+  fn grows_to_fit_points() {
+    let mut grid = Grid::new(0, 0, None);
+    grid.fit((3, -1), None);
+    grid[(3, -1)] = Some('a');
+    for x in -10..=10 {
+      grid.fit((x, x), None);
+      grid[(x, x)] = Some('b');
+    }
+
+    assert!(grid.contains((-10, -10)) && grid.contains((10, 10)));
+    assert_eq!(grid[(3, -1)], Some('a'));
+    assert_eq!(grid.values().filter(|v| v.is_some()).count(), 22);
+    // 21x22 cells are needed, growing one step at a time at most doubles that.
+    assert!(grid.width() <= 42 && grid.height() <= 44, "{}x{}", grid.width(), grid.height());
+  }
+
+  #[test]
+  #[should_panic(expected = "(3, 0) is outside of the grid")]
+  fn indexing_outside_panics() {
+    let grid = Grid::new(3, 3, 0);
+    let _ = grid[(3, 0)];
+  }
+}
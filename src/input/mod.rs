@@ -0,0 +1,94 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use super::utils::{read_file, Error};
+use super::solution::Puzzle;
+
+pub const DEFAULT_DIR: &str = "inputs";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Source {
+  // Look for day<N>.txt in the given directory and fall back to the
+  // embedded input if the file does not exist.
+  Directory(PathBuf),
+  File(PathBuf),
+  Stdin,
+}
+
+impl Default for Source {
+  fn default() -> Source {
+    Source::Directory(PathBuf::from(DEFAULT_DIR))
+  }
+}
+
+pub fn default_path(dir: &Path, day: u8) -> PathBuf {
+  dir.join(format!("day{}.txt", day))
+}
+
+pub fn read_stdin() -> Result<String, Error> {
+  let mut contents = String::new();
+  if let Err(err) = std::io::stdin().read_to_string(&mut contents) {
+    Err(Error::new(&format!("Unable to read input from stdin: {}", err)))?
+  }
+
+  Ok(contents)
+}
+
+pub fn load(puzzle: &dyn Puzzle, source: &Source) -> Result<String, Error> {
+  match source {
+    Source::File(path) => read_file(path),
+    Source::Stdin => read_stdin(),
+    Source::Directory(dir) => {
+      let path = default_path(dir, puzzle.day());
+      if path.exists() {
+        return read_file(&path);
+      }
+
+      match puzzle.embedded_input() {
+        Some(input) => Ok(input.to_string()),
+        None => Err(Error::new(&format!("No input for day {}: {} does not exist", puzzle.day(), path.display()))),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::solution;
+
+  #[test]
+  fn default_path_is_inside_the_directory() {
+    assert_eq!(default_path(Path::new("inputs"), 7), PathBuf::from("inputs/day7.txt"));
+  }
+
+  #[test]
+  fn load_reads_the_given_file() {
+    let path = std::env::temp_dir().join(format!("y2018-input-{}.txt", std::process::id()));
+    std::fs::write(&path, "+1\n-2\n").unwrap();
+    let input = load(solution::get(1).unwrap(), &Source::File(path.clone()));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(input.unwrap(), "+1\n-2\n");
+  }
+
+  #[test]
+  fn load_fails_for_missing_file() {
+    let source = Source::File(PathBuf::from("does/not/exist.txt"));
+    assert!(load(solution::get(1).unwrap(), &source).is_err());
+  }
+
+  #[cfg(feature = "embedded-inputs")]
+  #[test]
+  fn load_falls_back_to_embedded_input() {
+    let puzzle = solution::get(1).unwrap();
+    let source = Source::Directory(PathBuf::from("does/not/exist"));
+    assert_eq!(load(puzzle, &source).unwrap(), puzzle.embedded_input().unwrap());
+  }
+
+  #[cfg(not(feature = "embedded-inputs"))]
+  #[test]
+  fn load_fails_without_file_and_embedded_input() {
+    let source = Source::Directory(PathBuf::from("does/not/exist"));
+    assert!(load(solution::get(1).unwrap(), &source).is_err());
+  }
+}
@@ -0,0 +1,58 @@
+//! Solutions to the Advent of Code 2018 puzzles.
+//!
+//! Every day lives in its own module and exposes its model types together
+//! with a `DayN` type implementing `solution::Solution`. The `solution`
+//! module keeps a registry of all days, `input`, `report`, `bench` and
+//! `verify` contain the building blocks of the command line tool. Code
+//! shared between days lives in `elfcode`, `constraint` and `grid`.
+//!
+//! ```
+//! use std::str::FromStr;
+//! use y2018::day15::cave::Cave;
+//! use y2018::day15::unit::Kind;
+//!
+//! let map = "#######\n#.G...#\n#...EG#\n#.#.#G#\n#..G#E#\n#.....#\n#######";
+//! let cave = Cave::from_str(map).unwrap();
+//! let elves = cave.units.iter().filter(|unit| unit.kind == Kind::Elf).count();
+//! assert_eq!((cave.units.len(), elves), (6, 2));
+//! ```
+
+#[macro_use]
+extern crate lazy_static;
+extern crate regex;
+
+pub mod day1;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
+pub mod utils;
+pub mod solution;
+pub mod input;
+pub mod report;
+pub mod bench;
+pub mod verify;
+pub mod cli;
+pub mod elfcode;
+pub mod constraint;
+pub mod grid;
@@ -1,117 +1,225 @@
-#[macro_use]
-extern crate lazy_static;
-extern crate regex;
+extern crate y2018;
 
-mod day1;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
-mod utils;
+use y2018::{bench, cli, day16, elfcode, input, report, solution, utils, verify};
+use y2018::solution::Solution;
 
-fn run() -> Result<(), utils::Error> {
-  day23::problem2()?;
+// Days 19 and 21 both run on six registers.
+const PROGRAM_REGISTERS: usize = 6;
 
-  if false {
-    println!("Past problems:");
-    day1::problem1();
-    day1::problem2();
+fn run_selection(options: cli::RunOptions) -> Result<bool, utils::Error> {
+  let (puzzles, parts) = options.selection.resolve()?;
 
-    day2::problem1();
-    day2::problem2();
+  if let Some(header) = report::header(options.format) {
+    println!("{}", header);
+  }
 
-    day3::problem1();
-    day3::problem2();
+  let mut success = true;
+  for puzzle in puzzles {
+    for record in report::run_day(puzzle, &parts, &options.source) {
+      success &= record.is_ok();
+      println!("{}", report::format_record(&record, options.format));
+    }
+  }
 
-    day4::problem1()?;
-    day4::problem2()?;
+  Ok(success)
+}
 
-    day5::problem1();
-    day5::problem2();
+fn verify(options: cli::VerifyOptions) -> Result<bool, utils::Error> {
+  let (puzzles, parts) = options.selection.resolve()?;
+  let path = verify::answers_path(&options.answers, &options.source)?;
+  let answers = verify::parse_answers(&utils::read_file(&path)?)?;
 
-    day6::problem1()?;
-    day6::problem2()?;
+  if let Some(header) = verify::header(options.format) {
+    println!("{}", header);
+  }
 
-    day7::problem1()?;
-    day7::problem2()?;
+  let mut success = true;
+  for puzzle in puzzles {
+    for record in report::run_day(puzzle, &parts, &options.source) {
+      let outcome = verify::check(&record, &answers);
+      success &= !outcome.is_failure();
+      println!("{}", verify::format_outcome(&record, &outcome, options.format));
+    }
+  }
 
-    day8::problem1()?;
-    day8::problem2()?;
+  Ok(success)
+}
 
-    day9::problem1()?;
-    day9::problem2()?;
+fn bench(options: cli::BenchOptions) -> Result<bool, utils::Error> {
+  let (puzzles, parts) = options.selection.resolve()?;
+  let baseline = match &options.baseline {
+    Some(path) => Some(bench::parse_baseline(&utils::read_file(path)?)?),
+    None => None,
+  };
+
+  println!("{}", bench::header());
+
+  let mut success = true;
+  let mut timings = vec![];
+  for puzzle in puzzles {
+    let measured = input::load(puzzle, &options.source)
+      .and_then(|raw| bench::measure(puzzle, &raw, &parts, options.runs));
+
+    match measured {
+      Ok(v) => timings.extend(v),
+      Err(err) => {
+        eprintln!("Error in day {}: {}", puzzle.day(), utils::describe(&err));
+        success = false;
+        continue;
+      }
+    }
+
+    for timing in timings.iter().filter(|t| t.day == puzzle.day()) {
+      let base = baseline.as_ref().and_then(|b| b.get(&(timing.day, timing.phase)));
+      println!("{}", bench::format_timing(timing, base));
+    }
+  }
 
-    day10::problem1()?;
-    day10::problem2()?;
+  if let Some(path) = &options.save {
+    // Keep the entries of days that were not part of this run.
+    let mut saved = if path.exists() {
+      bench::parse_baseline(&utils::read_file(path)?)?
+    } else {
+      bench::Baseline::new()
+    };
+    bench::update_baseline(&mut saved, &timings);
+
+    if let Err(err) = std::fs::write(path, bench::format_baseline(&saved)) {
+      Err(utils::Error::new(&format!("Unable to write {}: {}", path.display(), err)))?
+    }
+  }
 
-    day11::problem1();
-    day11::problem2();
+  Ok(success)
+}
 
-    day12::problem1();
-    day12::problem2();
+fn load_program(options: &cli::ProgramOptions) -> Result<elfcode::Program<i128>, utils::Error> {
+  let raw = match (options.day.and_then(solution::get), &options.source) {
+    (Some(puzzle), source) => input::load(puzzle, source)?,
+    (None, input::Source::File(path)) => utils::read_file(path)?,
+    (None, input::Source::Stdin) => input::read_stdin()?,
+    (None, input::Source::Directory(_)) => Err(utils::Error::new("Either --day or --input is required"))?,
+  };
 
-    day13::problem1()?;
-    day13::problem2()?;
+  Ok(elfcode::parse(&raw, PROGRAM_REGISTERS)?)
+}
 
-    day14::problems()?;
+fn decompile(options: cli::ProgramOptions) -> Result<bool, utils::Error> {
+  let program = load_program(&options)?;
+  print!("{}", elfcode::decompile(&program));
 
-    day15::problem1()?;
-    day15::problem2()?;
+  Ok(true)
+}
 
-    day16::problems::problem1()?;
-    day16::problems::problem2()?;
+fn transpile(options: cli::ProgramOptions) -> Result<bool, utils::Error> {
+  let program = load_program(&options)?;
+  let source = match (options.day, &options.source) {
+    (Some(day), _) => format!("the day {} input", day),
+    (None, input::Source::File(path)) => path.display().to_string(),
+    (None, _) => "stdin".to_string(),
+  };
+  print!("{}", elfcode::transpiler::transpile(&program, PROGRAM_REGISTERS, &source)?);
 
-    day17::problems()?;
+  Ok(true)
+}
 
-    day18::problems::problem1()?;
-    day18::problems::problem2()?;
+// Every register is part of the result, so all of them are live at the end.
+fn cfg(options: cli::ProgramOptions) -> Result<bool, utils::Error> {
+  let program = load_program(&options)?;
+  let cfg = elfcode::cfg::build(&program);
+  let liveness = cfg.liveness(&program, PROGRAM_REGISTERS, &(0..PROGRAM_REGISTERS).collect::<Vec<usize>>());
+  print!("{}", cfg.dot(&program, Some(&liveness)));
 
-    day19::problems::problem1()?;
-    day19::problems::problem2()?;
+  Ok(true)
+}
 
-    day20::problems::problems()?;
+fn debug(options: cli::DebugOptions) -> Result<bool, utils::Error> {
+  let program = load_program(&options.program)?;
+  let mut debugger = elfcode::Debugger::new(&program, PROGRAM_REGISTERS)?;
+
+  match &options.script {
+    Some(path) => print!("{}", debugger.run_script(&utils::read_file(path)?)?),
+    None => {
+      let stdin = std::io::stdin();
+      if let Err(err) = debugger.interact(stdin.lock(), std::io::stdout()) {
+        Err(utils::Error::new(&format!("Unable to talk to the terminal: {}", err)))?
+      }
+    }
+  }
 
-    day21::problems::problems()?;
+  Ok(true)
+}
 
-    day22::problem1();
-    day22::problem2();
+fn profile(options: cli::ProfileOptions) -> Result<bool, utils::Error> {
+  let program = load_program(&options.program)?;
+  let mut machine = elfcode::Machine::for_program(&program, PROGRAM_REGISTERS)?;
+  machine.arithmetic = options.arithmetic;
+
+  let profile = match &options.trace {
+    Some(path) => {
+      let file = match std::fs::File::create(path) {
+        Ok(v) => v,
+        Err(err) => Err(utils::Error::new(&format!("Unable to write {}: {}", path.display(), err)))?
+      };
+      let mut trace = elfcode::trace::Trace::new(std::io::BufWriter::new(file), options.limits);
+      let profile = elfcode::trace::profile(&mut machine, &program, Some(&mut trace), options.max_cycles)?;
+      if let Err(err) = trace.finish() {
+        Err(utils::Error::new(&format!("Unable to write {}: {}", path.display(), err)))?
+      }
+      profile
+    },
+    None => elfcode::trace::profile::<_, std::io::Sink>(&mut machine, &program, None, options.max_cycles)?,
+  };
+
+  print!("{}", profile.report(&program));
+  Ok(true)
+}
 
-    day23::problem1()?;
-    day23::problem2()?;
+fn samples(source: input::Source) -> Result<bool, utils::Error> {
+  let raw = input::load(&day16::Day16, &source)?;
+  let validation = day16::validate::validate(&day16::Day16::parse(&raw)?);
+  print!("{}", validation.report());
 
-    day24::problems::problem1()?;
-    day24::problems::problem2()?;
+  Ok(validation.is_valid())
+}
 
-    day25::problem1()?;
-    // day25::problem2()?;
+fn list() {
+  for puzzle in solution::all() {
+    println!("Day {:2}: {}", puzzle.day(), puzzle.title());
   }
+}
 
-  Ok(())
+fn run() -> Result<bool, utils::Error> {
+  let command = cli::parse_args(std::env::args().skip(1))?;
+
+  match command {
+    cli::Command::Run(options) => run_selection(options),
+    cli::Command::Bench(options) => bench(options),
+    cli::Command::Verify(options) => verify(options),
+    cli::Command::Decompile(options) => decompile(options),
+    cli::Command::Debug(options) => debug(options),
+    cli::Command::Profile(options) => profile(options),
+    cli::Command::Transpile(options) => transpile(options),
+    cli::Command::Cfg(options) => cfg(options),
+    cli::Command::Samples(source) => samples(source),
+    cli::Command::List => {
+      list();
+      Ok(true)
+    },
+    cli::Command::Help => {
+      println!("{}", cli::USAGE);
+      Ok(true)
+    },
+  }
 }
 
 fn main() {
   match run() {
-    Err(err) => println!("Error occurred: {}", err),
-    _ => {}
+    Ok(true) => {},
+    Ok(false) => std::process::exit(1),
+    Err(err) => {
+      eprintln!("Error occurred: {}", utils::describe(&err));
+      eprintln!("{}", cli::USAGE);
+      std::process::exit(2);
+    }
   }
 }
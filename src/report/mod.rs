@@ -0,0 +1,217 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use super::utils::{describe, Error};
+use super::solution::{Answer, Puzzle};
+use super::input::{self, Source};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+  Table,
+  Json,
+}
+
+impl FromStr for Format {
+  type Err = Error;
+
+  fn from_str(s: &str) -> Result<Format, Error> {
+    match s {
+      "table" => Ok(Format::Table),
+      "json" => Ok(Format::Json),
+      _ => Err(Error::new(&format!("Unknown format: {} (expected table or json)", s))),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Status {
+  Ok,
+  Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Record {
+  pub day: u8,
+  pub part: u8,
+  pub answer: Answer,
+  pub elapsed: Duration,
+  pub status: Status,
+}
+
+impl Record {
+  pub fn new(day: u8, part: u8, result: Result<Answer, Error>, elapsed: Duration) -> Record {
+    let (answer, status) = match result {
+      Ok(answer) => (answer, Status::Ok),
+      Err(err) => (Answer::None, Status::Failed(error_message(&err))),
+    };
+
+    Record { day, part, answer, elapsed, status }
+  }
+
+  pub fn is_ok(&self) -> bool {
+    self.status == Status::Ok
+  }
+}
+
+// Loads the input of the puzzle and solves the given parts. If the input
+// can't be loaded or parsed, every part is reported as failed.
+pub fn run_day(puzzle: &dyn Puzzle, parts: &[u8], source: &Source) -> Vec<Record> {
+  let day = puzzle.day();
+  let start = Instant::now();
+  let input = input::load(puzzle, source).and_then(|raw| puzzle.parse(&raw));
+
+  let input = match input {
+    Ok(v) => v,
+    Err(err) => {
+      let elapsed = start.elapsed();
+      let what = error_message(&err);
+      return parts
+        .iter()
+        .map(|&part| Record::new(day, part, Err(Error::new(&what)), elapsed))
+        .collect();
+    }
+  };
+
+  parts
+    .iter()
+    .map(|&part| {
+      let start = Instant::now();
+      let result = puzzle.solve(input.as_ref(), part);
+      Record::new(day, part, result, start.elapsed())
+    })
+    .collect()
+}
+
+// The day is part of the record already, so it is left out of the message.
+pub fn error_message(err: &Error) -> String {
+  match err {
+    Error::Day { source, .. } => describe(source.as_ref()),
+    _ => describe(err),
+  }
+}
+
+fn milliseconds(elapsed: Duration) -> f64 {
+  elapsed.as_secs_f64() * 1000.0
+}
+
+pub fn json_string(s: &str) -> String {
+  let mut out = String::with_capacity(s.len() + 2);
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+
+  out
+}
+
+fn json_answer(answer: &Answer) -> String {
+  match answer {
+    Answer::Number(n) => n.to_string(),
+    Answer::Text(s) => json_string(s),
+    Answer::None => "null".to_string(),
+  }
+}
+
+fn to_json(record: &Record) -> String {
+  let (status, error) = match &record.status {
+    Status::Ok => ("ok", "null".to_string()),
+    Status::Failed(what) => ("error", json_string(what)),
+  };
+
+  format!(
+    "{{\"day\":{},\"part\":{},\"status\":\"{}\",\"answer\":{},\"elapsed_ms\":{:.3},\"error\":{}}}",
+    record.day, record.part, status, json_answer(&record.answer), milliseconds(record.elapsed), error)
+}
+
+// Width of the day, part, status and time columns in front of the answer.
+const ANSWER_COLUMN: usize = 33;
+
+fn to_row(record: &Record) -> String {
+  let (status, text) = match &record.status {
+    Status::Ok => ("ok", record.answer.to_string()),
+    Status::Failed(what) => ("error", what.clone()),
+  };
+
+  let time = format!("{:.3} ms", milliseconds(record.elapsed));
+  let mut lines = text.lines();
+  let first = lines.next().unwrap_or("");
+  let mut row = format!("{:>3}  {:>4}  {:<6}  {:>12}  {}", record.day, record.part, status, time, first);
+
+  // Multi line answers (e.g. the message of day 10) continue below the answer column.
+  for line in lines {
+    let _ = write!(row, "\n{:width$}{}", "", line, width = ANSWER_COLUMN);
+  }
+
+  row
+}
+
+pub fn header(format: Format) -> Option<String> {
+  match format {
+    Format::Table => Some(format!("{:>3}  {:>4}  {:<6}  {:>12}  {}", "Day", "Part", "Status", "Time", "Answer")),
+    Format::Json => None,
+  }
+}
+
+pub fn format_record(record: &Record, format: Format) -> String {
+  match format {
+    Format::Table => to_row(record),
+    Format::Json => to_json(record),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn record(result: Result<Answer, Error>) -> Record {
+    Record::new(3, 2, result, Duration::from_micros(1500))
+  }
+
+  #[test]
+  fn json_contains_all_fields() {
+    let line = format_record(&record(Ok(Answer::Number(42))), Format::Json);
+    assert_eq!(line, "{\"day\":3,\"part\":2,\"status\":\"ok\",\"answer\":42,\"elapsed_ms\":1.500,\"error\":null}");
+  }
+
+  #[test]
+  fn json_escapes_text_answers_and_errors() {
+    let line = format_record(&record(Ok(Answer::from("a\"b\nc"))), Format::Json);
+    assert!(line.contains("\"answer\":\"a\\\"b\\nc\""));
+
+    let line = format_record(&record(Err(Error::new("broken"))), Format::Json);
+    assert!(line.contains("\"status\":\"error\",\"answer\":null"));
+    assert!(line.contains("\"error\":\"broken\""));
+  }
+
+  #[test]
+  fn table_rows_align_with_header() {
+    let header = header(Format::Table).unwrap();
+    let row = format_record(&record(Ok(Answer::Number(42))), Format::Table);
+    assert_eq!(header.find("Answer"), row.find("42"));
+    assert_eq!(header.find("Answer"), Some(ANSWER_COLUMN));
+  }
+
+  #[test]
+  fn table_indents_multi_line_answers() {
+    let row = format_record(&record(Ok(Answer::from("#..\n.#."))), Format::Table);
+    let lines: Vec<&str> = row.lines().collect();
+    assert_eq!(lines[0].find("#.."), Some(ANSWER_COLUMN));
+    assert_eq!(lines[1].find(".#."), Some(ANSWER_COLUMN));
+  }
+
+  #[test]
+  fn parses_format() {
+    assert_eq!("json".parse::<Format>().unwrap(), Format::Json);
+    assert_eq!("table".parse::<Format>().unwrap(), Format::Table);
+    assert!("xml".parse::<Format>().is_err());
+  }
+}
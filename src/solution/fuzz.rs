@@ -0,0 +1,289 @@
+// Feeds random and mutated puzzle inputs to every parser and makes sure
+// that malformed input is reported as an error instead of a panic. Small
+// inputs that still parse are solved as well.
+
+use std::panic;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use super::all;
+
+const CASES_PER_DAY: usize = 300;
+const SOLVED_CASES_PER_DAY: usize = 100;
+// Day 11 always searches the whole grid and day 14 may make a hundred
+// million recipes looking for a pattern, which takes long in debug builds.
+// The programs of days 19 and 21 don't have to halt.
+const MAY_NOT_FINISH: [u8; 4] = [11, 14, 19, 21];
+const GIVE_UP_AFTER: Duration = Duration::from_secs(2);
+
+const INPUTS: [&str; 25] = [
+  include_str!("../day1/data/input_1-1.txt"),
+  include_str!("../day2/data/input_2-1.txt"),
+  include_str!("../day3/data/input.txt"),
+  include_str!("../day4/data/input.txt"),
+  include_str!("../day5/data/input.txt"),
+  include_str!("../day6/data/input.txt"),
+  include_str!("../day7/data/input.txt"),
+  include_str!("../day8/data/input.txt"),
+  include_str!("../day9/data/input.txt"),
+  include_str!("../day10/data/input.txt"),
+  include_str!("../day11/data/input.txt"),
+  include_str!("../day12/data/input.txt"),
+  include_str!("../day13/data/input.txt"),
+  include_str!("../day14/data/input.txt"),
+  include_str!("../day15/data/input.txt"),
+  include_str!("../day16/data/input.txt"),
+  include_str!("../day17/data/input.txt"),
+  include_str!("../day18/data/input.txt"),
+  include_str!("../day19/data/input.txt"),
+  include_str!("../day20/data/input.txt"),
+  include_str!("../day21/data/input.txt"),
+  include_str!("../day22/data/input.txt"),
+  include_str!("../day23/data/input.txt"),
+  include_str!("../day24/data/input.txt"),
+  include_str!("../day25/data/input.txt"),
+];
+
+// Small inputs, mutated and then solved.
+const SAMPLES: [&str; 25] = [
+  "+1\n-2\n+3\n+1\n",
+  "abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz\n",
+  "#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4\n#3 @ 5,5: 2x2\n",
+  "[1518-11-01 00:00] Guard #10 begins shift\n\
+   [1518-11-01 00:05] falls asleep\n\
+   [1518-11-01 00:25] wakes up\n\
+   [1518-11-02 00:40] falls asleep\n\
+   [1518-11-01 23:58] Guard #99 begins shift\n\
+   [1518-11-02 00:50] wakes up\n",
+  "dabAcCaCBAcCcaDA\n",
+  "1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9\n",
+  "Step C must be finished before step A can begin.\n\
+   Step C must be finished before step F can begin.\n\
+   Step A must be finished before step B can begin.\n\
+   Step A must be finished before step D can begin.\n\
+   Step B must be finished before step E can begin.\n\
+   Step D must be finished before step E can begin.\n\
+   Step F must be finished before step E can begin.\n",
+  "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2\n",
+  "9 players; last marble is worth 25 points\n",
+  "position=< 9,  1> velocity=< 0,  2>\n\
+   position=< 7,  0> velocity=<-1,  0>\n\
+   position=< 3, -2> velocity=<-1,  1>\n\
+   position=< 6, 10> velocity=<-2, -1>\n",
+  "18\n",
+  "initial state: #..#.#..##......###...###\n\n\
+   ...## => #\n..#.. => #\n.#... => #\n.#.#. => #\n.#.## => #\n.##.. => #\n.#### => #\n",
+  "/->-\\        \n|   |  /----\\\n| /-+--+-\\  |\n| | |  | v  |\n\\-+-/  \\-+--/\n  \\------/   \n",
+  "51589\n",
+  include_str!("../day15/data/example1.txt"),
+  "Before: [3, 2, 1, 1]\n9 2 1 2\nAfter:  [3, 2, 2, 1]\n\n\n\n9 2 1 2\n",
+  include_str!("../day17/data/example.txt"),
+  include_str!("../day18/data/example.txt"),
+  include_str!("../day19/data/example.txt"),
+  "^ENWWW(NEEE|SSE(EE|N))$\n",
+  include_str!("../day21/data/input.txt"),
+  "depth: 510\ntarget: 10,10\n",
+  "pos=<10,12,12>, r=2\npos=<12,14,12>, r=2\npos=<16,12,12>, r=4\npos=<14,14,14>, r=6\n",
+  include_str!("../day24/data/example.txt"),
+  include_str!("../day25/data/example1.txt"),
+];
+
+// Tokens that tend to break parsers in interesting ways.
+const TOKENS: [&str; 15] = [
+  "", " ", "\n", "-", "0", "-1", "99999999999999999999", "4294967296", "2147483647",
+  ",", "=", "#", "(", ")", "..",
+];
+
+// Puzzles mostly break on numbers at the limits of their types.
+const LIMITS: [&str; 8] = [
+  "2147483647", "-2147483648", "4294967295", "9223372036854775807", "-9223372036854775808",
+  "18446744073709551615", "170141183460469231731687303715884105727", "-170141183460469231731687303715884105728",
+];
+
+// xorshift64*, good enough to get reproducible test cases without dependencies.
+struct Rng(u64);
+
+impl Rng {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 >> 12;
+    self.0 ^= self.0 << 25;
+    self.0 ^= self.0 >> 27;
+    self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+
+  fn below(&mut self, n: usize) -> usize {
+    if n == 0 { 0 } else { (self.next() % n as u64) as usize }
+  }
+}
+
+fn char_boundary(s: &str, mut index: usize) -> usize {
+  while !s.is_char_boundary(index) {
+    index -= 1;
+  }
+  index
+}
+
+fn random_text(rng: &mut Rng, alphabet: &[char]) -> String {
+  let length = rng.below(300);
+  (0..length).map(|_| alphabet[rng.below(alphabet.len())]).collect()
+}
+
+// The byte ranges of the numbers in `s`, with their signs.
+fn numbers(s: &str) -> Vec<(usize, usize)> {
+  let bytes = s.as_bytes();
+  let mut ranges = vec![];
+  let mut start = None;
+  for (i, &byte) in bytes.iter().enumerate() {
+    match (start, byte.is_ascii_digit()) {
+      (None, true) => start = Some(if i > 0 && bytes[i - 1] == b'-' { i - 1 } else { i }),
+      (Some(a), false) => {
+        ranges.push((a, i));
+        start = None;
+      },
+      _ => (),
+    }
+  }
+  if let Some(a) = start {
+    ranges.push((a, bytes.len()));
+  }
+
+  ranges
+}
+
+fn mutate(rng: &mut Rng, input: &str) -> String {
+  let mut s = input.to_string();
+
+  for _ in 0..=rng.below(4) {
+    let a = char_boundary(&s, rng.below(s.len() + 1));
+    let b = char_boundary(&s, a + rng.below(s.len() - a + 1).min(40));
+    let numbers = numbers(&s);
+    match rng.below(6) {
+      // remove a range
+      0 => s.replace_range(a..b, ""),
+      // replace a range by a token
+      1 => s.replace_range(a..b, TOKENS[rng.below(TOKENS.len())]),
+      // insert a token
+      2 => s.insert_str(a, TOKENS[rng.below(TOKENS.len())]),
+      // cut off the end
+      3 => s.truncate(a),
+      // replace a number by one at the limit of a type
+      4 if !numbers.is_empty() => {
+        let (a, b) = numbers[rng.below(numbers.len())];
+        s.replace_range(a..b, LIMITS[rng.below(LIMITS.len())]);
+      },
+      // duplicate a range
+      _ => {
+        let copy = s[a..b].to_string();
+        s.insert_str(b, &copy);
+      },
+    }
+  }
+
+  s
+}
+
+fn parse_panics(day: u8, input: &str) -> bool {
+  let puzzle = all()[day as usize - 1];
+  let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+    let _ = puzzle.parse(input);
+  }));
+
+  result.is_err()
+}
+
+fn parses(day: u8, input: &str) -> bool {
+  let puzzle = all()[day as usize - 1];
+  panic::catch_unwind(panic::AssertUnwindSafe(|| puzzle.parse(input).is_ok())).unwrap_or(false)
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+  Finished,
+  Panicked,
+  TimedOut,
+}
+
+fn solve_panics(day: u8, input: &str, part: u8) -> bool {
+  let puzzle = all()[day as usize - 1];
+  let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+    let parsed = puzzle.parse(input).unwrap();
+    let _ = puzzle.solve(parsed.as_ref(), part);
+  }));
+
+  result.is_err()
+}
+
+// Days that may not finish are solved in another thread, which is left
+// behind if it takes too long.
+fn solve(day: u8, input: &str, part: u8) -> Outcome {
+  if !MAY_NOT_FINISH.contains(&day) {
+    return if solve_panics(day, input, part) { Outcome::Panicked } else { Outcome::Finished };
+  }
+
+  let (sender, receiver) = mpsc::channel();
+  let input = input.to_string();
+  thread::spawn(move || {
+    let _ = sender.send(solve_panics(day, &input, part));
+  });
+
+  match receiver.recv_timeout(GIVE_UP_AFTER) {
+    Ok(false) => Outcome::Finished,
+    Ok(true) => Outcome::Panicked,
+    Err(_) => Outcome::TimedOut,
+  }
+}
+
+#[test]
+fn parsers_never_panic() {
+  let mut rng = Rng(0x5eed_2018);
+  let mut failures = vec![];
+
+  for (index, input) in INPUTS.iter().enumerate() {
+    let day = index as u8 + 1;
+    let mut alphabet: Vec<char> = input.chars().collect();
+    alphabet.sort();
+    alphabet.dedup();
+
+    let mut cases = vec![String::new(), "\n\n".to_string()];
+    for _ in 0..CASES_PER_DAY {
+      cases.push(random_text(&mut rng, &alphabet));
+      cases.push(mutate(&mut rng, input));
+    }
+
+    if let Some(case) = cases.iter().find(|case| parse_panics(day, case)) {
+      failures.push(format!("day {} panicked on {:?}", day, case));
+    }
+  }
+
+  assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn solutions_never_panic() {
+  let mut rng = Rng(0x5017_2018);
+  let mut failures = vec![];
+
+  for (index, sample) in SAMPLES.iter().enumerate() {
+    let day = index as u8 + 1;
+    assert!(all()[index].parse(sample).is_ok(), "the sample of day {} doesn't parse", day);
+
+    let mut cases = vec![sample.to_string()];
+    cases.extend((0..SOLVED_CASES_PER_DAY).map(|_| mutate(&mut rng, sample)));
+    cases.retain(|case| parses(day, case));
+
+    'cases: for case in &cases {
+      for part in 1..=2 {
+        match solve(day, case, part) {
+          Outcome::Finished => (),
+          Outcome::TimedOut if MAY_NOT_FINISH.contains(&day) => break 'cases,
+          outcome => {
+            failures.push(format!("day {} part {}: {:?} on {:?}", day, part, outcome, case));
+            break 'cases;
+          },
+        }
+      }
+    }
+  }
+
+  assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,169 @@
+use std::any::Any;
+use std::fmt;
+use super::utils::Error;
+
+use super::{day1, day2, day3, day4, day5, day6, day7, day8, day9, day10};
+use super::{day11, day12, day13, day14, day15, day16, day17, day18, day19, day20};
+use super::{day21, day22, day23, day24, day25};
+
+#[cfg(test)]
+mod fuzz;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+  Number(i64),
+  Text(String),
+  // Day 25 only has one puzzle, the second star is given for free.
+  None,
+}
+
+impl fmt::Display for Answer {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Answer::Number(n) => write!(f, "{}", n),
+      Answer::Text(s) => write!(f, "{}", s),
+      Answer::None => write!(f, "-"),
+    }
+  }
+}
+
+macro_rules! answer_from_number {
+  ($($t:ty),*) => {
+    $(
+      impl From<$t> for Answer {
+        fn from(n: $t) -> Answer {
+          Answer::Number(n as i64)
+        }
+      }
+    )*
+  }
+}
+
+answer_from_number!(i32, u32, i64, u64, usize, i128);
+
+impl From<String> for Answer {
+  fn from(s: String) -> Answer {
+    Answer::Text(s)
+  }
+}
+
+impl From<&str> for Answer {
+  fn from(s: &str) -> Answer {
+    Answer::Text(s.to_string())
+  }
+}
+
+pub trait Solution {
+  type Input: 'static;
+
+  const DAY: u8;
+  const TITLE: &'static str;
+  // Puzzle input compiled into the binary, used when no input file is found.
+  const INPUT: Option<&'static str> = None;
+
+  fn parse(input: &str) -> Result<Self::Input, Error>;
+  fn part1(input: &Self::Input) -> Result<Answer, Error>;
+  fn part2(input: &Self::Input) -> Result<Answer, Error>;
+}
+
+// Object safe view on a Solution so all days can be kept in one registry.
+pub trait Puzzle: Sync {
+  fn day(&self) -> u8;
+  fn title(&self) -> &'static str;
+  fn embedded_input(&self) -> Option<&'static str>;
+  fn parse(&self, input: &str) -> Result<Box<dyn Any>, Error>;
+  fn solve(&self, input: &dyn Any, part: u8) -> Result<Answer, Error>;
+}
+
+impl<S> Puzzle for S where S: Solution + Sync {
+  fn day(&self) -> u8 {
+    S::DAY
+  }
+
+  fn title(&self) -> &'static str {
+    S::TITLE
+  }
+
+  fn embedded_input(&self) -> Option<&'static str> {
+    S::INPUT
+  }
+
+  fn parse(&self, input: &str) -> Result<Box<dyn Any>, Error> {
+    let parsed = S::parse(input).map_err(|err| err.in_day(S::DAY))?;
+    Ok(Box::new(parsed))
+  }
+
+  fn solve(&self, input: &dyn Any, part: u8) -> Result<Answer, Error> {
+    let input = match input.downcast_ref::<S::Input>() {
+      Some(v) => v,
+      None => Err(Error::new("Input was parsed for another day").in_day(S::DAY))?
+    };
+
+    let result = match part {
+      1 => S::part1(input),
+      2 => S::part2(input),
+      _ => Err(Error::new(&format!("There is no part {}", part))),
+    };
+
+    result.map_err(|err| err.in_day(S::DAY))
+  }
+}
+
+static SOLUTIONS: [&dyn Puzzle; 25] = [
+  &day1::Day1,
+  &day2::Day2,
+  &day3::Day3,
+  &day4::Day4,
+  &day5::Day5,
+  &day6::Day6,
+  &day7::Day7,
+  &day8::Day8,
+  &day9::Day9,
+  &day10::Day10,
+  &day11::Day11,
+  &day12::Day12,
+  &day13::Day13,
+  &day14::Day14,
+  &day15::Day15,
+  &day16::Day16,
+  &day17::Day17,
+  &day18::Day18,
+  &day19::Day19,
+  &day20::Day20,
+  &day21::Day21,
+  &day22::Day22,
+  &day23::Day23,
+  &day24::Day24,
+  &day25::Day25,
+];
+
+pub fn all() -> &'static [&'static dyn Puzzle] {
+  &SOLUTIONS
+}
+
+pub fn get(day: u8) -> Option<&'static dyn Puzzle> {
+  SOLUTIONS.iter().find(|s| s.day() == day).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn registry_contains_every_day_in_order() {
+    let days: Vec<u8> = all().iter().map(|s| s.day()).collect();
+    assert_eq!(days, (1..=25).collect::<Vec<u8>>());
+  }
+
+  #[test]
+  fn solve_rejects_input_of_another_day() {
+    let input = get(1).unwrap().parse("+1\n").unwrap();
+    assert!(get(2).unwrap().solve(input.as_ref(), 1).is_err());
+  }
+
+  #[test]
+  fn solve_rejects_unknown_part() {
+    let input = get(1).unwrap().parse("+1\n").unwrap();
+    assert!(get(1).unwrap().solve(input.as_ref(), 3).is_err());
+  }
+}
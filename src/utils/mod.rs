@@ -1,87 +1,233 @@
-use std::num::{ParseIntError, ParseFloatError};
+use std::error::Error as StdError;
 use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[allow(dead_code)]
-pub fn read_file(filename: &str) -> String {
-  let mut f = File::open(filename).expect("file not found");
-
+pub fn read_file<P: AsRef<Path>>(filename: P) -> Result<String, Error> {
+  let path = filename.as_ref().to_path_buf();
   let mut contents = String::new();
-  f.read_to_string(&mut contents)
-    .expect("something went wrong reading the file");
+  let result = File::open(&path).and_then(|mut f| f.read_to_string(&mut contents));
 
-  contents
+  match result {
+    Ok(_) => Ok(contents),
+    Err(source) => Err(Error::Io { path, source }),
+  }
 }
 
 pub fn preprocess_input(input: &str) -> Vec<&str> {
   input
-    .split("\n")
-    .filter(|v| *v != "")
+    .split('\n')
+    .filter(|v| !v.is_empty())
+    .collect()
+}
+
+// Like preprocess_input but keeps the (1 based) line numbers for error messages.
+pub fn numbered_lines(input: &str) -> impl Iterator<Item = (usize, &str)> {
+  input
+    .split('\n')
+    .enumerate()
+    .map(|(i, v)| (i + 1, v.trim_end_matches('\r')))
+    .filter(|(_, v)| !v.is_empty())
+}
+
+// Parses every non empty line with f. Errors are annotated with the
+// line number and the content of the offending line.
+pub fn parse_lines<T, F>(input: &str, mut f: F) -> Result<Vec<T>, ParseError>
+where F: FnMut(&str) -> Result<T, ParseError> {
+  numbered_lines(input)
+    .map(|(number, line)| f(line).map_err(|err| err.at_line(number, line)))
     .collect()
 }
 
+// Parses a number and keeps the offending text in the error message.
+pub fn parse_number<T>(s: &str) -> Result<T, ParseError>
+where T: FromStr, T::Err: StdError + Send + Sync + 'static {
+  s.trim().parse::<T>().map_err(|err| {
+    ParseError::new(&format!("Unable to parse number '{}'", s)).caused_by(err)
+  })
+}
+
+// Formats an error together with all its causes.
+pub fn describe(err: &dyn StdError) -> String {
+  let mut out = err.to_string();
+  let mut source = err.source();
+  while let Some(cause) = source {
+    out.push_str(": ");
+    out.push_str(&cause.to_string());
+    source = cause.source();
+  }
+
+  out
+}
+
 #[derive(Debug)]
-pub struct Error {
-  pub what: String
+pub enum Error {
+  Message(String),
+  Parse(ParseError),
+  Io { path: PathBuf, source: io::Error },
+  // Error that occurred while solving the puzzle of the given day.
+  Day { day: u8, source: Box<Error> },
 }
 
 impl Error {
   pub fn new(s: &str) -> Error {
-    let what = s.to_string();
-    Error { what }
+    Error::Message(s.to_string())
+  }
+
+  pub fn in_day(self, day: u8) -> Error {
+    match self {
+      Error::Day { .. } => self,
+      _ => Error::Day { day, source: Box::new(self) },
+    }
   }
 }
 
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.what)
+    match self {
+      Error::Message(what) => write!(f, "{}", what),
+      Error::Parse(err) => write!(f, "{}", err),
+      Error::Io { path, .. } => write!(f, "Unable to read {}", path.display()),
+      Error::Day { day, .. } => write!(f, "Day {}", day),
+    }
+  }
+}
+
+impl StdError for Error {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match self {
+      Error::Message(_) => None,
+      // Parse errors are displayed as they are, so their cause comes next.
+      Error::Parse(err) => err.source(),
+      Error::Io { source, .. } => Some(source),
+      Error::Day { source, .. } => Some(source.as_ref()),
+    }
   }
 }
 
+// Longer lines are shortened in error messages.
+const MAX_SNIPPET_LENGTH: usize = 60;
 
 #[derive(Debug)]
 pub struct ParseError {
-  pub what: String
+  pub what: String,
+  pub line: Option<usize>,
+  pub column: Option<usize>,
+  pub snippet: Option<String>,
+  source: Option<Box<dyn StdError + Send + Sync>>,
 }
 
 impl ParseError {
   pub fn new(s: &str) -> ParseError {
     let what = s.to_string();
-    ParseError { what }
+    ParseError { what, line: None, column: None, snippet: None, source: None }
+  }
+
+  pub fn caused_by<E>(mut self, source: E) -> ParseError
+  where E: StdError + Send + Sync + 'static {
+    self.source = Some(Box::new(source));
+    self
+  }
+
+  // Keeps the innermost line if the error is annotated more than once.
+  pub fn at_line(mut self, line: usize, snippet: &str) -> ParseError {
+    if self.line.is_none() {
+      self.line = Some(line);
+      self.snippet = Some(snippet.to_string());
+    }
+    self
+  }
+
+  // The (1 based) column within the line, innermost wins as well.
+  pub fn at_column(mut self, column: usize) -> ParseError {
+    if self.column.is_none() {
+      self.column = Some(column);
+    }
+    self
   }
 }
 
 impl fmt::Display for ParseError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{}", self.what)
+    match (self.line, self.column) {
+      (Some(line), Some(column)) => write!(f, "line {}, column {}: ", line, column)?,
+      (Some(line), None) => write!(f, "line {}: ", line)?,
+      (None, Some(column)) => write!(f, "column {}: ", column)?,
+      (None, None) => {},
+    }
+
+    write!(f, "{}", self.what)?;
+
+    if let Some(snippet) = &self.snippet {
+      if snippet.chars().count() > MAX_SNIPPET_LENGTH {
+        let short: String = snippet.chars().take(MAX_SNIPPET_LENGTH).collect();
+        write!(f, " in \"{}...\"", short)?;
+      } else {
+        write!(f, " in \"{}\"", snippet)?;
+      }
+    }
+
+    Ok(())
   }
 }
 
-impl std::error::Error for ParseError {
-  fn description(&self) -> &str {
-    "Error while parsing input"
+impl StdError for ParseError {
+  fn source(&self) -> Option<&(dyn StdError + 'static)> {
+    match &self.source {
+      Some(source) => Some(source.as_ref()),
+      None => None,
+    }
   }
+}
 
-  fn cause(&self) -> Option<&std::error::Error> {
-    None
+impl From<ParseError> for Error {
+  fn from(error: ParseError) -> Self {
+    Error::Parse(error)
   }
 }
 
-impl From<ParseIntError> for ParseError {
-  fn from(_error: ParseIntError) -> Self {
-    ParseError::new("Unable to parse integer")
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_error_keeps_its_message() {
+    let err = Error::from(ParseError::new("Unknown attack type"));
+    assert_eq!(describe(&err), "Unknown attack type");
   }
-}
 
-impl From<ParseFloatError> for ParseError {
-  fn from(_error: ParseFloatError) -> Self {
-    ParseError::new("Unable to parse float")
+  #[test]
+  fn describe_includes_day_line_snippet_and_cause() {
+    let err: Error = parse_lines("1\n\nx\n", parse_number::<i32>).unwrap_err().into();
+    let err = err.in_day(4);
+    assert_eq!(describe(&err), "Day 4: line 3: Unable to parse number 'x' in \"x\": invalid digit found in string");
   }
-}
 
-impl From<ParseError> for Error {
-  fn from(_error: ParseError) -> Self {
-    Error::new("Unable to parse something")
+  #[test]
+  fn innermost_line_wins() {
+    let err = ParseError::new("broken").at_line(2, "b").at_line(7, "a");
+    assert_eq!(err.to_string(), "line 2: broken in \"b\"");
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn columns_follow_the_line() {
+    let err = ParseError::new("broken").at_column(5).at_line(2, "b");
+    assert_eq!(err.to_string(), "line 2, column 5: broken in \"b\"");
+  }
+
+  #[test]
+  fn long_snippets_are_shortened() {
+    let line = "x".repeat(100);
+    let err = ParseError::new("broken").at_line(1, &line);
+    assert!(err.to_string().ends_with(&format!("\"{}...\"", "x".repeat(MAX_SNIPPET_LENGTH))));
+  }
+
+  #[test]
+  fn io_errors_name_the_file() {
+    let err = read_file("does/not/exist.txt").unwrap_err();
+    assert!(describe(&err).starts_with("Unable to read does/not/exist.txt: "));
+  }
+}
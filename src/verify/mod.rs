@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use super::utils::{parse_number, preprocess_input, Error};
+use super::input::Source;
+use super::report::{json_string, Format, Record, Status};
+
+pub const ANSWERS_FILE: &str = "answers.txt";
+
+// Expected answers of one input set, keyed by day and part. Answers are
+// stored in the same form as they are printed by `run`.
+pub type Answers = BTreeMap<(u8, u8), String>;
+
+// Answers files contain one answer per line:
+//
+//   <day> <part> <answer>
+//
+// Line breaks in an answer are written as \n, a backslash as \\.
+fn unescape(s: &str) -> String {
+  let mut out = String::with_capacity(s.len());
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    match (c, chars.clone().next()) {
+      ('\\', Some('n')) => { out.push('\n'); chars.next(); },
+      ('\\', Some('\\')) => { out.push('\\'); chars.next(); },
+      (c, _) => out.push(c),
+    }
+  }
+
+  out
+}
+
+fn parse_answer(line: &str) -> Result<((u8, u8), String), Error> {
+  let mut fields = line.splitn(3, ' ');
+  let (day, part, answer) = match (fields.next(), fields.next(), fields.next()) {
+    (Some(d), Some(p), Some(a)) => (d, p, a),
+    _ => Err(Error::new(&format!("Invalid answer: {}", line)))?
+  };
+
+  let day = parse_number::<u8>(day)?;
+  let part = parse_number::<u8>(part)?;
+  if !(1..=25).contains(&day) || !(1..=2).contains(&part) {
+    Err(Error::new(&format!("Invalid day or part: {}", line)))?
+  }
+
+  Ok(((day, part), unescape(answer)))
+}
+
+pub fn parse_answers(input: &str) -> Result<Answers, Error> {
+  preprocess_input(input)
+    .into_iter()
+    .filter(|line| !line.starts_with('#'))
+    .map(parse_answer)
+    .collect()
+}
+
+// Without an explicit answers file the one next to the puzzle inputs is used.
+pub fn answers_path(answers: &Option<PathBuf>, source: &Source) -> Result<PathBuf, Error> {
+  match (answers, source) {
+    (Some(path), _) => Ok(path.clone()),
+    (None, Source::Directory(dir)) => Ok(dir.join(ANSWERS_FILE)),
+    (None, _) => Err(Error::new("--answers is required when the input is given with --input")),
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Outcome {
+  Pass,
+  Fail(String),
+  Mismatch { expected: String, actual: String },
+  Missing,
+}
+
+impl Outcome {
+  pub fn is_failure(&self) -> bool {
+    match self {
+      Outcome::Fail(_) | Outcome::Mismatch { .. } => true,
+      Outcome::Pass | Outcome::Missing => false,
+    }
+  }
+
+  fn name(&self) -> &'static str {
+    match self {
+      Outcome::Pass => "pass",
+      Outcome::Fail(_) => "fail",
+      Outcome::Mismatch { .. } => "mismatch",
+      Outcome::Missing => "missing",
+    }
+  }
+}
+
+// Trailing whitespace is easily lost when answers are copied around,
+// so it is ignored when comparing.
+fn normalize(s: &str) -> String {
+  s.lines().map(|l| l.trim_end()).collect::<Vec<&str>>().join("\n")
+}
+
+pub fn check(record: &Record, answers: &Answers) -> Outcome {
+  if let Status::Failed(what) = &record.status {
+    return Outcome::Fail(what.clone());
+  }
+
+  let expected = match answers.get(&(record.day, record.part)) {
+    Some(v) => v,
+    None => return Outcome::Missing,
+  };
+
+  let actual = record.answer.to_string();
+  if normalize(expected) == normalize(&actual) {
+    Outcome::Pass
+  } else {
+    Outcome::Mismatch { expected: expected.clone(), actual }
+  }
+}
+
+pub fn header(format: Format) -> Option<String> {
+  match format {
+    Format::Table => Some(format!("{:>3}  {:>4}  {:<8}  Details", "Day", "Part", "Result")),
+    Format::Json => None,
+  }
+}
+
+fn single_line(s: &str) -> String {
+  s.replace('\n', "\\n")
+}
+
+pub fn format_outcome(record: &Record, outcome: &Outcome, format: Format) -> String {
+  match format {
+    Format::Table => {
+      let details = match outcome {
+        Outcome::Pass => single_line(&record.answer.to_string()),
+        Outcome::Fail(what) => what.clone(),
+        Outcome::Mismatch { expected, actual } =>
+          format!("expected {}, got {}", single_line(expected), single_line(actual)),
+        Outcome::Missing => format!("no expected answer, got {}", single_line(&record.answer.to_string())),
+      };
+      format!("{:>3}  {:>4}  {:<8}  {}", record.day, record.part, outcome.name(), details)
+    },
+    Format::Json => {
+      let (expected, actual, error) = match outcome {
+        Outcome::Pass => (Some(record.answer.to_string()), Some(record.answer.to_string()), None),
+        Outcome::Fail(what) => (None, None, Some(what.clone())),
+        Outcome::Mismatch { expected, actual } => (Some(expected.clone()), Some(actual.clone()), None),
+        Outcome::Missing => (None, Some(record.answer.to_string()), None),
+      };
+      let json = |v: Option<String>| v.map_or("null".to_string(), |s| json_string(&s));
+      format!("{{\"day\":{},\"part\":{},\"result\":\"{}\",\"expected\":{},\"actual\":{},\"error\":{}}}",
+        record.day, record.part, outcome.name(), json(expected), json(actual), json(error))
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+  use super::super::solution::Answer;
+
+  fn record(result: Result<Answer, Error>) -> Record {
+    Record::new(7, 1, result, Duration::from_millis(1))
+  }
+
+  #[test]
+  fn parses_answers_with_escapes() {
+    let answers = parse_answers("# comment\n7 1 ABC\n10 1 #..\\n.#.\n9 2 36\n").unwrap();
+    assert_eq!(answers[&(7, 1)], "ABC");
+    assert_eq!(answers[&(10, 1)], "#..\n.#.");
+    assert_eq!(answers[&(9, 2)], "36");
+  }
+
+  #[test]
+  fn rejects_invalid_answers() {
+    assert!(parse_answers("7 ABC\n").is_err());
+    assert!(parse_answers("26 1 ABC\n").is_err());
+    assert!(parse_answers("7 3 ABC\n").is_err());
+    let err = parse_answers("x7 1 ABC\n").unwrap_err();
+    assert_eq!(err.to_string(), "Unable to parse number 'x7'");
+  }
+
+  #[test]
+  fn checks_records_against_answers() {
+    let answers = parse_answers("7 1 ABC\n").unwrap();
+    assert_eq!(check(&record(Ok(Answer::from("ABC"))), &answers), Outcome::Pass);
+    assert_eq!(check(&record(Ok(Answer::from("ABD"))), &answers),
+      Outcome::Mismatch { expected: "ABC".to_string(), actual: "ABD".to_string() });
+    assert_eq!(check(&record(Err(Error::new("broken"))), &answers), Outcome::Fail("broken".to_string()));
+    assert_eq!(check(&record(Ok(Answer::from("ABC"))), &Answers::new()), Outcome::Missing);
+  }
+
+  #[test]
+  fn ignores_trailing_whitespace() {
+    let answers = parse_answers("7 1 #.. \\n.#.\n").unwrap();
+    assert_eq!(check(&record(Ok(Answer::from("#..\n.#.  "))), &answers), Outcome::Pass);
+  }
+
+  #[test]
+  fn bundled_answers_cover_every_day() {
+    let answers = parse_answers(include_str!("../../inputs/answers.txt")).unwrap();
+    assert_eq!(answers.len(), 50);
+  }
+
+  #[test]
+  fn answers_file_defaults_to_input_directory() {
+    let source = Source::Directory(PathBuf::from("mine"));
+    assert_eq!(answers_path(&None, &source).unwrap(), PathBuf::from("mine/answers.txt"));
+    assert!(answers_path(&None, &Source::Stdin).is_err());
+  }
+
+  #[test]
+  fn json_reports_expected_and_actual() {
+    let outcome = Outcome::Mismatch { expected: "1".to_string(), actual: "2".to_string() };
+    let line = format_outcome(&record(Ok(Answer::Number(2))), &outcome, Format::Json);
+    assert_eq!(line, "{\"day\":7,\"part\":1,\"result\":\"mismatch\",\"expected\":\"1\",\"actual\":\"2\",\"error\":null}");
+  }
+}